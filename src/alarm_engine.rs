@@ -0,0 +1,294 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::config::{AlarmClassConfig, AlarmDirection};
+use crate::state_store::ParamKey;
+
+/// Разделяемый движок пороговых тревог (сортировщик обновляет, HTTP читает)
+pub type SharedAlarmEngine = Arc<Mutex<AlarmEngine>>;
+
+/// Уровень тревоги параметра
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlarmLevel {
+    /// Значение в норме
+    Normal,
+    /// Превышен порог предупреждения
+    Warning,
+    /// Превышен критический порог
+    Critical,
+    /// Тревога снята (переходное состояние, далее Normal)
+    Cleared,
+}
+
+/// Событие смены состояния тревоги
+#[derive(Debug, Clone, Serialize)]
+pub struct AlarmEvent {
+    pub event: &'static str,         // Всегда "threshold_alarm"
+    pub class: String,               // Класс параметра из конфигурации
+    pub from: AlarmLevel,            // Предыдущее состояние
+    pub to: AlarmLevel,              // Новое состояние
+    #[serde(flatten)]
+    pub key: ParamKey,
+    pub value: f32,                  // Значение, вызвавшее переход
+    pub threshold: f32,              // Порог, относительно которого произошел переход
+    pub value_min: f32,              // Минимальное значение из пакета
+    pub value_max: f32,              // Максимальное значение из пакета
+    pub timestamp: DateTime<Utc>,    // Время перехода
+}
+
+/// Активная тревога для выдачи наружу
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveAlarm {
+    pub class: String,
+    #[serde(flatten)]
+    pub key: ParamKey,
+    pub level: AlarmLevel,
+    pub value: f32,
+    pub since: DateTime<Utc>,
+}
+
+/// Состояние конечного автомата одного параметра
+#[derive(Debug, Clone)]
+struct AlarmState {
+    level: AlarmLevel,                                 // Текущий уровень (Normal/Warning/Critical)
+    since: DateTime<Utc>,                              // Время входа в текущий уровень
+    pending: Option<DateTime<Utc>>,                    // Начало превышения, ожидающего min_duration
+    value: f32,                                        // Последнее значение
+}
+
+/// Измерение для оценки порогов
+#[derive(Debug, Clone, Copy)]
+pub struct Measurement {
+    pub key: ParamKey,
+    pub value: f32,
+    pub value_min: f32,
+    pub value_max: f32,
+}
+
+/// Движок пороговых тревог с гистерезисом и минимальной длительностью
+/// Переходы: normal → warning → critical → cleared (→ normal)
+pub struct AlarmEngine {
+    classes: Vec<AlarmClassConfig>,
+    states: HashMap<ParamKey, AlarmState>,
+}
+
+impl AlarmEngine {
+    /// Создает движок с классами порогов из конфигурации
+    pub fn new(classes: Vec<AlarmClassConfig>) -> Self {
+        Self {
+            classes,
+            states: HashMap::new(),
+        }
+    }
+
+    /// Создает разделяемый движок
+    pub fn shared(classes: Vec<AlarmClassConfig>) -> SharedAlarmEngine {
+        Arc::new(Mutex::new(Self::new(classes)))
+    }
+
+    /// Оценивает новое значение параметра, возвращает событие при смене состояния
+    /// Нечисловые значения (NaN, бесконечность) не меняют состояние и не сбрасывают ожидание
+    pub fn evaluate(&mut self, measurement: Measurement, now: DateTime<Utc>) -> Option<AlarmEvent> {
+        if !measurement.value.is_finite() {
+            return None;
+        }
+        let key = measurement.key;
+        let class = self.classes.iter().find(|class| class.matches(&key))?.clone();
+
+        // Для порогов "снизу" инвертируем значения и сравниваем как "сверху"
+        let sign = match class.direction {
+            AlarmDirection::High => 1.0,
+            AlarmDirection::Low => -1.0,
+        };
+        let value = measurement.value * sign;
+        let warning = class.warning * sign;
+        let critical = class.critical * sign;
+        let hysteresis = class.hysteresis.abs();
+
+        let state = self.states.entry(key).or_insert(AlarmState {
+            level: AlarmLevel::Normal,
+            since: now,
+            pending: None,
+            value: measurement.value,
+        });
+        state.value = measurement.value;
+
+        // Уровень, который диктует значение без учета гистерезиса
+        let raw_level = if value >= critical {
+            AlarmLevel::Critical
+        } else if value >= warning {
+            AlarmLevel::Warning
+        } else {
+            AlarmLevel::Normal
+        };
+
+        let (new_level, threshold) = if raw_level > state.level {
+            // Повышение уровня - только после min_duration непрерывного превышения
+            let since = *state.pending.get_or_insert(now);
+            let held = (now - since).num_milliseconds() as f64 / 1000.0;
+            if held < class.min_duration_secs {
+                return None;
+            }
+            let threshold = if raw_level == AlarmLevel::Critical { class.critical } else { class.warning };
+            (raw_level, threshold)
+        } else {
+            state.pending = None;
+            // Понижение уровня - только после выхода за порог с гистерезисом
+            match state.level {
+                AlarmLevel::Critical if value < warning - hysteresis => (AlarmLevel::Cleared, class.warning),
+                AlarmLevel::Critical if value < critical - hysteresis => (AlarmLevel::Warning, class.critical),
+                AlarmLevel::Warning if value < warning - hysteresis => (AlarmLevel::Cleared, class.warning),
+                _ => return None,
+            }
+        };
+
+        let from = state.level;
+        state.level = if new_level == AlarmLevel::Cleared { AlarmLevel::Normal } else { new_level };
+        state.since = now;
+        state.pending = None;
+
+        Some(AlarmEvent {
+            event: "threshold_alarm",
+            class: class.name,
+            from,
+            to: new_level,
+            key,
+            value: measurement.value,
+            threshold,
+            value_min: measurement.value_min,
+            value_max: measurement.value_max,
+            timestamp: now,
+        })
+    }
+
+    /// Возвращает активные тревоги (уровень warning или critical)
+    pub fn active_alarms(&self) -> Vec<ActiveAlarm> {
+        let mut alarms: Vec<ActiveAlarm> = self.states.iter()
+            .filter(|(_, state)| state.level != AlarmLevel::Normal)
+            .filter_map(|(key, state)| {
+                let class = self.classes.iter().find(|class| class.matches(key))?;
+                Some(ActiveAlarm {
+                    class: class.name.clone(),
+                    key: *key,
+                    level: state.level,
+                    value: state.value,
+                    since: state.since,
+                })
+            })
+            .collect();
+        alarms.sort_by_key(|alarm| alarm.key);
+        alarms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use AlarmLevel::{Cleared, Critical, Normal, Warning};
+
+    fn class(direction: AlarmDirection, warning: f32, critical: f32, min_duration_secs: f64) -> AlarmClassConfig {
+        AlarmClassConfig {
+            name: String::from("fpga_temp"),
+            prm_ids: vec![10],
+            src_ids: None,
+            dev_ids: None,
+            warning,
+            critical,
+            hysteresis: 2.0,
+            min_duration_secs,
+            direction,
+        }
+    }
+
+    /// Последовательность значений через секунду; возвращает переходы (from, to)
+    fn run(engine: &mut AlarmEngine, values: &[f32]) -> Vec<Option<(AlarmLevel, AlarmLevel)>> {
+        let start = Utc::now();
+        values.iter()
+            .enumerate()
+            .map(|(index, &value)| {
+                let measurement = Measurement {
+                    key: ParamKey { module_addr: 9, module_addr_bm: 1, module_addr_mcu: 1, dev_id: 1, src_id: 2, prm_id: 10 },
+                    value,
+                    value_min: value,
+                    value_max: value,
+                };
+                engine.evaluate(measurement, start + Duration::seconds(index as i64))
+                    .map(|event| (event.from, event.to))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn escalation_after_min_duration() {
+        let mut engine = AlarmEngine::new(vec![class(AlarmDirection::High, 80.0, 90.0, 3.0)]);
+        let events = run(&mut engine, &[85.0, 85.0, 85.0, 85.0, 95.0, 95.0, 95.0, 95.0]);
+        assert_eq!(events, [
+            None, None, None, Some((Normal, Warning)),
+            None, None, None, Some((Warning, Critical)),
+        ]);
+        assert_eq!(engine.active_alarms()[0].level, Critical);
+    }
+
+    #[test]
+    fn pending_resets_when_value_dips() {
+        let mut engine = AlarmEngine::new(vec![class(AlarmDirection::High, 80.0, 90.0, 2.0)]);
+        let events = run(&mut engine, &[85.0, 85.0, 70.0, 85.0, 85.0, 85.0]);
+        assert_eq!(events, [None, None, None, None, None, Some((Normal, Warning))]);
+    }
+
+    #[test]
+    fn hysteresis_edges() {
+        let mut engine = AlarmEngine::new(vec![class(AlarmDirection::High, 80.0, 90.0, 0.0)]);
+        // Critical → warning ниже 90 - 2, critical → cleared ниже 80 - 2
+        let events = run(&mut engine, &[95.0, 88.5, 87.9, 95.0, 78.5, 95.0, 77.9, 79.0, 78.1, 77.9]);
+        assert_eq!(events, [
+            Some((Normal, Critical)),
+            None,
+            Some((Critical, Warning)),
+            Some((Warning, Critical)),
+            Some((Critical, Warning)),
+            Some((Warning, Critical)),
+            Some((Critical, Cleared)),
+            None,
+            None,
+            None,
+        ]);
+        assert!(engine.active_alarms().is_empty());
+
+        let events = run(&mut engine, &[82.0, 78.1, 77.9]);
+        assert_eq!(events, [Some((Normal, Warning)), None, Some((Warning, Cleared))]);
+    }
+
+    #[test]
+    fn low_direction() {
+        let mut engine = AlarmEngine::new(vec![class(AlarmDirection::Low, 10.0, 5.0, 0.0)]);
+        let events = run(&mut engine, &[12.0, 8.0, 4.0, 6.5, 7.5, 11.5, 12.5]);
+        assert_eq!(events, [
+            None,
+            Some((Normal, Warning)),
+            Some((Warning, Critical)),
+            None,
+            Some((Critical, Warning)),
+            None,
+            Some((Warning, Cleared)),
+        ]);
+    }
+
+    #[test]
+    fn nan_values_ignored() {
+        let mut engine = AlarmEngine::new(vec![class(AlarmDirection::High, 80.0, 90.0, 2.0)]);
+        // NaN не сбрасывает ожидание повышения
+        let events = run(&mut engine, &[85.0, f32::NAN, 85.0]);
+        assert_eq!(events, [None, None, Some((Normal, Warning))]);
+
+        // И не снимает активную тревогу
+        let events = run(&mut engine, &[f32::NAN, f32::INFINITY]);
+        assert_eq!(events, [None, None]);
+        assert_eq!(engine.active_alarms()[0].level, Warning);
+        assert_eq!(engine.active_alarms()[0].value, 85.0);
+    }
+}
@@ -0,0 +1,143 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+
+/// Конфигурация симулятора, загружаемая из TOML-файла (--config)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimConfig {
+    pub rate_hz: f64,                    // Циклов опроса модулей в секунду
+    pub seed: u64,                       // Начальное значение генератора шума
+    pub can_id: u32,                     // Идентификатор CAN-кадров (режим VCAN)
+    pub alarm_temperature: f32,          // Температура, выше которой выставляется аварийный бит 0
+    pub modules: Vec<ModuleConfig>,      // Эмулируемые модули
+    pub scenario: Vec<ScenarioStep>,     // Сценарий событий
+}
+
+impl Default for SimConfig {
+    /// Два BM-модуля по три FPGA без сценария
+    fn default() -> Self {
+        Self {
+            rate_hz: 10.0,
+            seed: 1,
+            can_id: 0x100,
+            alarm_temperature: 85.0,
+            modules: vec![
+                ModuleConfig { bm: 0, ..ModuleConfig::default() },
+                ModuleConfig { bm: 1, ..ModuleConfig::default() },
+            ],
+            scenario: Vec::new(),
+        }
+    }
+}
+
+impl SimConfig {
+    /// Загружает конфигурацию из файла
+    pub fn load(path: &str) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .context(format!("Failed to read simulator config: {}", path))?;
+        let config: Self = toml::from_str(&text)
+            .context(format!("Failed to parse simulator config: {}", path))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Проверяет диапазоны адресов и параметров
+    pub fn validate(&self) -> Result<()> {
+        if !self.rate_hz.is_finite() || self.rate_hz <= 0.0 {
+            return Err(anyhow::anyhow!("rate_hz must be positive"));
+        }
+        if self.modules.is_empty() {
+            return Err(anyhow::anyhow!("At least one module is required"));
+        }
+        for module in &self.modules {
+            if module.bm > 15 || module.mcu > 7 {
+                return Err(anyhow::anyhow!(
+                    "Module BM{}.MCU{}: bm must be 0..=15, mcu 0..=7", module.bm, module.mcu
+                ));
+            }
+            if let Some(dev_id) = module.fpgas.iter().find(|&&dev_id| dev_id == 0 || dev_id > 6) {
+                return Err(anyhow::anyhow!("Module BM{}: FPGA dev_id {} must be 1..=6", module.bm, dev_id));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Эмулируемый BM-модуль с FPGA
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModuleConfig {
+    pub bm: u8,                  // Номер BM (0-15)
+    pub mcu: u8,                 // Адрес MCU (1 или 2 для пакетов температуры)
+    pub fpgas: Vec<u8>,          // Идентификаторы FPGA (dev_id 1-6)
+    pub temperature: f32,        // Базовая температура FPGA, °C
+    pub power: f32,              // Базовое энергопотребление модуля, Вт
+}
+
+impl Default for ModuleConfig {
+    fn default() -> Self {
+        Self {
+            bm: 0,
+            mcu: 1,
+            fpgas: vec![1, 2, 3],
+            temperature: 45.0,
+            power: 120.0,
+        }
+    }
+}
+
+/// Шаг сценария: событие, действующее с at_secs в течение duration_secs
+/// Незаданные bm/dev_id означают все модули/FPGA
+/// (deny_unknown_fields несовместим с flatten, поэтому лишние поля не проверяются)
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScenarioStep {
+    pub at_secs: f64,            // Начало события от старта симуляции
+    pub duration_secs: f64,      // Длительность события
+    pub bm: Option<u8>,          // Модуль
+    pub dev_id: Option<u8>,      // FPGA модуля
+    #[serde(flatten)]
+    pub action: ScenarioAction,  // Событие
+}
+
+impl ScenarioStep {
+    /// Подходит ли шаг под модуль и FPGA
+    pub fn applies_to(&self, bm: u8, dev_id: Option<u8>) -> bool {
+        self.bm.is_none_or(|step_bm| step_bm == bm)
+            && match (self.dev_id, dev_id) {
+                (Some(step_dev), Some(dev_id)) => step_dev == dev_id,
+                _ => true,
+            }
+    }
+
+    /// Время от начала события, если оно уже началось
+    pub fn elapsed(&self, now_secs: f64) -> Option<f64> {
+        (now_secs >= self.at_secs).then_some(now_secs - self.at_secs)
+    }
+
+    /// Активно ли событие в момент времени
+    pub fn is_active(&self, now_secs: f64) -> bool {
+        self.elapsed(now_secs).is_some_and(|elapsed| elapsed < self.duration_secs)
+    }
+}
+
+/// Событие сценария
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ScenarioAction {
+    /// Рост температуры со скоростью rate °C/с, после окончания - остывание с той же скоростью
+    Overheat { rate: f32 },
+    /// Модуль перестает отправлять пакеты
+    Dropout,
+    /// Кадры отправляются с испорченным CRC с вероятностью probability
+    CrcBurst {
+        #[serde(default = "full_probability")]
+        probability: f64,
+    },
+    /// Перезагрузка модуля: молчание на время события, затем время работы сбрасывается
+    Reboot,
+}
+
+fn full_probability() -> f64 {
+    1.0
+}
@@ -0,0 +1,127 @@
+use anyhow::Result;
+
+use crate::include::{byte_stuffing, crc, linear11};
+
+/// Байт-разделитель кадров
+pub const FRAME_DELIMITER: u8 = 0xC0;
+
+/// Тип пакета с одним значением параметра (prm, prm_max, prm_min)
+pub const PACKAGE_TYPE_VALUES: u16 = 0x8000;
+
+/// Идентификатор модуля BM в поле addr
+pub const MODULE_ID_BM: u8 = 2;
+
+/// Поля заголовка пакета (стандартная раскладка)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameHeader {
+    pub bm: u8,          // Номер BM
+    pub mcu: u8,         // Адрес MCU
+    pub module_id: u8,   // Идентификатор модуля
+    pub dev_id: u8,      // Идентификатор устройства
+    pub pwr_line: u8,    // Линия питания
+    pub src_id: u8,      // Идентификатор источника
+    pub rtr: bool,       // Запрос значения (RTR)
+    pub prm_id: u16,     // Идентификатор параметра
+    pub alarms: u8,      // Аварийные биты
+    pub prm_type: u8,    // Тип параметра
+}
+
+impl FrameHeader {
+    /// Слова заголовка: addr, package_type, src, data_type
+    fn words(&self) -> [u16; 4] {
+        let addr = ((self.module_id as u16 & 0x0F) << 7)
+            | ((self.bm as u16 & 0x0F) << 3)
+            | (self.mcu as u16 & 0x07);
+        let src = ((self.rtr as u16) << 15)
+            | ((self.src_id as u16 & 0x1F) << 11)
+            | ((self.pwr_line as u16 & 0x0F) << 7)
+            | (self.dev_id as u16 & 0x7F);
+        let data_type = ((self.prm_type as u16 & 0x03) << 14)
+            | ((self.alarms as u16 & 0x0F) << 10)
+            | (self.prm_id & 0x03FF);
+        [addr, PACKAGE_TYPE_VALUES, src, data_type]
+    }
+
+    /// Разбирает заголовок из первых 8 байт пакета
+    fn parse(body: &[u8]) -> Option<Self> {
+        if body.len() < 8 {
+            return None;
+        }
+        let word = |offset: usize| ((body[offset + 1] as u16) << 8) | body[offset] as u16;
+        let (addr, src, data_type) = (word(0), word(4), word(6));
+        Some(Self {
+            bm: ((addr >> 3) & 0x0F) as u8,
+            mcu: (addr & 0x07) as u8,
+            module_id: ((addr >> 7) & 0x0F) as u8,
+            dev_id: (src & 0x7F) as u8,
+            pwr_line: ((src >> 7) & 0x0F) as u8,
+            src_id: ((src >> 11) & 0x1F) as u8,
+            rtr: src & 0x8000 != 0,
+            prm_id: data_type & 0x03FF,
+            alarms: ((data_type >> 10) & 0x0F) as u8,
+            prm_type: (data_type >> 14) as u8,
+        })
+    }
+}
+
+/// Формирует кадр значения параметра: заголовок, значения в Linear11, CRC,
+/// байт-стаффинг и разделитель. `corrupt_crc` портит CRC (сценарий crc_burst)
+pub fn encode_values(header: &FrameHeader, value: f32, value_max: f32, value_min: f32, corrupt_crc: bool) -> Result<Vec<u8>> {
+    let mut body = Vec::with_capacity(16);
+    for word in header.words() {
+        body.extend_from_slice(&word.to_le_bytes());
+    }
+    for value in [value, value_max, value_min] {
+        body.extend_from_slice(&linear11::to_linear11(value)?.to_le_bytes());
+    }
+
+    let mut crc = crc::calculate_crc16(&body);
+    if corrupt_crc {
+        crc ^= 0x5A5A;
+    }
+    body.extend_from_slice(&crc.to_be_bytes());
+
+    byte_stuffing::request_byte_stuffing(&mut body);
+    body.push(FRAME_DELIMITER);
+    Ok(body)
+}
+
+/// Разбирает входящий кадр (без разделителя) и возвращает заголовок,
+/// если экранирование и CRC корректны
+pub fn decode_request(frame: &[u8]) -> Option<FrameHeader> {
+    if byte_stuffing::has_invalid_escape(frame) {
+        return None;
+    }
+    let body = byte_stuffing::byte_stuffing(frame);
+    if body.len() < 10 {
+        return None;
+    }
+    let (data, crc) = body.split_at(body.len() - 2);
+    if !crc::crc16_validate(data, ((crc[0] as u16) << 8) | crc[1] as u16) {
+        return None;
+    }
+    FrameHeader::parse(data)
+}
+
+/// Собирает кадры из потока байтов, разделенных 0xC0
+#[derive(Default)]
+pub struct FrameAssembler {
+    current: Vec<u8>,    // Текущий собираемый кадр
+}
+
+impl FrameAssembler {
+    /// Добавляет байты потока и возвращает завершенные кадры
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Vec<u8>> {
+        let mut frames = Vec::new();
+        for &byte in bytes {
+            if byte == FRAME_DELIMITER {
+                if !self.current.is_empty() {
+                    frames.push(std::mem::take(&mut self.current));
+                }
+            } else {
+                self.current.push(byte);
+            }
+        }
+        frames
+    }
+}
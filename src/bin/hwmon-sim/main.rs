@@ -0,0 +1,185 @@
+use anyhow::{Context, Result};
+use clap::{Arg, Command};
+use std::process;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+mod config;
+mod frame;
+mod model;
+mod output;
+
+// Общие модули основного приложения (только используемые симулятором)
+#[path = "../../include"]
+mod include {
+    pub mod byte_stuffing;
+    #[allow(dead_code)] // Параметрический CRC нужен только основному приложению
+    pub mod crc;
+    #[allow(dead_code)] // Декодер Linear11 нужен только основному приложению
+    pub mod linear11;
+}
+
+#[path = "../../logging.rs"]
+mod logging;
+
+use config::SimConfig;
+use frame::FrameAssembler;
+use logging::{LogFormat, RateLimiter};
+use model::Simulator;
+use output::Output;
+
+/// Длительность симуляции в режиме FILE по умолчанию, с
+const DEFAULT_FILE_DURATION: f64 = 60.0;
+
+/// Минимальный интервал между предупреждениями о некорректных запросах
+const REQUEST_WARN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Симулятор BM/FPGA модулей: генерирует трафик пакетов температуры,
+/// системных пакетов и пакетов энергопотребления, отвечает на RTR-запросы
+/// и проигрывает сценарий событий (перегрев, пропадание, ошибки CRC, перезагрузка)
+fn main() -> Result<()> {
+    let matches = Command::new("HWMon simulator")
+        .about("Emulates BM/FPGA modules for HWMon testing")
+        .arg(
+            Arg::new("output")
+                .required(true)
+                .index(1)
+                .help("Output: FILE <path> / TCP <addr> / PTY / VCAN <interface>")
+        )
+        .arg(
+            Arg::new("target")
+                .required(false)
+                .index(2)
+                .help("File path, TCP listen address or CAN interface")
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .value_name("FILE")
+                .help("TOML simulator configuration (modules, rate, scenario)")
+        )
+        .arg(
+            Arg::new("duration")
+                .long("duration")
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(f64))
+                .help("Simulated time to run (FILE default: 60, otherwise until Ctrl+C)")
+        )
+        .arg(
+            Arg::new("log-level")
+                .long("log-level")
+                .value_name("FILTER")
+                .help("Log filter, e.g. info or debug (overrides HWMON_LOG)")
+        )
+        .arg(
+            Arg::new("log-format")
+                .long("log-format")
+                .value_name("FORMAT")
+                .default_value("text")
+                .help("Log output format: text / json")
+        )
+        .arg(
+            Arg::new("log-file")
+                .long("log-file")
+                .value_name("FILE")
+                .help("Append logs to FILE instead of stdout")
+        )
+        .get_matches();
+
+    let log_filter = matches.get_one::<String>("log-level").map(String::as_str);
+    let log_format = LogFormat::parse(
+        matches.get_one::<String>("log-format").map(String::as_str).unwrap_or("text"),
+    )?;
+    match matches.get_one::<String>("log-file") {
+        Some(path) => logging::init_to_file(log_filter, log_format, Some(path.as_str()))?,
+        None => logging::init(log_filter, log_format)?,
+    }
+
+    let config = match matches.get_one::<String>("config") {
+        Some(path) => SimConfig::load(path)?,
+        None => SimConfig::default(),
+    };
+
+    let operation = matches.get_one::<String>("output")
+        .context("Output argument is required")?;
+    let target = matches.get_one::<String>("target");
+    let mut output = match operation.as_str() {
+        "FILE" => Output::file(target.context("Path required for FILE output")?)?,
+        "TCP" => Output::tcp(target.map_or("127.0.0.1:7000", String::as_str))?,
+        "PTY" => Output::pty()?,
+        "VCAN" => Output::vcan(target.map_or("vcan0", String::as_str), config.can_id)?,
+        _ => {
+            error!("Unknown output: {}", operation);
+            eprintln!("Use: FILE <path> / TCP <addr> / PTY / VCAN <interface>");
+            process::exit(1);
+        }
+    };
+
+    let duration = matches.get_one::<f64>("duration").copied()
+        .or((!output.is_realtime()).then_some(DEFAULT_FILE_DURATION));
+    run(config, &mut output, duration)
+}
+
+/// Основной цикл: кадры каждого цикла опроса и ответы на RTR-запросы
+/// В реальном времени цикл выдерживает rate_hz, в файл пишет без пауз
+fn run(config: SimConfig, output: &mut Output, duration: Option<f64>) -> Result<()> {
+    let period = 1.0 / config.rate_hz;
+    info!(
+        modules = config.modules.len(),
+        rate_hz = config.rate_hz,
+        scenario_steps = config.scenario.len(),
+        "Simulation started"
+    );
+
+    let mut simulator = Simulator::new(config);
+    let mut assembler = FrameAssembler::default();
+    let started = Instant::now();
+    let mut dropped = 0u64;
+    let mut request_warn_limiter = RateLimiter::new(REQUEST_WARN_INTERVAL);
+
+    for tick in 0u64.. {
+        let now = tick as f64 * period;
+        if duration.is_some_and(|duration| now >= duration) {
+            break;
+        }
+
+        if output.is_realtime() {
+            let deadline = started + Duration::from_secs_f64(now);
+            if let Some(wait) = deadline.checked_duration_since(Instant::now()) {
+                std::thread::sleep(wait);
+            }
+        }
+
+        // Ответы на RTR-запросы, пришедшие с прошлого цикла
+        for request in assembler.push(&output.read()?) {
+            match frame::decode_request(&request) {
+                Some(header) => {
+                    if let Some(reply) = simulator.answer(&header, now)? {
+                        output.write(&reply)?;
+                    }
+                }
+                None => {
+                    if let Some(suppressed) = request_warn_limiter.check() {
+                        warn!(frame = %hex::encode(&request), suppressed, "Invalid request frame ignored");
+                    }
+                }
+            }
+        }
+
+        let frames = simulator.tick(now)?;
+        if !frames.is_empty() && !output.write(&frames)? {
+            dropped += 1;
+        }
+    }
+
+    let stats = simulator.stats();
+    info!(
+        frames = stats.frames,
+        corrupted = stats.corrupted,
+        rtr_replies = stats.rtr_replies,
+        silent_skips = stats.silent_skips,
+        dropped_cycles = dropped,
+        "Simulation finished"
+    );
+    Ok(())
+}
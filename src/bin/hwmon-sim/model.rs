@@ -0,0 +1,234 @@
+use anyhow::Result;
+use tracing::{debug, info};
+
+use crate::config::{ModuleConfig, ScenarioAction, SimConfig};
+use crate::frame::{self, FrameHeader, MODULE_ID_BM};
+
+/// Параметр температуры FPGA (маршрут TMonitor)
+pub const PRM_TEMPERATURE: u16 = 10;
+/// Системный параметр: время работы модуля в секундах (маршрут SMonitor)
+pub const PRM_SYSTEM: u16 = 20;
+/// Энергопотребление модуля (маршрут PUMonitor)
+pub const PRM_POWER: u16 = 30;
+
+/// Источник пакетов FPGA
+const SRC_FPGA: u8 = 2;
+/// Источник пакетов MCU
+const SRC_MCU: u8 = 1;
+/// dev_id MCU модуля (системные пакеты и пакеты энергопотребления)
+const DEV_MCU: u8 = 7;
+
+/// Генератор шума xorshift64 (воспроизводимый по seed)
+struct Noise(u64);
+
+impl Noise {
+    fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    /// Случайное число в диапазоне [-1, 1)
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        ((self.0 >> 40) as f32 / (1u64 << 23) as f32) - 1.0
+    }
+
+    /// Случайное число в диапазоне [0, 1)
+    fn unit(&mut self) -> f64 {
+        (self.next() as f64 + 1.0) / 2.0
+    }
+}
+
+/// Счетчики симулятора
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SimStats {
+    pub frames: u64,          // Отправлено кадров
+    pub corrupted: u64,       // Кадров с испорченным CRC
+    pub rtr_replies: u64,     // Ответов на RTR-запросы
+    pub silent_skips: u64,    // Кадров, не отправленных из-за молчания модуля
+}
+
+/// Модель BM-модулей: значения параметров и события сценария
+pub struct Simulator {
+    config: SimConfig,       // Конфигурация и сценарий
+    noise: Noise,            // Шум измерений
+    silent: Vec<bool>,       // Молчит ли модуль (по индексу в config.modules)
+    stats: SimStats,         // Счетчики
+}
+
+impl Simulator {
+    /// Создает модель по конфигурации
+    pub fn new(config: SimConfig) -> Self {
+        Self {
+            noise: Noise::new(config.seed),
+            silent: vec![false; config.modules.len()],
+            config,
+            stats: SimStats::default(),
+        }
+    }
+
+    /// Возвращает счетчики
+    pub fn stats(&self) -> SimStats {
+        self.stats
+    }
+
+    /// Формирует кадры одного цикла опроса всех модулей
+    pub fn tick(&mut self, now: f64) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        for index in 0..self.config.modules.len() {
+            let module = self.config.modules[index].clone();
+            if self.update_silence(index, &module, now) {
+                self.stats.silent_skips += module.fpgas.len() as u64 + 2;
+                continue;
+            }
+
+            for &dev_id in &module.fpgas {
+                let header = Self::header(&module, dev_id, SRC_FPGA, PRM_TEMPERATURE);
+                out.extend(self.encode(&module, header, now)?);
+            }
+            for prm_id in [PRM_SYSTEM, PRM_POWER] {
+                let header = Self::header(&module, DEV_MCU, SRC_MCU, prm_id);
+                out.extend(self.encode(&module, header, now)?);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Отвечает на RTR-запрос текущим значением параметра
+    /// Запросы к неизвестным или молчащим модулям остаются без ответа
+    pub fn answer(&mut self, request: &FrameHeader, now: f64) -> Result<Option<Vec<u8>>> {
+        if !request.rtr {
+            return Ok(None);
+        }
+        let Some(index) = self.config.modules.iter()
+            .position(|module| module.bm == request.bm && module.mcu == request.mcu)
+        else {
+            debug!(bm = request.bm, mcu = request.mcu, "RTR request for unknown module");
+            return Ok(None);
+        };
+        let module = self.config.modules[index].clone();
+        if self.silent[index] {
+            return Ok(None);
+        }
+
+        let known = match request.prm_id {
+            PRM_TEMPERATURE => module.fpgas.contains(&request.dev_id),
+            PRM_SYSTEM | PRM_POWER => request.dev_id == DEV_MCU,
+            _ => false,
+        };
+        if !known {
+            debug!(bm = request.bm, dev_id = request.dev_id, prm_id = request.prm_id, "RTR request for unknown parameter");
+            return Ok(None);
+        }
+
+        let header = FrameHeader { rtr: false, ..Self::header(&module, request.dev_id, request.src_id, request.prm_id) };
+        let reply = self.encode(&module, header, now)?;
+        self.stats.rtr_replies += 1;
+        Ok(Some(reply))
+    }
+
+    /// Заголовок пакета модуля
+    fn header(module: &ModuleConfig, dev_id: u8, src_id: u8, prm_id: u16) -> FrameHeader {
+        FrameHeader {
+            bm: module.bm,
+            mcu: module.mcu,
+            module_id: MODULE_ID_BM,
+            dev_id,
+            pwr_line: 0,
+            src_id,
+            rtr: false,
+            prm_id,
+            alarms: 0,
+            prm_type: 0,
+        }
+    }
+
+    /// Вычисляет значение параметра и кодирует кадр
+    fn encode(&mut self, module: &ModuleConfig, mut header: FrameHeader, now: f64) -> Result<Vec<u8>> {
+        let (value, spread) = match header.prm_id {
+            PRM_TEMPERATURE => {
+                let value = module.temperature
+                    + header.dev_id as f32 * 1.5
+                    + self.overheat(module.bm, Some(header.dev_id), now)
+                    + self.noise.next() * 0.25;
+                if value > self.config.alarm_temperature {
+                    header.alarms |= 0x01;
+                }
+                (value, 0.5)
+            }
+            PRM_SYSTEM => (self.uptime(module.bm, now) as f32, 0.0),
+            _ => {
+                // Энергопотребление растет вместе с перегревом FPGA
+                let heat: f32 = module.fpgas.iter()
+                    .map(|&dev_id| self.overheat(module.bm, Some(dev_id), now))
+                    .sum();
+                (module.power + heat * 2.0 + self.noise.next() * 1.5, 2.0)
+            }
+        };
+        let value_max = value + self.noise.next().abs() * spread;
+        let value_min = value - self.noise.next().abs() * spread;
+
+        let corrupt = self.crc_corruption(module.bm, now);
+        if corrupt {
+            self.stats.corrupted += 1;
+        }
+        self.stats.frames += 1;
+        frame::encode_values(&header, value, value_max, value_min, corrupt)
+    }
+
+    /// Обновляет и возвращает признак молчания модуля (dropout или reboot)
+    fn update_silence(&mut self, index: usize, module: &ModuleConfig, now: f64) -> bool {
+        let silent = self.config.scenario.iter().any(|step| {
+            matches!(step.action, ScenarioAction::Dropout | ScenarioAction::Reboot)
+                && step.applies_to(module.bm, None)
+                && step.is_active(now)
+        });
+        if silent != self.silent[index] {
+            if silent {
+                info!(bm = module.bm, mcu = module.mcu, at_secs = format!("{:.1}", now), "Module went silent");
+            } else {
+                info!(bm = module.bm, mcu = module.mcu, at_secs = format!("{:.1}", now), "Module is back");
+            }
+            self.silent[index] = silent;
+        }
+        silent
+    }
+
+    /// Прирост температуры от событий overheat: рост во время события, затем остывание
+    fn overheat(&self, bm: u8, dev_id: Option<u8>, now: f64) -> f32 {
+        self.config.scenario.iter()
+            .filter(|step| step.applies_to(bm, dev_id))
+            .filter_map(|step| match step.action {
+                ScenarioAction::Overheat { rate } => step.elapsed(now).map(|elapsed| {
+                    let heating = elapsed.min(step.duration_secs);
+                    let cooling = (elapsed - step.duration_secs).max(0.0);
+                    (rate as f64 * (heating - cooling)).max(0.0) as f32
+                }),
+                _ => None,
+            })
+            .sum()
+    }
+
+    /// Время работы модуля: от старта симуляции или окончания последней перезагрузки
+    fn uptime(&self, bm: u8, now: f64) -> f64 {
+        let boot = self.config.scenario.iter()
+            .filter(|step| matches!(step.action, ScenarioAction::Reboot) && step.applies_to(bm, None))
+            .map(|step| step.at_secs + step.duration_secs)
+            .filter(|&end| end <= now)
+            .fold(0.0, f64::max);
+        now - boot
+    }
+
+    /// Нужно ли испортить CRC очередного кадра модуля
+    fn crc_corruption(&mut self, bm: u8, now: f64) -> bool {
+        let probability = self.config.scenario.iter()
+            .filter(|step| step.applies_to(bm, None) && step.is_active(now))
+            .filter_map(|step| match step.action {
+                ScenarioAction::CrcBurst { probability } => Some(probability),
+                _ => None,
+            })
+            .fold(0.0, f64::max);
+        probability > 0.0 && self.noise.unit() < probability
+    }
+}
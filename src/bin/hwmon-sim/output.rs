@@ -0,0 +1,331 @@
+use anyhow::{Context, Result};
+use std::ffi::{CStr, CString};
+use std::fs::File;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Таймаут чтения запросов из TCP-соединения
+const TCP_READ_TIMEOUT: Duration = Duration::from_millis(1);
+
+/// Максимальная длина данных классического CAN-кадра
+const CAN_DATA_LEN: usize = 8;
+
+/// Канал вывода симулятора
+/// Поток кадров (с разделителями 0xC0) пишется как есть; в режиме VCAN поток
+/// режется на CAN-кадры по 8 байт с идентификатором can_id
+pub enum Output {
+    /// Файл дампа (формат режима DUMP)
+    File(File),
+    /// TCP-сервер: поток отправляется подключившемуся клиенту
+    Tcp {
+        listener: TcpListener,
+        stream: Option<TcpStream>,
+    },
+    /// Псевдотерминал: клиент открывает подчиненную сторону как последовательный порт
+    /// Недописанный остаток кадров хранится в pending, чтобы в поток не попадали обрывки кадров
+    Pty {
+        master: File,
+        _slave: OwnedFd,
+        pending: Vec<u8>,
+    },
+    /// Виртуальная CAN-шина (SocketCAN)
+    Vcan {
+        socket: File,
+        can_id: u32,
+    },
+}
+
+impl Output {
+    /// Открывает файл дампа (перезаписывает существующий)
+    pub fn file(path: &str) -> Result<Self> {
+        let file = File::create(path).context(format!("Failed to create dump file: {}", path))?;
+        info!(path, "Writing frames to file");
+        Ok(Output::File(file))
+    }
+
+    /// Начинает принимать TCP-подключения по адресу
+    pub fn tcp(addr: &str) -> Result<Self> {
+        let listener = TcpListener::bind(addr).context(format!("Failed to listen on {}", addr))?;
+        listener.set_nonblocking(true)?;
+        info!(addr, "Waiting for TCP client");
+        Ok(Output::Tcp { listener, stream: None })
+    }
+
+    /// Создает псевдотерминал и выводит путь его подчиненной стороны
+    pub fn pty() -> Result<Self> {
+        let (mut master, mut slave) = (-1, -1);
+        // SAFETY: openpty заполняет два дескриптора, остальные аргументы могут быть NULL
+        let result = unsafe {
+            libc::openpty(&mut master, &mut slave, std::ptr::null_mut(), std::ptr::null(), std::ptr::null())
+        };
+        if result != 0 {
+            return Err(std::io::Error::last_os_error()).context("Failed to open pty");
+        }
+        // SAFETY: дескрипторы только что открыты и принадлежат нам
+        let (master, slave) = unsafe { (File::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+
+        // Сырой режим без эха и преобразования переводов строк
+        // SAFETY: termios заполняется tcgetattr перед использованием
+        unsafe {
+            let mut termios: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(slave.as_raw_fd(), &mut termios) == 0 {
+                libc::cfmakeraw(&mut termios);
+                libc::tcsetattr(slave.as_raw_fd(), libc::TCSANOW, &termios);
+            }
+        }
+        set_nonblocking(master.as_raw_fd())?;
+
+        let mut name = [0 as libc::c_char; 128];
+        // SAFETY: буфер достаточной длины, ptsname_r завершает строку нулем
+        if unsafe { libc::ptsname_r(master.as_raw_fd(), name.as_mut_ptr(), name.len()) } != 0 {
+            return Err(std::io::Error::last_os_error()).context("Failed to get pty name");
+        }
+        // SAFETY: ptsname_r записал строку с завершающим нулем
+        let path = unsafe { CStr::from_ptr(name.as_ptr()) }.to_string_lossy().into_owned();
+        info!(path = %path, "Pty created, read it with: hwmon UART --port {}", path);
+
+        Ok(Output::Pty { master, _slave: slave, pending: Vec::new() })
+    }
+
+    /// Открывает raw-сокет SocketCAN на интерфейсе (например vcan0)
+    pub fn vcan(interface: &str, can_id: u32) -> Result<Self> {
+        let name = CString::new(interface).context("Invalid CAN interface name")?;
+        // SAFETY: вызовы сокетного API с проверкой результатов
+        let socket = unsafe {
+            let fd = libc::socket(libc::PF_CAN, libc::SOCK_RAW | libc::SOCK_NONBLOCK, libc::CAN_RAW);
+            if fd < 0 {
+                return Err(std::io::Error::last_os_error()).context("Failed to open CAN socket");
+            }
+            let socket = File::from_raw_fd(fd);
+
+            let ifindex = libc::if_nametoindex(name.as_ptr());
+            if ifindex == 0 {
+                return Err(std::io::Error::last_os_error())
+                    .context(format!("CAN interface not found: {}", interface));
+            }
+            let mut addr: libc::sockaddr_can = std::mem::zeroed();
+            addr.can_family = libc::AF_CAN as libc::sa_family_t;
+            addr.can_ifindex = ifindex as libc::c_int;
+            let result = libc::bind(
+                fd,
+                &addr as *const libc::sockaddr_can as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_can>() as libc::socklen_t,
+            );
+            if result != 0 {
+                return Err(std::io::Error::last_os_error())
+                    .context(format!("Failed to bind CAN socket to {}", interface));
+            }
+            socket
+        };
+        info!(interface, can_id = format!("{:#x}", can_id), "Writing frames to CAN interface");
+        Ok(Output::Vcan { socket, can_id })
+    }
+
+    /// Нужно ли выдерживать темп реального времени (для файла - нет)
+    pub fn is_realtime(&self) -> bool {
+        !matches!(self, Output::File(_))
+    }
+
+    /// Отправляет байты потока; если получатель не готов, данные отбрасываются
+    /// Возвращает false, если данные не были отправлены
+    pub fn write(&mut self, bytes: &[u8]) -> Result<bool> {
+        match self {
+            Output::File(file) => {
+                file.write_all(bytes)?;
+                Ok(true)
+            }
+            Output::Tcp { listener, stream } => {
+                if stream.is_none() {
+                    *stream = accept(listener)?;
+                }
+                let Some(client) = stream else {
+                    return Ok(false);
+                };
+                match client.write_all(bytes) {
+                    Ok(()) => Ok(true),
+                    Err(e) => {
+                        warn!("TCP client disconnected: {}", e);
+                        *stream = None;
+                        Ok(false)
+                    }
+                }
+            }
+            Output::Pty { master, pending, .. } => {
+                // Сначала дописываем остаток прошлых кадров; пока он не ушел,
+                // новые кадры отбрасываются целиком (никто не читает подчиненную сторону)
+                write_pending(master, pending)?;
+                if !pending.is_empty() {
+                    return Ok(false);
+                }
+                pending.extend_from_slice(bytes);
+                write_pending(master, pending)?;
+                Ok(true)
+            }
+            Output::Vcan { socket, can_id } => {
+                for chunk in bytes.chunks(CAN_DATA_LEN) {
+                    // SAFETY: can_frame - POD-структура, нулевое значение допустимо
+                    let mut frame: libc::can_frame = unsafe { std::mem::zeroed() };
+                    frame.can_id = *can_id;
+                    frame.can_dlc = chunk.len() as u8;
+                    frame.data[..chunk.len()].copy_from_slice(chunk);
+                    // SAFETY: передаем структуру целиком, ее размер ожидает CAN_RAW
+                    let raw = unsafe {
+                        std::slice::from_raw_parts(
+                            &frame as *const libc::can_frame as *const u8,
+                            std::mem::size_of::<libc::can_frame>(),
+                        )
+                    };
+                    match socket.write(raw) {
+                        Ok(_) => {}
+                        Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(false),
+                        Err(e) => return Err(e).context("Failed to write CAN frame"),
+                    }
+                }
+                Ok(true)
+            }
+        }
+    }
+
+    /// Читает доступные входящие байты (RTR-запросы) без ожидания
+    pub fn read(&mut self) -> Result<Vec<u8>> {
+        let mut buffer = [0u8; 1024];
+        match self {
+            Output::File(_) => Ok(Vec::new()),
+            Output::Tcp { stream, .. } => {
+                let Some(client) = stream else {
+                    return Ok(Vec::new());
+                };
+                match client.read(&mut buffer) {
+                    Ok(0) => {
+                        info!("TCP client closed the connection");
+                        *stream = None;
+                        Ok(Vec::new())
+                    }
+                    Ok(size) => Ok(buffer[..size].to_vec()),
+                    Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Ok(Vec::new()),
+                    Err(e) => {
+                        warn!("TCP read error: {}", e);
+                        *stream = None;
+                        Ok(Vec::new())
+                    }
+                }
+            }
+            Output::Pty { master, .. } => match master.read(&mut buffer) {
+                Ok(size) => Ok(buffer[..size].to_vec()),
+                // EIO - подчиненная сторона еще не открыта или уже закрыта
+                Err(e) if e.kind() == ErrorKind::WouldBlock || e.raw_os_error() == Some(libc::EIO) => Ok(Vec::new()),
+                Err(e) => Err(e).context("Failed to read from pty"),
+            },
+            Output::Vcan { socket, .. } => {
+                let mut bytes = Vec::new();
+                // SAFETY: can_frame - POD-структура, нулевое значение допустимо
+                let mut frame: libc::can_frame = unsafe { std::mem::zeroed() };
+                loop {
+                    // SAFETY: буфер - структура can_frame целиком
+                    let raw = unsafe {
+                        std::slice::from_raw_parts_mut(
+                            &mut frame as *mut libc::can_frame as *mut u8,
+                            std::mem::size_of::<libc::can_frame>(),
+                        )
+                    };
+                    match socket.read(raw) {
+                        Ok(size) if size == raw.len() => {
+                            let len = (frame.can_dlc as usize).min(CAN_DATA_LEN);
+                            bytes.extend_from_slice(&frame.data[..len]);
+                        }
+                        Ok(_) => {}
+                        Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(bytes),
+                        Err(e) => return Err(e).context("Failed to read CAN frame"),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Принимает ожидающее TCP-подключение, если оно есть
+fn accept(listener: &TcpListener) -> Result<Option<TcpStream>> {
+    match listener.accept() {
+        Ok((stream, peer)) => {
+            stream.set_nonblocking(false)?;
+            stream.set_read_timeout(Some(TCP_READ_TIMEOUT))?;
+            stream.set_nodelay(true)?;
+            info!(%peer, "TCP client connected");
+            Ok(Some(stream))
+        }
+        Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(None),
+        Err(e) => Err(e).context("Failed to accept TCP client"),
+    }
+}
+
+/// Пишет в неблокирующий дескриптор сколько примет; записанное удаляется из буфера
+fn write_pending(writer: &mut impl Write, pending: &mut Vec<u8>) -> Result<()> {
+    while !pending.is_empty() {
+        match writer.write(pending) {
+            Ok(0) => break,
+            Ok(size) => {
+                pending.drain(..size);
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e).context("Failed to write to pty"),
+        }
+    }
+    Ok(())
+}
+
+/// Переводит дескриптор в неблокирующий режим
+fn set_nonblocking(fd: libc::c_int) -> Result<()> {
+    // SAFETY: fcntl с F_GETFL/F_SETFL на открытом дескрипторе
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL);
+        if flags < 0 || libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) < 0 {
+            return Err(std::io::Error::last_os_error()).context("Failed to set O_NONBLOCK");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Получатель, принимающий не больше `capacity` байт до следующего чтения
+    struct Limited {
+        written: Vec<u8>,
+        capacity: usize,
+    }
+
+    impl Write for Limited {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            let size = bytes.len().min(self.capacity - self.written.len());
+            if size == 0 {
+                return Err(ErrorKind::WouldBlock.into());
+            }
+            self.written.extend_from_slice(&bytes[..size]);
+            Ok(size)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn partial_write_kept_pending() {
+        let mut writer = Limited { written: Vec::new(), capacity: 3 };
+        let mut pending = b"frame".to_vec();
+        write_pending(&mut writer, &mut pending).unwrap();
+        assert_eq!(writer.written, b"fra");
+        assert_eq!(pending, b"me");
+
+        // Получатель освободил буфер - остаток дописывается без потерь
+        writer.capacity = 10;
+        write_pending(&mut writer, &mut pending).unwrap();
+        assert_eq!(writer.written, b"frame");
+        assert!(pending.is_empty());
+    }
+}
@@ -1,5 +1,7 @@
 use tokio::sync::mpsc;
 
+use crate::record::FrameRecord;
+
 // Каналы для связи между компонентами системы
 // Типы для передачи пакетов данных между компонентами
 pub type PackageSender = mpsc::UnboundedSender<Vec<u8>>;     // Отправитель пакетов данных
@@ -9,6 +11,10 @@ pub type PackageReceiver = mpsc::UnboundedReceiver<Vec<u8>>;  // Получат
 pub type CommandSender = mpsc::UnboundedSender<Vec<u8>>;      // Отправитель команд
 pub type CommandReceiver = mpsc::UnboundedReceiver<Vec<u8>>;  // Получатель команд
 
+// Типы для передачи декодированных записей (лог пакетов терминального интерфейса)
+pub type RecordSender = mpsc::Sender<FrameRecord>;            // Отправитель записей
+pub type RecordReceiver = mpsc::Receiver<FrameRecord>;        // Получатель записей
+
 /// Создает неограниченный канал для передачи пакетов данных
 /// Используется для передачи пакетов от читателей к сортировщику
 pub fn package_channel() -> (PackageSender, PackageReceiver) {
@@ -19,4 +25,14 @@ pub fn package_channel() -> (PackageSender, PackageReceiver) {
 /// Используется для отправки команд на запись данных
 pub fn command_channel() -> (CommandSender, CommandReceiver) {
     mpsc::unbounded_channel()
-}
\ No newline at end of file
+}
+
+/// Емкость каналов записей от сортировщика (терминальный интерфейс, поток, экспорт)
+pub const RECORD_TAP_CAPACITY: usize = 4096;
+
+/// Создает ограниченный канал для декодированных записей
+/// Копии для терминального интерфейса и потока при заполненном канале отбрасываются,
+/// экспорт обрабатывает переполнение сам (influx_export::ExportTap)
+pub fn record_channel(capacity: usize) -> (RecordSender, RecordReceiver) {
+    mpsc::channel(capacity)
+}
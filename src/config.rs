@@ -0,0 +1,369 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+
+use crate::protocol::{ByteOrder, CrcVariant, HeaderLayout, STANDARD_PROFILE};
+use crate::state_store::ParamKey;
+
+/// Конфигурация приложения, загружаемая из TOML-файла (--config)
+/// Все разделы необязательны и имеют значения по умолчанию
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub watchdog: WatchdogConfig,    // Обнаружение "замолчавших" параметров
+    pub alarms: AlarmsConfig,        // Пороговые тревоги
+    pub hw_alarms: HwAlarmsConfig,   // Аппаратные аварийные биты (поле alarms)
+    pub params: ParamsConfig,        // Словарь параметров
+    pub package_types: Vec<PackageTypeConfig>,  // Дополнительные типы пакетов
+    pub protocol: ProtocolConfig,    // Профили протокола
+    pub quarantine: QuarantineConfig, // Карантин отклоненных кадров
+    pub mqtt: MqttConfig,            // Публикация значений и событий в MQTT
+    pub influx: InfluxConfig,        // Экспорт значений в InfluxDB line protocol
+    pub recording: RecordingConfig,  // Запись захвата через HTTP API
+}
+
+impl Config {
+    /// Загружает конфигурацию из файла
+    pub fn load(path: &str) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .context(format!("Failed to read config file: {}", path))?;
+        let config: Self = toml::from_str(&text)
+            .context(format!("Failed to parse config file: {}", path))?;
+        config.watchdog.validate()
+            .context(format!("Invalid config file: {}", path))?;
+        Ok(config)
+    }
+}
+
+/// Настройки сторожевого таймера параметров
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WatchdogConfig {
+    pub enabled: bool,                   // Включено ли обнаружение
+    pub check_interval_secs: f64,        // Период проверки
+    pub stale_factor: f64,               // Параметр "замолчал" через stale_factor * ожидаемый интервал
+    pub min_updates: u64,                // Минимум обновлений для обучения интервала
+    pub min_interval_secs: f64,          // Нижняя граница ожидаемого интервала
+    pub params: Vec<WatchdogParamConfig>, // Явно заданные интервалы
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            check_interval_secs: 1.0,
+            stale_factor: 3.0,
+            min_updates: 3,
+            min_interval_secs: 0.5,
+            params: Vec::new(),
+        }
+    }
+}
+
+impl WatchdogConfig {
+    /// Проверяет период проверки: он задает интервал таймера и не может быть нулевым
+    pub fn validate(&self) -> Result<()> {
+        if !(self.check_interval_secs.is_finite() && self.check_interval_secs > 0.0) {
+            return Err(anyhow::anyhow!(
+                "watchdog.check_interval_secs must be a positive number, got {}",
+                self.check_interval_secs
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Ожидаемый интервал обновления для параметров, подходящих под фильтр
+/// Незаданные поля фильтра подходят под любое значение
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatchdogParamConfig {
+    pub prm_id: u16,                     // Идентификатор параметра
+    pub src_id: Option<u8>,              // Идентификатор источника
+    pub module_addr: Option<u8>,         // Адрес модуля
+    pub dev_id: Option<u8>,              // Идентификатор устройства
+    pub expected_interval_secs: f64,     // Ожидаемый интервал обновления
+}
+
+/// Настройки пороговых тревог
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlarmsConfig {
+    pub classes: Vec<AlarmClassConfig>,   // Классы параметров с порогами
+}
+
+/// Направление порога
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlarmDirection {
+    /// Тревога при превышении порога
+    #[default]
+    High,
+    /// Тревога при падении ниже порога
+    Low,
+}
+
+/// Пороги для класса параметров (например, температура FPGA или платы)
+/// Незаданные списки фильтра подходят под любое значение
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlarmClassConfig {
+    pub name: String,                    // Имя класса (fpga_temp, board_temp, ...)
+    pub prm_ids: Vec<u16>,               // Идентификаторы параметров класса
+    pub src_ids: Option<Vec<u8>>,        // Идентификаторы источников
+    pub dev_ids: Option<Vec<u8>>,        // Идентификаторы устройств
+    pub warning: f32,                    // Порог предупреждения
+    pub critical: f32,                   // Критический порог
+    #[serde(default)]
+    pub hysteresis: f32,                 // Гистерезис при снятии тревоги
+    #[serde(default)]
+    pub min_duration_secs: f64,          // Минимальная длительность превышения
+    #[serde(default)]
+    pub direction: AlarmDirection,       // Направление порога
+}
+
+impl AlarmClassConfig {
+    /// Проверяет, относится ли параметр к классу
+    pub fn matches(&self, key: &ParamKey) -> bool {
+        self.prm_ids.contains(&key.prm_id)
+            && self.src_ids.as_ref().is_none_or(|ids| ids.contains(&key.src_id))
+            && self.dev_ids.as_ref().is_none_or(|ids| ids.contains(&key.dev_id))
+    }
+}
+
+/// Настройки аппаратных аварийных битов
+/// Сохранение истории по умолчанию выключено: активные биты хранятся без источника,
+/// поэтому живой прием и воспроизведение дампа должны писать в разные файлы
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HwAlarmsConfig {
+    pub history_file: Option<String>,    // Файл истории (не задан или пустая строка - не сохранять)
+    pub history_size: usize,             // Максимальное число переходов в истории
+    pub save_interval_secs: f64,         // Период сохранения истории при изменениях
+    pub bits: Vec<HwAlarmBitConfig>,     // Словарь имен условий
+}
+
+impl Default for HwAlarmsConfig {
+    fn default() -> Self {
+        Self {
+            history_file: None,
+            history_size: 1000,
+            save_interval_secs: 5.0,
+            bits: Vec::new(),
+        }
+    }
+}
+
+/// Имя условия для аварийного бита
+/// Если prm_ids не задан, имя действует для всех параметров
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct HwAlarmBitConfig {
+    pub bit: u8,                         // Номер бита (0-3)
+    pub name: String,                    // Имя условия (over_temperature, ...)
+    pub prm_ids: Option<Vec<u16>>,       // Параметры, к которым относится имя
+}
+
+/// Настройки словаря параметров
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ParamsConfig {
+    pub dictionary: Option<String>,      // Файл словаря (TOML или CSV)
+}
+
+/// Формат содержимого пакета (байты после заголовка)
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadLayout {
+    /// Значение параметра, максимум и минимум (как у 0x8000)
+    Values,
+    /// Значения параметров prm_id, prm_id + 1, ... подряд
+    Block,
+    /// Пары (data_type, значение) для нескольких параметров
+    Burst,
+    /// Строка UTF-8 (версия прошивки, серийный номер)
+    String,
+}
+
+/// Описание типа пакета
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackageTypeConfig {
+    pub package_type: u16,               // Значение поля package_type
+    pub layout: PayloadLayout,           // Формат содержимого
+    #[serde(default = "default_max_items")]
+    pub max_items: usize,                // Максимум значений (для string - байт)
+}
+
+fn default_max_items() -> usize {
+    64
+}
+
+/// Настройки профилей протокола
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProtocolConfig {
+    pub profile: String,                     // Профиль по умолчанию ("auto" - автоопределение)
+    pub detect_frames: u32,                  // Кадров для автоопределения
+    pub sources: HashMap<String, String>,    // Профиль для отдельных источников (uart, dump, can)
+    pub profiles: Vec<ProtocolProfileConfig>, // Дополнительные профили
+    pub crc_diagnostics: bool,               // Подбирать вариант CRC для пакетов с ошибкой CRC
+}
+
+impl Default for ProtocolConfig {
+    fn default() -> Self {
+        Self {
+            profile: String::from(STANDARD_PROFILE),
+            detect_frames: 16,
+            sources: HashMap::new(),
+            profiles: Vec::new(),
+            crc_diagnostics: false,
+        }
+    }
+}
+
+/// Описание профиля протокола
+/// Поля раскладки, не заданные в layout, совпадают со стандартными
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProtocolProfileConfig {
+    pub name: String,                    // Имя профиля (не "auto")
+    #[serde(default)]
+    pub crc: CrcVariant,                 // Вариант CRC
+    #[serde(default)]
+    pub crc_byte_order: ByteOrder,       // Порядок байтов CRC в пакете
+    #[serde(default)]
+    pub layout: HeaderLayout,            // Раскладка заголовка
+}
+
+/// Настройки карантина кадров с ошибкой CRC или разбора
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuarantineConfig {
+    pub endpoint: Option<String>,        // ZMQ адрес маршрута карантина (пустая строка - не публиковать)
+    pub file: Option<String>,            // Файл JSON Lines (пустая строка - не сохранять)
+    pub max_file_bytes: u64,             // Размер файла, после которого он ротируется
+    pub max_files: usize,                // Количество хранимых старых файлов
+}
+
+impl Default for QuarantineConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: Some(String::from("tcp://*:5562")),
+            file: None,
+            max_file_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+}
+
+/// Настройки публикации в MQTT
+/// Шаблоны топиков: {source}, {bm}, {mcu}, {dev}, {src}, {prm}, {param}, {route}, {event}
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MqttConfig {
+    pub broker: Option<String>,          // Адрес брокера host:port или [IPv6]:port (не задан - публикация выключена)
+    pub client_id: String,               // Идентификатор клиента
+    pub username: Option<String>,        // Имя пользователя
+    #[serde(skip_serializing)]
+    pub password: Option<String>,        // Пароль (не выдается через HTTP /config)
+    pub topic: String,                   // Шаблон топика значений параметров
+    pub events_topic: Option<String>,    // Шаблон топика событий (пустая строка - не публиковать)
+    pub status_topic: String,            // Топик состояния: online, при потере связи - last will offline
+    pub qos: u8,                         // Качество обслуживания (0, 1, 2)
+    pub retain: bool,                    // Сохранять последние значения на брокере
+    pub keep_alive_secs: u64,            // Интервал keep-alive
+    pub buffer_size: usize,              // Сообщений в очереди на время переподключения
+    pub reconnect_delay_secs: f64,       // Пауза перед повторным подключением
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            broker: None,
+            client_id: String::from("hwmon"),
+            username: None,
+            password: None,
+            topic: String::from("hwmon/{source}/bm{bm}/mcu{mcu}/dev{dev}/{param}"),
+            events_topic: Some(String::from("hwmon/{source}/events/{event}")),
+            status_topic: String::from("hwmon/{source}/status"),
+            qos: 1,
+            retain: true,
+            keep_alive_secs: 30,
+            buffer_size: 10_000,
+            reconnect_delay_secs: 2.0,
+        }
+    }
+}
+
+/// Настройки экспорта значений в InfluxDB line protocol
+/// Строки пишутся в файл и/или отправляются POST-запросом на url, например
+/// "http://127.0.0.1:8086/api/v2/write?org=lab&bucket=hwmon&precision=ns"
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InfluxConfig {
+    pub file: Option<String>,            // Файл для дописывания строк
+    pub url: Option<String>,             // Адрес записи по HTTP (только http://)
+    #[serde(skip_serializing)]
+    pub token: Option<String>,           // Токен (заголовок Authorization: Token ...)
+    pub measurement: String,             // Имя измерения
+    pub batch_size: usize,               // Строк в пакете записи
+    pub flush_interval_secs: f64,        // Период сброса неполного пакета
+    pub retry_delay_secs: f64,           // Пауза перед повторной отправкой
+    pub timeout_secs: f64,               // Таймаут HTTP-запроса
+    pub spool_file: String,              // Файл для строк, не отправленных по HTTP
+    pub spool_max_bytes: u64,            // Предельный размер файла отложенных строк
+}
+
+impl Default for InfluxConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            url: None,
+            token: None,
+            measurement: String::from("hwmon"),
+            batch_size: 5000,
+            flush_interval_secs: 1.0,
+            retry_delay_secs: 5.0,
+            timeout_secs: 5.0,
+            spool_file: String::from("hwmon-influx.spool"),
+            spool_max_bytes: 100 * 1024 * 1024,
+        }
+    }
+}
+
+/// Настройки записи захвата через HTTP API (POST /recording/start)
+/// Клиент передает только имя файла внутри каталога записей
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecordingConfig {
+    pub directory: String,               // Каталог файлов захвата (создается при первой записи)
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self { directory: String::from("recordings") }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watchdog_check_interval_validated() {
+        assert!(WatchdogConfig::default().validate().is_ok());
+        for interval in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let config = WatchdogConfig { check_interval_secs: interval, ..Default::default() };
+            assert!(config.validate().is_err(), "{}", interval);
+        }
+
+        // Ошибка возвращается при загрузке файла, а не в задаче сторожевого таймера
+        let path = std::env::temp_dir().join(format!("hwmon-config-test-{}.toml", std::process::id()));
+        fs::write(&path, "[watchdog]\ncheck_interval_secs = 0\n").unwrap();
+        let error = Config::load(&path.to_string_lossy()).unwrap_err();
+        assert!(format!("{:#}", error).contains("check_interval_secs"));
+        fs::remove_file(&path).unwrap();
+    }
+}
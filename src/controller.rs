@@ -1,16 +1,38 @@
 use anyhow::{Context, Result};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
 use tokio::task::JoinHandle;
+use tracing::{debug, error, info, trace, warn};
 
-use crate::channels::{PackageSender, CommandSender, package_channel, command_channel};
+use crate::channels::{PackageSender, CommandSender, CommandReceiver, package_channel, command_channel, record_channel, RECORD_TAP_CAPACITY};
+use crate::config::Config;
 use crate::dump_reader::DumpReader;
+use crate::events::EventPublisher;
+use crate::hw_alarms::{HwAlarmTracker, SharedHwAlarms};
+use crate::http_api::HttpApi;
+use crate::http_server::HttpServer;
+use crate::influx_export::{InfluxExporter, InfluxHandle};
+use crate::metrics::METRICS;
+use crate::mqtt_sink::MqttSink;
+use crate::package_types::PackageTypeRegistry;
+use crate::param_dict::ParamDictionary;
 use crate::preader::PReader;
-use crate::psorter::PSorter;
+use crate::protocol::ProtocolSelector;
+use crate::quarantine::Quarantine;
+use crate::recorder::{Recorder, SharedRecorder};
+use crate::psorter::{route_name, PSorter, DIAGNOSTICS_PACK_TYPE};
 use crate::pwriter::PWriter;
+use crate::state_store::SharedStateStore;
+use crate::stats_reporter::StatsReporter;
 use crate::uart::Uart;
+use crate::watchdog::Watchdog;
+use crate::ws_stream::{StreamHub, WsServer};
 use crate::zmq_sender::ZmqSender;
 
+/// Время на сброс экспорта при завершении
+const EXPORT_FINISH_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// Тип операции чтения данных
 #[derive(Debug, Clone, PartialEq)]
 pub enum ReadOperation {
@@ -22,32 +44,47 @@ pub enum ReadOperation {
     Can,
 }
 
+impl ReadOperation {
+    /// Имя источника данных для метрик и статистики
+    pub fn source_name(&self) -> &'static str {
+        match self {
+            ReadOperation::Uart => "uart",
+            ReadOperation::Dump => "dump",
+            ReadOperation::Can => "can",
+        }
+    }
+}
+
 /// Основной контроллер приложения, управляющий всеми компонентами
 pub struct Controller {
-    // ZMQ отправители для различных типов мониторов
-    sender_t: Arc<ZmqSender>,  // Для TMonitor
-    sender_s: Arc<ZmqSender>,  // Для SMonitor
-    sender_pu: Arc<ZmqSender>, // Для PUMonitor
-    sender_o: Arc<ZmqSender>,  // Для OMonitor
-    sender_c: Arc<ZmqSender>,  // Для CMonitor
+    // ZMQ отправители мониторов пакетов принадлежат обработчику пакетов
+    events: EventPublisher,    // Для EMonitor (события: stale/recovered и др.)
+    stream_hub: StreamHub,     // Поток WebSocket (пакеты и события)
     
     // Каналы для передачи данных между компонентами
     package_sender: PackageSender,  // Для отправки пакетов на сортировку
     command_sender: CommandSender,  // Для отправки команд на запись
+    command_receiver: Option<CommandReceiver>,  // Передается писателю команд в режиме Uart
     
     // Компоненты системы
-    dump_reader: Option<DumpReader>,          // Читатель дамп-файлов
     uart: Option<Arc<Mutex<Uart>>>,           // UART интерфейс (защищен мьютексом)
-    p_reader: Option<Arc<Mutex<PReader>>>,    // Читатель пакетов
-    p_writer: Option<Arc<Mutex<PWriter>>>,    // Писатель пакетов (не используется)
+    p_writer: Option<Arc<PWriter>>,           // Писатель команд (режим Uart)
     p_sorter: Arc<Mutex<PSorter>>,            // Сортировщик пакетов
+    recorder: SharedRecorder,                 // Запись захвата (управляется через HTTP)
     
     // Состояние контроллера
     dump_filename: Option<String>,    // Имя файла дампа (если используется режим Dump)
+    uart_port: String,                // Путь к последовательному порту (режим Uart)
     read_operation: ReadOperation,    // Текущий режим чтения
+    config: Config,                   // Конфигурация из файла (--config)
+    http_addr: Option<String>,        // Адрес локального HTTP API
+    ws: Option<(String, u32)>,        // Адрес сервера WebSocket и ограничение частоты на клиента
+    stats_reporter: Option<(Duration, StatsReporter)>,  // Периодический отчет и его интервал
     
     // Асинхронные задачи, выполняемые контроллером
-    tasks: Vec<JoinHandle<()>>,
+    pipeline_tasks: Vec<JoinHandle<()>>,  // Читатели и обработчик пакетов
+    tasks: Vec<JoinHandle<()>>,           // Служебные задачи (команды, HTTP, отчеты)
+    influx: Option<InfluxHandle>,         // Экспорт в InfluxDB (сбрасывается при завершении)
 }
 
 impl Controller {
@@ -59,6 +96,8 @@ impl Controller {
         let sender_pu = Arc::new(ZmqSender::new("tcp://*:5557")); // PUMonitor
         let sender_o = Arc::new(ZmqSender::new("tcp://*:5558"));  // OMonitor
         let sender_c = Arc::new(ZmqSender::new("tcp://*:5559"));  // CMonitor
+        let sender_e = Arc::new(ZmqSender::new("tcp://*:5560"));  // EMonitor
+        let sender_d = Arc::new(ZmqSender::new("tcp://*:5561"));  // DMonitor
 
         // Создание каналов для межкомпонентного взаимодействия
         let (package_sender, package_receiver) = package_channel();   // Канал для пакетов
@@ -67,55 +106,56 @@ impl Controller {
         // Инициализация сортировщика пакетов
         let sorter = Arc::new(Mutex::new(PSorter::new()));
         let sorter_clone = Arc::clone(&sorter);
+        let recorder = Recorder::shared();
+        let recorder_clone = Arc::clone(&recorder);
         
-        // Подготовка клонов для использования в асинхронной задаче
-        let sender_t_clone = Arc::clone(&sender_t);
-        let sender_s_clone = Arc::clone(&sender_s);
-        let sender_pu_clone = Arc::clone(&sender_pu);
-        let sender_o_clone = Arc::clone(&sender_o);
-        let sender_c_clone = Arc::clone(&sender_c);
+        let stream_hub = StreamHub::default();
+        let events = EventPublisher::new(sender_e, stream_hub.clone());
+        let events_clone = events.clone();
 
         // Запуск задачи обработки пакетов
         let package_handler = tokio::spawn(async move {
             Self::handle_packages(
                 package_receiver,
                 sorter_clone,
-                sender_t_clone,
-                sender_s_clone,
-                sender_pu_clone,
-                sender_o_clone,
-                sender_c_clone,
+                recorder_clone,
+                sender_t,
+                sender_s,
+                sender_pu,
+                sender_o,
+                sender_c,
+                sender_d,
+                events_clone,
             ).await;
         });
 
-        // Запуск задачи обработки команд
-        let command_handler = tokio::spawn(async move {
-            Self::handle_commands(command_receiver).await;
-        });
-
         Ok(Self {
-            sender_t,
-            sender_s,
-            sender_pu,
-            sender_o,
-            sender_c,
+            events,
+            stream_hub,
             package_sender,
             command_sender,
-            dump_reader: None,
+            command_receiver: Some(command_receiver),
             uart: None,
-            p_reader: None,
             p_writer: None,
             p_sorter: sorter,
+            recorder,
             dump_filename: None,
+            uart_port: String::from(crate::uart::DEFAULT_PORT),
             read_operation: ReadOperation::Uart,  // По умолчанию UART режим
-            tasks: vec![package_handler, command_handler],
+            config: Config::default(),
+            http_addr: None,
+            ws: None,
+            stats_reporter: None,
+            pipeline_tasks: vec![package_handler],
+            tasks: Vec::new(),
+            influx: None,
         })
     }
 
     /// Устанавливает режим чтения данных
     pub fn set_read_operation(&mut self, operation: ReadOperation) {
         self.read_operation = operation.clone();
-        println!("Read operation changed to: {:?}", operation);
+        debug!(?operation, "Read operation changed");
     }
 
     /// Устанавливает имя файла дампа для режима Dump
@@ -123,17 +163,139 @@ impl Controller {
         self.dump_filename = Some(filename);
     }
 
+    /// Устанавливает путь к последовательному порту для режима Uart
+    pub fn set_uart_port(&mut self, path: String) {
+        self.uart_port = path;
+    }
+
+    /// Устанавливает конфигурацию, загруженную из файла
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+    }
+
+    /// Устанавливает адрес локального HTTP/JSON API (в том числе /metrics)
+    pub fn set_http_addr(&mut self, addr: String) {
+        self.http_addr = Some(addr);
+    }
+
+    /// Устанавливает адрес сервера WebSocket и ограничение частоты сообщений на клиента
+    pub fn set_ws_addr(&mut self, addr: String, max_rate: u32) {
+        self.ws = Some((addr, max_rate));
+    }
+
+    /// Включает периодический отчет статистики с указанным интервалом
+    pub fn set_stats_reporter(&mut self, interval: Duration, reporter: StatsReporter) {
+        self.stats_reporter = Some((interval, reporter));
+    }
+
+    /// Возвращает сортировщик пакетов (счетчики, статистика устройств, тревоги)
+    pub fn sorter(&self) -> Arc<Mutex<PSorter>> {
+        Arc::clone(&self.p_sorter)
+    }
+
+    /// Настраивает разбор пакетов сортировщика: словарь параметров, типы пакетов,
+    /// профиль протокола источника и диагностику CRC
+    pub fn configure_decoding(sorter: &mut PSorter, config: &Config, source: &str) -> Result<()> {
+        if let Some(path) = &config.params.dictionary {
+            let dictionary = ParamDictionary::load(path)?;
+            info!(path = %path, params = dictionary.len(), "Parameter dictionary loaded");
+            sorter.set_param_dict(dictionary);
+        }
+        let package_types = PackageTypeRegistry::from_config(&config.package_types)?;
+        for (package_type, decoder) in package_types.entries() {
+            debug!(package_type = format!("{:#06x}", package_type), decoder, "Package type registered");
+        }
+        sorter.set_package_types(package_types);
+        sorter.set_protocol(ProtocolSelector::from_config(&config.protocol, source)?);
+        sorter.set_crc_diagnostics(config.protocol.crc_diagnostics);
+        Ok(())
+    }
+
     /// Запускает контроллер в выбранном режиме чтения
     pub async fn start(&mut self) -> Result<()> {
-        println!("================================================");
-        println!("Starting server with ZeroMQ endpoints:");
-        println!("TMonitor:  tcp://localhost:5555");   // Порт для TMonitor
-        println!("SMonitor:  tcp://localhost:5556");   // Порт для SMonitor
-        println!("PUMonitor: tcp://localhost:5557");   // Порт для PUMonitor
-        println!("OMonitor:  tcp://localhost:5558");   // Порт для OMonitor
-        println!("CMonitor:  tcp://localhost:5559");   // Порт для CMonitor
-        println!("Current read operation: {:?}", self.read_operation);
-        println!("================================================");
+        info!(
+            tmonitor = "tcp://localhost:5555",   // Порт для TMonitor
+            smonitor = "tcp://localhost:5556",   // Порт для SMonitor
+            pumonitor = "tcp://localhost:5557",  // Порт для PUMonitor
+            omonitor = "tcp://localhost:5558",   // Порт для OMonitor
+            cmonitor = "tcp://localhost:5559",   // Порт для CMonitor
+            emonitor = "tcp://localhost:5560",   // Порт для событий (EMonitor)
+            dmonitor = "tcp://localhost:5561",   // Порт для диагностики (DMonitor)
+            read_operation = ?self.read_operation,
+            "Starting server with ZeroMQ endpoints"
+        );
+
+        {
+            let mut sorter = self.p_sorter.lock().await;
+            sorter.set_source(self.read_operation.source_name());
+            sorter.set_alarm_classes(self.config.alarms.classes.clone());
+            sorter.set_hw_alarms(HwAlarmTracker::load(&self.config.hw_alarms)?);
+            Self::configure_decoding(&mut sorter, &self.config, self.read_operation.source_name())?;
+            sorter.set_quarantine(Quarantine::from_config(&self.config.quarantine)?);
+        }
+
+        // Запуск HTTP API, если задан адрес; команды принимаются только там, где есть писатель
+        if let Some(addr) = &self.http_addr {
+            let command_sender = matches!(self.read_operation, ReadOperation::Uart)
+                .then(|| self.command_sender.clone());
+            let api = HttpApi::new(
+                self.read_operation.source_name(),
+                self.config.clone(),
+                Arc::clone(&self.p_sorter),
+                command_sender,
+                Arc::clone(&self.recorder),
+            ).await;
+            let handler = api.into_handler();
+            let server = HttpServer::bind(addr, handler).await?;
+            self.tasks.push(tokio::spawn(server.run()));
+        }
+
+        // Общая очередь пакетов и событий для WebSocket и MQTT: копии декодированных
+        // записей идут из сортировщика через ограниченный канал, сортировщик получателей не ждет
+        let mqtt_sink = MqttSink::from_config(&self.config.mqtt, self.read_operation.source_name())?;
+        let influx = InfluxExporter::from_config(&self.config.influx, self.read_operation.source_name())?;
+        if self.ws.is_some() || mqtt_sink.is_some() {
+            let (record_sender, record_receiver) = record_channel(RECORD_TAP_CAPACITY);
+            self.p_sorter.lock().await.add_record_tap(record_sender);
+            self.tasks.push(tokio::spawn(self.stream_hub.clone().forward_records(record_receiver)));
+        }
+        if let Some((addr, max_rate)) = &self.ws {
+            let server = WsServer::bind(addr, self.stream_hub.clone(), *max_rate).await?;
+            self.tasks.push(tokio::spawn(server.run()));
+        }
+        if let Some(sink) = mqtt_sink {
+            self.tasks.extend(sink.start(self.stream_hub.clone()));
+        }
+        // InfluxDB получает записи через собственный канал без потерь;
+        // ждать экспорт может только чтение дампа, живой источник не тормозится
+        if let Some(influx) = influx {
+            let backpressure = matches!(self.read_operation, ReadOperation::Dump);
+            let (handle, export_tap) = influx.start(backpressure);
+            self.p_sorter.lock().await.set_export_tap(export_tap);
+            self.influx = Some(handle);
+        }
+
+        // Запуск периодического отчета статистики
+        if let Some((interval, reporter)) = self.stats_reporter.take() {
+            let sorter = Arc::clone(&self.p_sorter);
+            self.tasks.push(tokio::spawn(Self::report_statistics(sorter, interval, reporter)));
+        }
+
+        // Периодическое сохранение истории аппаратных аварийных битов
+        let hw_alarms = self.p_sorter.lock().await.hw_alarms();
+        let interval = Duration::from_secs_f64(self.config.hw_alarms.save_interval_secs.max(0.1));
+        self.tasks.push(tokio::spawn(Self::save_hw_alarms(hw_alarms, interval)));
+
+        // Запуск сторожевого таймера параметров
+        if self.config.watchdog.enabled {
+            let state_store = self.p_sorter.lock().await.state_store();
+            let watchdog = Watchdog::new(self.config.watchdog.clone());
+            let interval = Duration::from_secs_f64(self.config.watchdog.check_interval_secs);
+            let events = self.events.clone();
+            self.tasks.push(tokio::spawn(async move {
+                Self::run_watchdog(watchdog, state_store, interval, events).await;
+            }));
+        }
 
         // Запуск в зависимости от выбранного режима
         match self.read_operation {
@@ -144,7 +306,7 @@ impl Controller {
                 self.start_dump_mode().await?;  // Режим чтения из файла дампа
             }
             ReadOperation::Can => {
-                println!("CAN mode selected - not implemented yet");  // CAN режим не реализован
+                info!("CAN mode selected - not implemented yet");  // CAN режим не реализован
             }
         }
 
@@ -154,7 +316,7 @@ impl Controller {
     /// Запускает режим чтения через UART
     async fn start_uart_mode(&mut self) -> Result<()> {
         // Создание UART интерфейса
-        let uart = Arc::new(Mutex::new(Uart::new()?));
+        let uart = Arc::new(Mutex::new(Uart::new(&self.uart_port)?));
         
         // Создание читателя пакетов
         let mut p_reader = PReader::new(
@@ -165,22 +327,30 @@ impl Controller {
         // Запуск задачи чтения из UART
         let uart_task = tokio::spawn(async move {
             // Начало чтения (синхронная операция)
-            if let Err(e) = p_reader.start_reading() {
-                eprintln!("Failed to start UART reading: {}", e);
+            if let Err(e) = p_reader.start_reading().await {
+                error!("Failed to start UART reading: {}", e);
                 return;
             }
             
             // Основной цикл чтения (асинхронная операция)
             if let Err(e) = p_reader.read_loop().await {
-                eprintln!("UART reading error: {}", e);
+                error!("UART reading error: {}", e);
             }
         });
         
+        // Запуск записи команд в тот же порт
+        let p_writer = Arc::new(PWriter::new(Arc::clone(&uart)));
+        if let Some(command_receiver) = self.command_receiver.take() {
+            let writer = Arc::clone(&p_writer);
+            self.tasks.push(tokio::spawn(Self::handle_commands(command_receiver, writer)));
+        }
+
         // Сохранение ссылок на компоненты
         self.uart = Some(uart);
-        self.tasks.push(uart_task);
+        self.p_writer = Some(p_writer);
+        self.pipeline_tasks.push(uart_task);
         
-        println!("UART mode started - reading continuously");
+        info!("UART mode started - reading continuously");
         Ok(())
     }
 
@@ -191,38 +361,49 @@ impl Controller {
             .context("Dump filename not set")?;
         
         // Создание читателя дампа
-        let dump_reader = DumpReader::new(self.package_sender.clone()); // убираем mut
+        let dump_reader = DumpReader::new(self.package_sender.clone());
         let filename_clone = filename.clone();
         
         // Запуск задачи чтения дампа
         let dump_task = tokio::spawn(async move {
             if let Err(e) = dump_reader.start_read(&filename_clone).await {
-                eprintln!("Dump reading error: {}", e);
+                error!("Dump reading error: {}", e);
             } else {
-                println!("Dump processing task completed");
+                debug!("Dump processing task completed");
             }
             // Когда задача завершается, package_sender выходит из области видимости
             // и канал автоматически закрывается
         });
         
-        self.tasks.push(dump_task);
+        self.pipeline_tasks.push(dump_task);
         
-        println!("Dump mode started - streaming packets");
+        info!("Dump mode started - streaming packets");
         Ok(())
     }
 
     /// Обрабатывает входящие пакеты и распределяет их по соответствующим ZMQ отправителям
+    #[allow(clippy::too_many_arguments)]
     async fn handle_packages(
         mut package_receiver: crate::channels::PackageReceiver,  // Приемник пакетов
         sorter: Arc<Mutex<PSorter>>,                             // Сортировщик пакетов
+        recorder: SharedRecorder,                                // Запись захвата
         sender_t: Arc<ZmqSender>,                                // Отправитель для TMonitor
         sender_s: Arc<ZmqSender>,                                // Отправитель для SMonitor
         sender_pu: Arc<ZmqSender>,                               // Отправитель для PUMonitor
         sender_o: Arc<ZmqSender>,                                // Отправитель для OMonitor
         sender_c: Arc<ZmqSender>,                                // Отправитель для CMonitor
+        sender_d: Arc<ZmqSender>,                                // Отправитель для DMonitor
+        events: EventPublisher,                                  // Публикатор событий (EMonitor)
     ) {
         // Основной цикл обработки пакетов
         while let Some(package) = package_receiver.recv().await {
+            METRICS.channel_depth.set(&["packages"], package_receiver.len() as u64);
+
+            // Кадр записывается как принят, до разбора
+            recorder.lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .record(&package);
+
             // Блокировка сортировщика для обработки пакета
             let mut sorter_guard = sorter.lock().await;
             
@@ -232,78 +413,143 @@ impl Controller {
             let sender_pu = Arc::clone(&sender_pu);
             let sender_o = Arc::clone(&sender_o);
             let sender_c = Arc::clone(&sender_c);
+            let sender_d = Arc::clone(&sender_d);
             
             // Обработка пакета через сортировщик
             sorter_guard.slot_input_package(&package, move |pack_type, data| {
                 // Распределение пакета по типам
-                match pack_type {
-                    1 => {  // Пакет для TMonitor
-                        if let Err(e) = sender_t.send_package(data) {
-                            eprintln!("Failed to send to TMonitor: {}", e);
-                        } else {
-                            println!("Successfully sent to TMonitor");
-                        }
-                    }
-                    2 => {  // Пакет для SMonitor
-                        if let Err(e) = sender_s.send_package(data) {
-                            eprintln!("Failed to send to SMonitor: {}", e);
-                        } else {
-                            println!("Successfully sent to SMonitor");
-                        }
-                    }
-                    3 => {  // Пакет для PUMonitor
-                        if let Err(e) = sender_pu.send_package(data) {
-                            eprintln!("Failed to send to PUMonitor: {}", e);
-                        } else {
-                            println!("Successfully sent to PUMonitor");
-                        }
-                    }
-                    4 => {  // Пакет для OMonitor
-                        if let Err(e) = sender_o.send_package(data) {
-                            eprintln!("Failed to send to OMonitor: {}", e);
-                        } else {
-                            println!("Successfully sent to OMonitor");
-                        }
-                    }
-                    5 => {  // Пакет для CMonitor
-                        if let Err(e) = sender_c.send_package(data) {
-                            eprintln!("Failed to send to CMonitor: {}", e);
-                        } else {
-                            println!("Successfully sent to CMonitor");
-                        }
-                    }
-                    6 => {  // Неиспользуемый тип пакета
-                        // Пустая обработка для типа 6
-                    }
-                    _ => {  // Пакет неизвестного типа - отправляем в OMonitor по умолчанию
-                        if let Err(e) = sender_o.send_package(data) {
-                            eprintln!("Failed to send to OMonitor: {}", e);
-                        } else {
-                            println!("Successfully sent to OMonitor (default)");
-                        }
-                    }
+                let sender = match pack_type {
+                    1 => &sender_t,   // Пакет для TMonitor
+                    2 => &sender_s,   // Пакет для SMonitor
+                    3 => &sender_pu,  // Пакет для PUMonitor
+                    4 => &sender_o,   // Пакет для OMonitor
+                    5 => &sender_c,   // Пакет для CMonitor
+                    DIAGNOSTICS_PACK_TYPE => &sender_d,  // Пакет, не прошедший разбор
+                    _ => &sender_o,   // Пакет неизвестного типа - отправляем в OMonitor по умолчанию
+                };
+
+                // Ошибки отправки логируются в ZmqSender с ограничением частоты
+                let route = route_name(pack_type);
+                if sender.send_package(data).is_ok() {
+                    METRICS.route_sent.inc(&[route]);
+                    trace!(route, "Package sent");
+                } else {
+                    METRICS.route_failed.inc(&[route]);
                 }
             });
+
+            // Публикация событий, сформированных при обработке пакета
+            for event in sorter_guard.take_events() {
+                events.publish(&event);
+            }
+
+            // Экспорт: для дампа обработка пакетов ждет места в канале,
+            // для живого источника записи сверх емкости канала пишутся в файлы экспорта
+            let exports = sorter_guard.take_exports();
+            drop(sorter_guard);
+            if let Some((export_tap, records)) = exports {
+                for record in records {
+                    export_tap.export(record).await;
+                }
+            }
+        }
+    }
+
+    /// Периодически формирует отчет по счетчикам сортировщика
+    async fn report_statistics(
+        sorter: Arc<Mutex<PSorter>>,
+        interval: Duration,
+        mut reporter: StatsReporter,
+    ) {
+        reporter.start(sorter.lock().await.stats());
+
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;  // Первый тик срабатывает сразу
+        loop {
+            ticker.tick().await;
+            let stats = sorter.lock().await.stats();
+            if let Err(e) = reporter.report(stats) {
+                warn!("Failed to write statistics report: {}", e);
+            }
         }
     }
 
-    /// Обрабатывает входящие команды (в настоящее время только логирует)
-    async fn handle_commands(mut command_receiver: crate::channels::CommandReceiver) {
+    /// Периодически проверяет время последнего обновления параметров
+    /// и публикует события stale/recovered
+    async fn run_watchdog(
+        mut watchdog: Watchdog,
+        state_store: SharedStateStore,
+        interval: Duration,
+        events: EventPublisher,
+    ) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let snapshot = state_store.snapshot();
+            for event in watchdog.check(&snapshot, chrono::Utc::now()) {
+                warn!(
+                    event = event.event,
+                    bm = event.key.module_addr_bm,
+                    mcu = event.key.module_addr_mcu,
+                    dev_id = event.key.dev_id,
+                    src_id = event.key.src_id,
+                    prm_id = event.key.prm_id,
+                    silent_secs = format!("{:.1}", event.silent_secs),
+                    "Parameter watchdog event"
+                );
+                events.publish(&event);
+            }
+        }
+    }
+
+    /// Сохраняет историю аппаратных аварийных битов, если она изменилась
+    async fn save_hw_alarms(hw_alarms: SharedHwAlarms, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let mut tracker = hw_alarms.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if let Err(e) = tracker.save_if_dirty() {
+                warn!("Failed to save hardware alarm history: {}", e);
+            }
+        }
+    }
+
+    /// Записывает входящие команды в UART через PWriter
+    async fn handle_commands(mut command_receiver: CommandReceiver, p_writer: Arc<PWriter>) {
         while let Some(command) = command_receiver.recv().await {
-            println!("Received command to write: {} bytes", command.len());
-            // Здесь будет логика отправки команд через PWriter
-            // В текущей реализации команды только логируются
+            debug!(size = command.len(), "Received command to write");
+            if let Err(e) = p_writer.write_command(&command).await {
+                error!("Failed to write command: {}", e);
+            }
         }
     }
 
-    /// Ожидает завершения всех асинхронных задач
+    /// Ожидает завершения чтения и обработки всех пакетов
     pub async fn wait_for_completion(&mut self) {
-        // Ждем завершения всех задач
-        for task in std::mem::take(&mut self.tasks) {
+        // Закрываем собственный отправитель контроллера: канал закроется,
+        // когда читатели завершатся, и обработчик пакетов выйдет из цикла
+        let (closed_sender, _) = package_channel();
+        drop(std::mem::replace(&mut self.package_sender, closed_sender));
+
+        // Ждем завершения задач конвейера
+        for task in std::mem::take(&mut self.pipeline_tasks) {
             let _ = task.await;  // Игнорируем результат, так как задачи возвращают ()
         }
     }
 
+    /// Завершает работу: сохраняет историю аппаратных тревог и сбрасывает экспорт
+    /// (накопленные строки записываются или откладываются до следующего запуска)
+    pub async fn shutdown(&mut self) {
+        let hw_alarms = self.p_sorter.lock().await.hw_alarms();
+        let saved = hw_alarms.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).save_if_dirty();
+        if let Err(e) = saved {
+            warn!("Failed to save hardware alarm history: {}", e);
+        }
+        if let Some(influx) = self.influx.take() {
+            influx.finish(EXPORT_FINISH_TIMEOUT).await;
+        }
+    }
+
     /// Выводит финальную статистику работы системы
     pub async fn print_statistics(&self) {
         let sorter_guard = self.p_sorter.lock().await;
@@ -312,7 +558,34 @@ impl Controller {
         println!("Total packages processed: {}", sorter_guard.input_package_counter());      // Всего обработано пакетов
         println!("CRC correct: {}", sorter_guard.crc_correct_counter());                     // Пакетов с корректным CRC
         println!("CRC incorrect: {}", sorter_guard.crc_incorrect_counter());                 // Пакетов с некорректным CRC
-        println!("Packages sent to TMonitor: {}", sorter_guard.send_pack_forTmon_counter()); // Пакетов отправлено в TMonitor
+        println!("Packages sent to TMonitor: {}", sorter_guard.send_pack_for_tmon_counter()); // Пакетов отправлено в TMonitor
+        for (kind, count) in sorter_guard.stats().parse_errors {
+            println!("Parse errors ({}): {}", kind, count);                                  // Пакетов, не прошедших разбор
+        }
+        for (crc_match, count) in sorter_guard.stats().crc_matches {
+            println!("CRC diagnostics ({}): {}", crc_match, count);                          // Подобранные варианты CRC
+        }
+
+        // Таблица по устройствам; устройства с ошибками CRC помечаются "!"
+        let devices = sorter_guard.device_stats().lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entries();
+        if !devices.is_empty() {
+            println!("------------------------------------------------");
+            println!("PER-DEVICE STATISTICS:");
+            println!("  {:<8} {:>3} {:>4} {:>4} {:>10} {:>9} {:>7}  Last seen",
+                     "Source", "BM", "MCU", "DEV", "Packages", "CRC fail", "Params");
+            for device in devices {
+                let last_seen = device.last_seen
+                    .map(|time| time.with_timezone(&chrono::Local).format("%H:%M:%S%.3f").to_string())
+                    .unwrap_or_else(|| "-".to_string());
+                println!("{} {:<8} {:>3} {:>4} {:>4} {:>10} {:>9} {:>7}  {}",
+                         if device.crc_failures > 0 { "!" } else { " " },
+                         device.key.source, device.key.module_addr_bm, device.key.module_addr_mcu,
+                         device.key.dev_id, device.packages, device.crc_failures,
+                         device.parameters.len(), last_seen);
+            }
+        }
         println!("================================================");
     }
 }
@@ -321,7 +594,7 @@ impl Controller {
 impl Drop for Controller {
     fn drop(&mut self) {
         // При уничтожении контроллера прерываем все асинхронные задачи
-        for task in &self.tasks {
+        for task in self.pipeline_tasks.iter().chain(self.tasks.iter()) {
             task.abort();
         }
     }
@@ -0,0 +1,588 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use std::fs;
+use std::io::{BufWriter, Write};
+use tracing::{info, warn};
+
+use crate::include::byte_stuffing;
+use crate::inspect;
+use crate::package_types::PACKAGE_TYPE_VALUES;
+use crate::protocol::CrcCheck;
+use crate::psorter::PSorter;
+use crate::record::FrameRecord;
+
+/// Разделитель кадров в потоке
+const DELIMITER: u8 = 0xC0;
+
+/// Тип канального уровня pcapng для кадров HWMon (LINKTYPE_USER0)
+const PCAPNG_LINKTYPE: u16 = 147;
+/// Типы блоков pcapng
+const PCAPNG_SECTION_HEADER: u32 = 0x0A0D_0D0A;
+const PCAPNG_INTERFACE: u32 = 0x0000_0001;
+const PCAPNG_SIMPLE_PACKET: u32 = 0x0000_0003;
+const PCAPNG_ENHANCED_PACKET: u32 = 0x0000_0006;
+/// Маркер порядка байтов секции pcapng
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+/// Опция интерфейса pcapng: разрешение меток времени
+const PCAPNG_OPTION_TSRESOL: u16 = 9;
+
+/// Формат захвата
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaptureFormat {
+    /// Поток кадров с разделителями 0xC0 (формат режима DUMP)
+    Raw,
+    /// Текстовый захват: строка "<время RFC 3339> <кадр hex>" на кадр
+    Capture,
+    /// pcapng: пакет на кадр (LINKTYPE_USER0), метки времени в микросекундах
+    Pcapng,
+    /// Строка hex на кадр (в том числе строки логов "UART packet sent")
+    Hex,
+    /// JSON-строки с декодированными записями
+    Jsonl,
+    /// CSV с декодированными записями
+    Csv,
+}
+
+impl CaptureFormat {
+    /// Разбирает формат из аргумента командной строки
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "raw" | "dump" => Ok(CaptureFormat::Raw),
+            "capture" => Ok(CaptureFormat::Capture),
+            "pcapng" => Ok(CaptureFormat::Pcapng),
+            "hex" => Ok(CaptureFormat::Hex),
+            "jsonl" | "json" => Ok(CaptureFormat::Jsonl),
+            "csv" => Ok(CaptureFormat::Csv),
+            other => Err(anyhow::anyhow!(
+                "Unknown capture format: {} (expected raw/capture/pcapng/hex/jsonl/csv)", other
+            )),
+        }
+    }
+
+    /// Определяет формат по расширению файла (неизвестное расширение - raw)
+    pub fn from_path(path: &str) -> Self {
+        let extension = path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default();
+        match extension.as_str() {
+            "cap" | "capture" => CaptureFormat::Capture,
+            "pcapng" => CaptureFormat::Pcapng,
+            "hex" | "txt" | "log" => CaptureFormat::Hex,
+            "jsonl" | "json" => CaptureFormat::Jsonl,
+            "csv" => CaptureFormat::Csv,
+            _ => CaptureFormat::Raw,
+        }
+    }
+
+    /// Содержит ли формат метки времени для каждого кадра
+    fn has_timestamps(self) -> bool {
+        matches!(self, CaptureFormat::Capture | CaptureFormat::Pcapng)
+    }
+}
+
+/// Параметры преобразования
+#[derive(Debug, Clone)]
+pub struct ConvertOptions {
+    pub from: CaptureFormat,         // Формат входного файла
+    pub to: CaptureFormat,           // Формат выходного файла
+    pub start: DateTime<Utc>,        // Время первого кадра без метки времени
+    pub interval: Duration,          // Интервал между кадрами без меток времени
+}
+
+/// Кадр захвата: время приема (если известно) и кадр как в потоке (со стаффингом, без разделителей)
+#[derive(Debug, Clone)]
+pub struct CapturedFrame {
+    pub timestamp: Option<DateTime<Utc>>,
+    pub frame: Vec<u8>,
+}
+
+/// Преобразует захват из одного формата в другой
+/// Кадры восстанавливаются в виде потока: для JSON-строк и CSV - из поля destuffed
+/// или заново по словам заголовка и сырым значениям (с CRC текущего профиля)
+pub fn run(input: &str, output: &str, options: &ConvertOptions, mut sorter: PSorter) -> Result<()> {
+    let mut frames = read_frames(input, options.from, sorter.crc_check())?;
+
+    // Кадрам без меток времени назначается время start + index * interval
+    if options.to.has_timestamps() {
+        let mut timestamp = options.start;
+        for frame in frames.iter_mut() {
+            timestamp = frame.timestamp.unwrap_or(timestamp);
+            frame.timestamp = Some(timestamp);
+            timestamp += options.interval;
+        }
+    }
+
+    let file = fs::File::create(output).context(format!("Failed to create output file: {}", output))?;
+    let mut out = BufWriter::new(file);
+    match options.to {
+        CaptureFormat::Raw | CaptureFormat::Capture | CaptureFormat::Pcapng | CaptureFormat::Hex => {
+            write_header(&mut out, options.to)?;
+            for captured in &frames {
+                write_frame(&mut out, options.to, captured)?;
+            }
+        }
+        CaptureFormat::Jsonl => {
+            for record in decode(&frames, &mut sorter) {
+                writeln!(out, "{}", serde_json::to_string(&record)?)?;
+            }
+        }
+        CaptureFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(&mut out);
+            for record in decode(&frames, &mut sorter) {
+                writer.serialize(record)?;
+            }
+            writer.flush()?;
+        }
+    }
+    out.flush().context(format!("Failed to write output file: {}", output))?;
+
+    info!(
+        input,
+        output,
+        from = ?options.from,
+        to = ?options.to,
+        frames = frames.len(),
+        "Capture converted"
+    );
+    Ok(())
+}
+
+/// Читает кадры захвата в формате format
+/// Пакеты, собираемые заново из записей JSON-строк и CSV, получают CRC crc_check
+pub fn read_frames(path: &str, format: CaptureFormat, crc_check: CrcCheck) -> Result<Vec<CapturedFrame>> {
+    let data = fs::read(path).context(format!("Failed to read capture: {}", path))?;
+    match format {
+        CaptureFormat::Raw => Ok(read_raw(&data)),
+        CaptureFormat::Capture => read_capture(&text(&data, path)?),
+        CaptureFormat::Pcapng => read_pcapng(&data),
+        CaptureFormat::Hex => read_hex(&text(&data, path)?),
+        CaptureFormat::Jsonl => {
+            let records = text(&data, path)?.lines()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(index, line)| {
+                    serde_json::from_str::<FrameRecord>(line)
+                        .context(format!("Invalid JSON record at line {}", index + 1))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            frames_from_records(records, crc_check)
+        }
+        CaptureFormat::Csv => {
+            let records = csv::Reader::from_reader(data.as_slice())
+                .deserialize::<FrameRecord>()
+                .collect::<Result<Vec<_>, _>>()
+                .context("Invalid CSV record")?;
+            frames_from_records(records, crc_check)
+        }
+    }
+}
+
+/// Пишет начало захвата (для pcapng - секция и интерфейс)
+/// JSON-строки и CSV требуют декодирования и кадр за кадром не пишутся
+pub fn write_header(out: &mut impl Write, format: CaptureFormat) -> Result<()> {
+    match format {
+        CaptureFormat::Pcapng => write_pcapng_header(out),
+        CaptureFormat::Raw | CaptureFormat::Capture | CaptureFormat::Hex => Ok(()),
+        CaptureFormat::Jsonl | CaptureFormat::Csv => Err(anyhow::anyhow!(
+            "Format {:?} holds decoded records, expected raw/capture/pcapng/hex", format
+        )),
+    }
+}
+
+/// Пишет один кадр захвата (после write_header)
+pub fn write_frame(out: &mut impl Write, format: CaptureFormat, captured: &CapturedFrame) -> Result<()> {
+    match format {
+        CaptureFormat::Raw => write_raw(out, captured),
+        CaptureFormat::Capture => write_capture(out, captured),
+        CaptureFormat::Pcapng => write_pcapng_packet(out, captured),
+        CaptureFormat::Hex => write_hex(out, captured),
+        CaptureFormat::Jsonl | CaptureFormat::Csv => write_header(out, format),
+    }
+}
+
+/// Содержимое текстового формата
+fn text(data: &[u8], path: &str) -> Result<String> {
+    String::from_utf8(data.to_vec()).context(format!("Capture is not valid UTF-8 text: {}", path))
+}
+
+/// Декодирует кадры в записи (как в режиме INSPECT)
+fn decode(frames: &[CapturedFrame], sorter: &mut PSorter) -> Vec<FrameRecord> {
+    let param_dict = sorter.param_dict();
+    frames.iter()
+        .enumerate()
+        .flat_map(|(index, captured)| {
+            let frame = sorter.inspect_package(&captured.frame);
+            let records: Vec<_> = frame.records.iter().collect();
+            FrameRecord::from_frame(index + 1, captured.timestamp, &frame, &records, &param_dict)
+        })
+        .collect()
+}
+
+/// Читает поток кадров с разделителями 0xC0
+fn read_raw(data: &[u8]) -> Vec<CapturedFrame> {
+    inspect::split_frames(data)
+        .map(|frame| CapturedFrame { timestamp: None, frame: frame.to_vec() })
+        .collect()
+}
+
+/// Пишет кадр потока, завершая его разделителем 0xC0
+fn write_raw(out: &mut impl Write, captured: &CapturedFrame) -> Result<()> {
+    out.write_all(&captured.frame)?;
+    out.write_all(&[DELIMITER])?;
+    Ok(())
+}
+
+/// Читает текстовый захват: "<время RFC 3339> <кадр hex>"
+fn read_capture(text: &str) -> Result<Vec<CapturedFrame>> {
+    let mut frames = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (timestamp, frame) = line.split_once(char::is_whitespace)
+            .context(format!("Capture line {}: expected '<timestamp> <hex>'", index + 1))?;
+        let timestamp = DateTime::parse_from_rfc3339(timestamp)
+            .context(format!("Capture line {}: invalid timestamp", index + 1))?;
+        let frame = hex::decode(frame.trim())
+            .context(format!("Capture line {}: invalid hex", index + 1))?;
+        frames.push(CapturedFrame { timestamp: Some(timestamp.with_timezone(&Utc)), frame });
+    }
+    Ok(frames)
+}
+
+/// Пишет строку текстового захвата
+fn write_capture(out: &mut impl Write, captured: &CapturedFrame) -> Result<()> {
+    let timestamp = captured.timestamp.unwrap_or_default();
+    writeln!(out, "{} {}", timestamp.to_rfc3339_opts(chrono::SecondsFormat::Micros, true), hex::encode(&captured.frame))?;
+    Ok(())
+}
+
+/// Читает строки hex: голые кадры, строки "UART packet sent: <hex>" (println! старых версий),
+/// строки логов с полем package=<hex> и JSON-строки логов (--log-format json) с полем package;
+/// метка времени строки лога сохраняется
+fn read_hex(text: &str) -> Result<Vec<CapturedFrame>> {
+    let mut frames = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = strip_ansi(line);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('{') {
+            frames.extend(read_json_log_line(line, index)?);
+            continue;
+        }
+
+        let hex_text = if let Some((_, rest)) = line.split_once("UART packet sent: ") {
+            rest.trim()
+        } else if let Some((_, rest)) = line.split_once("package=") {
+            rest.split_whitespace().next().unwrap_or_default()
+        } else if line.contains(char::is_whitespace) {
+            // Строки лога без кадра пропускаются
+            continue;
+        } else {
+            line
+        };
+        let frame = hex::decode(hex_text).context(format!("Hex line {}: invalid hex", index + 1))?;
+
+        let timestamp = line.split_whitespace()
+            .next()
+            .and_then(|token| DateTime::parse_from_rfc3339(token).ok())
+            .map(|timestamp| timestamp.with_timezone(&Utc));
+        frames.push(CapturedFrame { timestamp, frame });
+    }
+    Ok(frames)
+}
+
+/// Кадр из JSON-строки лога: поле package на верхнем уровне или в fields
+/// Строки лога без кадра пропускаются
+fn read_json_log_line(line: &str, index: usize) -> Result<Option<CapturedFrame>> {
+    let entry: serde_json::Value = serde_json::from_str(line)
+        .context(format!("Hex line {}: invalid JSON log line", index + 1))?;
+    let package = entry.get("package")
+        .or_else(|| entry.get("fields").and_then(|fields| fields.get("package")))
+        .and_then(|package| package.as_str());
+    let Some(package) = package else {
+        return Ok(None);
+    };
+    let frame = hex::decode(package).context(format!("Hex line {}: invalid hex", index + 1))?;
+    let timestamp = entry.get("timestamp")
+        .and_then(|timestamp| timestamp.as_str())
+        .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+        .map(|timestamp| timestamp.with_timezone(&Utc));
+    Ok(Some(CapturedFrame { timestamp, frame }))
+}
+
+/// Пишет строку hex с кадром
+fn write_hex(out: &mut impl Write, captured: &CapturedFrame) -> Result<()> {
+    writeln!(out, "{}", hex::encode(&captured.frame))?;
+    Ok(())
+}
+
+/// Удаляет управляющие последовательности цвета (ESC [ ... m) из строки лога
+fn strip_ansi(line: &str) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Восстанавливает кадры из декодированных записей
+/// Записи одного кадра (с одинаковым номером) дают один кадр
+fn frames_from_records(records: Vec<FrameRecord>, crc_check: CrcCheck) -> Result<Vec<CapturedFrame>> {
+    let mut frames = Vec::new();
+    let mut last_frame = None;
+    for record in records {
+        if last_frame == Some(record.frame) {
+            continue;
+        }
+        last_frame = Some(record.frame);
+
+        let mut destuffed = if !record.destuffed.is_empty() {
+            hex::decode(&record.destuffed).context(format!("Frame {}: invalid destuffed hex", record.frame))?
+        } else {
+            match encode_values(&record, crc_check) {
+                Some(frame) => frame,
+                None => {
+                    warn!(frame = record.frame, "Record without frame bytes and raw values skipped");
+                    continue;
+                }
+            }
+        };
+        byte_stuffing::request_byte_stuffing(&mut destuffed);
+        frames.push(CapturedFrame { timestamp: record.timestamp, frame: destuffed });
+    }
+    Ok(frames)
+}
+
+/// Собирает пакет с одним значением по словам заголовка и сырым значениям записи
+fn encode_values(record: &FrameRecord, crc_check: CrcCheck) -> Option<Vec<u8>> {
+    let words = [
+        record.addr?,
+        record.package_type.unwrap_or(PACKAGE_TYPE_VALUES),
+        record.src?,
+        record.data_type?,
+        record.prm?,
+        record.prm_max.or(record.prm)?,
+        record.prm_min.or(record.prm)?,
+    ];
+    let mut package: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+    crc_check.append(&mut package);
+    Some(package)
+}
+
+/// Читает pcapng: блоки EPB и SPB, порядок байтов и разрешение времени по секции и интерфейсу
+fn read_pcapng(data: &[u8]) -> Result<Vec<CapturedFrame>> {
+    let mut frames = Vec::new();
+    let mut big_endian = false;
+    let mut resolutions: Vec<u64> = Vec::new();    // Тиков в секунду по интерфейсам
+    let mut offset = 0;
+
+    while offset + 12 <= data.len() {
+        let block_type = read_u32(&data[offset..], big_endian);
+        if block_type == PCAPNG_SECTION_HEADER {
+            // Порядок байтов секции определяется по маркеру после длины блока
+            let magic = data.get(offset + 8..offset + 12).context("Truncated pcapng section header")?;
+            big_endian = match u32::from_le_bytes(magic.try_into()?) {
+                PCAPNG_BYTE_ORDER_MAGIC => false,
+                magic if magic.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => true,
+                _ => return Err(anyhow::anyhow!("Invalid pcapng byte-order magic")),
+            };
+            resolutions.clear();
+        }
+        let block_len = read_u32(&data[offset + 4..], big_endian) as usize;
+        if block_len < 12 || offset + block_len > data.len() {
+            return Err(anyhow::anyhow!("Invalid pcapng block length {} at offset {}", block_len, offset));
+        }
+        let body = &data[offset + 8..offset + block_len - 4];
+
+        match block_type {
+            PCAPNG_INTERFACE => resolutions.push(interface_resolution(body, big_endian)),
+            PCAPNG_ENHANCED_PACKET if body.len() >= 20 => {
+                let interface = read_u32(body, big_endian) as usize;
+                let ticks = ((read_u32(&body[4..], big_endian) as u64) << 32) | read_u32(&body[8..], big_endian) as u64;
+                let captured_len = read_u32(&body[12..], big_endian) as usize;
+                let packet = body.get(20..20 + captured_len).context("Truncated pcapng packet")?;
+                let resolution = resolutions.get(interface).copied().unwrap_or(1_000_000);
+                let nanos = (ticks as u128 * 1_000_000_000 / resolution as u128) as i64;
+                frames.push(CapturedFrame { timestamp: Some(Utc.timestamp_nanos(nanos)), frame: packet.to_vec() });
+            }
+            PCAPNG_SIMPLE_PACKET if body.len() >= 4 => {
+                let packet_len = read_u32(body, big_endian) as usize;
+                let packet = &body[4..(4 + packet_len).min(body.len())];
+                frames.push(CapturedFrame { timestamp: None, frame: packet.to_vec() });
+            }
+            _ => {}
+        }
+        offset += block_len;
+    }
+    Ok(frames)
+}
+
+/// Разрешение меток времени интерфейса (опция if_tsresol), тиков в секунду
+fn interface_resolution(body: &[u8], big_endian: bool) -> u64 {
+    let read_u16 = |bytes: &[u8]| {
+        let bytes = [bytes[0], bytes[1]];
+        if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) }
+    };
+    // Опции идут после linktype, reserved и snaplen
+    let mut offset = 8;
+    while offset + 4 <= body.len() {
+        let code = read_u16(&body[offset..]);
+        let len = read_u16(&body[offset + 2..]) as usize;
+        if code == 0 {
+            break;
+        }
+        if code == PCAPNG_OPTION_TSRESOL && len >= 1 && offset + 4 < body.len() {
+            let value = body[offset + 4];
+            // Старший бит: степень двойки, иначе степень десяти
+            let exponent = (value & 0x7F) as u32;
+            return if value & 0x80 != 0 { 2u64.saturating_pow(exponent) } else { 10u64.saturating_pow(exponent) };
+        }
+        offset += 4 + len.div_ceil(4) * 4;
+    }
+    1_000_000
+}
+
+/// Читает u32 в порядке байтов секции
+fn read_u32(bytes: &[u8], big_endian: bool) -> u32 {
+    let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) }
+}
+
+/// Пишет начало pcapng: секция и интерфейс LINKTYPE_USER0
+fn write_pcapng_header(out: &mut impl Write) -> Result<()> {
+    let mut section = Vec::new();
+    section.extend(PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes());
+    section.extend(1u16.to_le_bytes());              // Версия 1.0
+    section.extend(0u16.to_le_bytes());
+    section.extend((-1i64).to_le_bytes());           // Длина секции не указана
+    write_block(out, PCAPNG_SECTION_HEADER, &section)?;
+
+    let mut interface = Vec::new();
+    interface.extend(PCAPNG_LINKTYPE.to_le_bytes());
+    interface.extend(0u16.to_le_bytes());
+    interface.extend(0u32.to_le_bytes());            // snaplen не ограничен
+    write_block(out, PCAPNG_INTERFACE, &interface)
+}
+
+/// Пишет пакет EPB pcapng с кадром как в потоке
+fn write_pcapng_packet(out: &mut impl Write, captured: &CapturedFrame) -> Result<()> {
+    let micros = captured.timestamp.unwrap_or_default().timestamp_micros() as u64;
+    let mut packet = Vec::with_capacity(20 + captured.frame.len());
+    packet.extend(0u32.to_le_bytes());               // Интерфейс 0
+    packet.extend(((micros >> 32) as u32).to_le_bytes());
+    packet.extend((micros as u32).to_le_bytes());
+    packet.extend((captured.frame.len() as u32).to_le_bytes());
+    packet.extend((captured.frame.len() as u32).to_le_bytes());
+    packet.extend(&captured.frame);
+    write_block(out, PCAPNG_ENHANCED_PACKET, &packet)
+}
+
+/// Пишет блок pcapng с выравниванием тела до 4 байтов
+fn write_block(out: &mut impl Write, block_type: u32, body: &[u8]) -> Result<()> {
+    let padding = body.len().next_multiple_of(4) - body.len();
+    let block_len = (12 + body.len() + padding) as u32;
+    out.write_all(&block_type.to_le_bytes())?;
+    out.write_all(&block_len.to_le_bytes())?;
+    out.write_all(body)?;
+    out.write_all(&[0u8; 3][..padding])?;
+    out.write_all(&block_len.to_le_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames() -> Vec<CapturedFrame> {
+        let start = Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap();
+        vec![
+            CapturedFrame { timestamp: Some(start), frame: vec![0x01, 0xDB, 0xDC, 0x02] },
+            CapturedFrame { timestamp: Some(start + Duration::microseconds(1500)), frame: vec![0x03; 7] },
+        ]
+    }
+
+    fn assert_same(left: &[CapturedFrame], right: &[CapturedFrame]) {
+        assert_eq!(left.len(), right.len());
+        for (left, right) in left.iter().zip(right) {
+            assert_eq!(left.frame, right.frame);
+            assert_eq!(left.timestamp, right.timestamp);
+        }
+    }
+
+    fn write(format: CaptureFormat) -> Vec<u8> {
+        let mut data = Vec::new();
+        write_header(&mut data, format).unwrap();
+        for captured in &frames() {
+            write_frame(&mut data, format, captured).unwrap();
+        }
+        data
+    }
+
+    #[test]
+    fn pcapng_round_trip() {
+        let data = write(CaptureFormat::Pcapng);
+        assert_same(&read_pcapng(&data).unwrap(), &frames());
+    }
+
+    #[test]
+    fn capture_round_trip() {
+        let data = write(CaptureFormat::Capture);
+        assert_same(&read_capture(&String::from_utf8(data).unwrap()).unwrap(), &frames());
+    }
+
+    #[test]
+    fn hex_log_lines() {
+        let text = "UART read 12 bytes\n\
+                    UART packet sent: 01dbdc02\n\
+                    2026-01-02T03:04:05Z DEBUG hwmon::preader: UART packet sent package=\u{1b}[0m0303\n";
+        let frames = read_hex(text).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].frame, vec![0x01, 0xDB, 0xDC, 0x02]);
+        assert_eq!(frames[1].frame, vec![0x03, 0x03]);
+        assert!(frames[1].timestamp.is_some());
+    }
+
+    #[test]
+    fn hex_json_log_lines() {
+        let text = concat!(
+            r#"{"timestamp":"2026-01-02T03:04:05.5Z","level":"INFO","message":"UART mode started","target":"hwmon::controller"}"#, "\n",
+            r#"{"timestamp":"2026-01-02T03:04:06Z","level":"DEBUG","message":"UART packet sent","package":"01dbdc02","target":"hwmon::preader"}"#, "\n",
+            r#"{"timestamp":"2026-01-02T03:04:07Z","level":"WARN","fields":{"message":"CRC incorrect for package","package":"0303"}}"#, "\n",
+        );
+        let frames = read_hex(text).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].frame, vec![0x01, 0xDB, 0xDC, 0x02]);
+        assert_eq!(frames[0].timestamp.unwrap().to_rfc3339(), "2026-01-02T03:04:06+00:00");
+        assert_eq!(frames[1].frame, vec![0x03, 0x03]);
+
+        assert!(read_hex(r#"{"package":"0g"}"#).is_err());
+    }
+
+    #[test]
+    fn values_reencoded_with_crc() {
+        let record = FrameRecord {
+            frame: 1,
+            addr: Some(0x0101),
+            src: Some(0x1001),
+            data_type: Some(0x000a),
+            prm: Some(0xea6e),
+            ..FrameRecord::default()
+        };
+        let crc_check = CrcCheck::default();
+        let frames = frames_from_records(vec![record], crc_check).unwrap();
+        let destuffed = byte_stuffing::byte_stuffing(&frames[0].frame);
+        assert_eq!(destuffed.len(), 16);
+        assert!(crc_check.validate(&destuffed));
+    }
+}
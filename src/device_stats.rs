@@ -0,0 +1,135 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+
+/// Разделяемая таблица статистики устройств (сортировщик пишет, HTTP и отчеты читают)
+pub type SharedDeviceStats = Arc<Mutex<DeviceStatsTable>>;
+
+/// Ключ устройства: источник, адрес BM, адрес MCU и идентификатор устройства (FPGA)
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct DeviceKey {
+    pub source: String,          // Источник пакетов (uart, dump, ...)
+    pub module_addr_bm: u8,      // Адрес BM (4 бита)
+    pub module_addr_mcu: u8,     // Адрес MCU (3 бита)
+    pub dev_id: u8,              // Идентификатор устройства (7 бит)
+}
+
+/// Счетчики одного устройства
+#[derive(Debug, Clone, Default)]
+pub struct DeviceStats {
+    pub packages: u64,                    // Пакетов с корректным CRC
+    pub crc_failures: u64,                // Пакетов с некорректным CRC
+    pub parameters: BTreeSet<u16>,        // Встреченные идентификаторы параметров
+    pub last_seen: Option<DateTime<Utc>>, // Время последнего пакета
+}
+
+/// Запись таблицы устройств для выдачи наружу (JSON)
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceStatsEntry {
+    #[serde(flatten)]
+    pub key: DeviceKey,
+    pub packages: u64,
+    pub crc_failures: u64,
+    pub crc_failure_ratio: f64,
+    pub parameters: Vec<u16>,
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+/// Статистика по всем устройствам
+#[derive(Debug, Default)]
+pub struct DeviceStatsTable {
+    devices: BTreeMap<DeviceKey, DeviceStats>,
+}
+
+impl DeviceStatsTable {
+    /// Создает пустую разделяемую таблицу
+    pub fn shared() -> SharedDeviceStats {
+        Arc::new(Mutex::new(Self::default()))
+    }
+
+    /// Учитывает пакет с корректным CRC
+    pub fn record_package(&mut self, key: DeviceKey, prm_ids: impl IntoIterator<Item = u16>) {
+        let stats = self.devices.entry(key).or_default();
+        stats.packages += 1;
+        stats.parameters.extend(prm_ids);
+        stats.last_seen = Some(Utc::now());
+    }
+
+    /// Учитывает пакет с некорректным CRC
+    pub fn record_crc_failure(&mut self, key: DeviceKey) {
+        let stats = self.devices.entry(key).or_default();
+        stats.crc_failures += 1;
+        stats.last_seen = Some(Utc::now());
+    }
+
+    /// Возвращает снимок таблицы в порядке ключей
+    pub fn entries(&self) -> Vec<DeviceStatsEntry> {
+        self.devices.iter()
+            .map(|(key, stats)| {
+                let total = stats.packages + stats.crc_failures;
+                DeviceStatsEntry {
+                    key: key.clone(),
+                    packages: stats.packages,
+                    crc_failures: stats.crc_failures,
+                    crc_failure_ratio: if total > 0 {
+                        stats.crc_failures as f64 / total as f64
+                    } else {
+                        0.0
+                    },
+                    parameters: stats.parameters.iter().copied().collect(),
+                    last_seen: stats.last_seen,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(bm: u8, dev_id: u8) -> DeviceKey {
+        DeviceKey { source: String::from("dump"), module_addr_bm: bm, module_addr_mcu: 1, dev_id }
+    }
+
+    #[test]
+    fn counts_per_device() {
+        let mut table = DeviceStatsTable::default();
+        let before = Utc::now();
+        table.record_package(key(2, 1), [10, 11]);
+        table.record_package(key(2, 1), [11, 12]);
+        table.record_crc_failure(key(2, 1));
+        table.record_package(key(1, 3), [20]);
+        table.record_crc_failure(key(3, 3));
+
+        let entries = table.entries();
+        // Порядок ключей: BM 1, 2, 3
+        let keys: Vec<(u8, u8)> = entries.iter().map(|entry| (entry.key.module_addr_bm, entry.key.dev_id)).collect();
+        assert_eq!(keys, [(1, 3), (2, 1), (3, 3)]);
+
+        let device = &entries[1];
+        assert_eq!(device.packages, 2);
+        assert_eq!(device.crc_failures, 1);
+        assert!((device.crc_failure_ratio - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(device.parameters, [10, 11, 12]);
+        assert!(device.last_seen.unwrap() >= before);
+
+        // Устройство, известное только по ошибкам CRC
+        let failed = &entries[2];
+        assert_eq!(failed.packages, 0);
+        assert_eq!(failed.crc_failure_ratio, 1.0);
+        assert!(failed.parameters.is_empty());
+    }
+
+    #[test]
+    fn last_seen_advances() {
+        let mut table = DeviceStatsTable::default();
+        table.record_package(key(2, 1), [10]);
+        let first = table.entries()[0].last_seen.unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        table.record_crc_failure(key(2, 1));
+        let second = table.entries()[0].last_seen.unwrap();
+        assert!(second > first);
+    }
+}
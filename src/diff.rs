@@ -0,0 +1,457 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufWriter, Write};
+
+use crate::convert::{self, CaptureFormat};
+use crate::inspect::ReportFormat;
+use crate::psorter::{route_name, PSorter};
+use crate::record::{device_name, param_name, DeviceKey};
+
+/// Сдвиг среднего (в стандартных отклонениях), начиная с которого распределение считается изменившимся
+const SHIFT_SIGMA: f64 = 1.0;
+
+/// Относительное изменение частоты обновления, начиная с которого о нем сообщается
+const RATE_CHANGE_RATIO: f64 = 0.2;
+
+/// Профиль параметра в захвате (по кадрам с корректным CRC)
+#[derive(Debug, Default)]
+struct ParamProfile {
+    count: u64,                              // Записей параметра
+    values: Vec<f64>,                        // Декодированные числовые значения
+    routes: BTreeMap<&'static str, u64>,     // Записей по маршрутам
+}
+
+/// Профиль захвата
+#[derive(Debug)]
+struct CaptureProfile {
+    path: String,                                        // Путь к захвату
+    frames: u64,                                         // Кадров
+    crc_fail: u64,                                       // Кадров с ошибкой CRC
+    parse_errors: u64,                                   // Кадров с корректным CRC, не прошедших разбор
+    duration_secs: Option<f64>,                          // Длительность по меткам времени
+    devices: BTreeSet<DeviceKey>,                        // Устройства
+    params: BTreeMap<(DeviceKey, u16), ParamProfile>,    // Параметры устройств
+}
+
+impl CaptureProfile {
+    /// Декодирует захват и собирает профиль
+    fn load(path: &str, format: CaptureFormat, mut sorter: PSorter) -> Result<Self> {
+        let frames = convert::read_frames(path, format, sorter.crc_check())?;
+        let mut profile = Self {
+            path: path.to_string(),
+            frames: frames.len() as u64,
+            crc_fail: 0,
+            parse_errors: 0,
+            duration_secs: None,
+            devices: BTreeSet::new(),
+            params: BTreeMap::new(),
+        };
+
+        let timestamps: Vec<_> = frames.iter().filter_map(|captured| captured.timestamp).collect();
+        if let (Some(first), Some(last)) = (timestamps.iter().min(), timestamps.iter().max()) {
+            let secs = (*last - *first).num_microseconds().unwrap_or(i64::MAX) as f64 / 1e6;
+            profile.duration_secs = (secs > 0.0).then_some(secs);
+        }
+
+        for captured in &frames {
+            let frame = sorter.inspect_package(&captured.frame);
+            if !frame.crc_ok {
+                profile.crc_fail += 1;
+                continue;
+            }
+            if frame.error.is_some() {
+                profile.parse_errors += 1;
+            }
+            let route = frame.route.map(route_name).unwrap_or("none");
+            for record in &frame.records {
+                let device = (record.module_addr_bm, record.module_addr_mcu, record.dev_id);
+                profile.devices.insert(device);
+                let param = profile.params.entry((device, record.prm_id)).or_default();
+                param.count += 1;
+                *param.routes.entry(route).or_insert(0) += 1;
+                if record.value_text.is_none() && record.value.is_finite() {
+                    param.values.push(record.value as f64);
+                }
+            }
+        }
+        Ok(profile)
+    }
+
+    /// Доля кадров с ошибкой CRC
+    fn crc_error_rate(&self) -> f64 {
+        if self.frames == 0 { 0.0 } else { self.crc_fail as f64 / self.frames as f64 }
+    }
+
+    /// Частота обновления параметра: в секунду или на 1000 кадров
+    fn rate(&self, count: u64, per_second: bool) -> f64 {
+        match self.duration_secs {
+            Some(secs) if per_second => count as f64 / secs,
+            _ if self.frames > 0 => count as f64 * 1000.0 / self.frames as f64,
+            _ => 0.0,
+        }
+    }
+
+    /// Сводка по захвату для отчета
+    fn summary(&self) -> CaptureSummary {
+        CaptureSummary {
+            path: self.path.clone(),
+            frames: self.frames,
+            crc_fail: self.crc_fail,
+            crc_error_rate: self.crc_error_rate(),
+            parse_errors: self.parse_errors,
+            duration_secs: self.duration_secs,
+            devices: self.devices.len(),
+            params: self.params.len(),
+        }
+    }
+}
+
+/// Сводка по захвату в отчете
+#[derive(Debug, Serialize)]
+struct CaptureSummary {
+    path: String,
+    frames: u64,
+    crc_fail: u64,
+    crc_error_rate: f64,
+    parse_errors: u64,
+    duration_secs: Option<f64>,
+    devices: usize,
+    params: usize,
+}
+
+/// Распределение значений параметра
+#[derive(Debug, Serialize)]
+struct Distribution {
+    count: usize,
+    min: f64,
+    max: f64,
+    mean: f64,
+    stddev: f64,
+    median: f64,
+}
+
+impl Distribution {
+    /// Распределение значений; None, если значений нет
+    fn of(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let count = sorted.len();
+        let mean = sorted.iter().sum::<f64>() / count as f64;
+        let variance = sorted.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / count as f64;
+        Some(Self {
+            count,
+            min: sorted[0],
+            max: sorted[count - 1],
+            mean,
+            stddev: variance.sqrt(),
+            median: sorted[count / 2],
+        })
+    }
+}
+
+/// Изменение частоты обновления параметра
+#[derive(Debug, Serialize)]
+struct RateChange {
+    param: String,
+    rate_a: f64,
+    rate_b: f64,
+    change: f64,             // Относительное изменение (b - a) / a
+}
+
+/// Сдвиг распределения значений параметра
+#[derive(Debug, Serialize)]
+struct ValueShift {
+    param: String,
+    a: Distribution,
+    b: Distribution,
+    shift_sigma: Option<f64>,    // Сдвиг среднего в стандартных отклонениях (None при постоянных значениях)
+}
+
+/// Изменение маршрутов параметра
+#[derive(Debug, Serialize)]
+struct RouteChange {
+    param: String,
+    routes_a: BTreeMap<&'static str, u64>,
+    routes_b: BTreeMap<&'static str, u64>,
+}
+
+/// Результат сравнения двух захватов
+#[derive(Debug, Serialize)]
+struct DiffReport {
+    rate_unit: &'static str,             // per_second или per_1000_frames
+    a: CaptureSummary,
+    b: CaptureSummary,
+    crc_error_rate_change: f64,          // Изменение доли ошибок CRC (b - a)
+    devices_only_in_a: Vec<String>,
+    devices_only_in_b: Vec<String>,
+    params_only_in_a: Vec<String>,
+    params_only_in_b: Vec<String>,
+    rate_changes: Vec<RateChange>,
+    value_shifts: Vec<ValueShift>,
+    route_changes: Vec<RouteChange>,
+}
+
+/// Декодирует два захвата и печатает различия трафика: устройства и параметры,
+/// частоты обновления, распределения значений, маршруты и долю ошибок CRC
+/// Частоты считаются в секунду, если оба захвата содержат метки времени, иначе на 1000 кадров
+pub fn run(
+    (path_a, format_a): (&str, CaptureFormat),
+    (path_b, format_b): (&str, CaptureFormat),
+    sorters: (PSorter, PSorter),
+    format: ReportFormat,
+) -> Result<()> {
+    let a = CaptureProfile::load(path_a, format_a, sorters.0)?;
+    let b = CaptureProfile::load(path_b, format_b, sorters.1)?;
+    let report = compare(&a, &b);
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let result = match format {
+        ReportFormat::Json => serde_json::to_string_pretty(&report)
+            .map_err(io::Error::other)
+            .and_then(|json| writeln!(out, "{}", json)),
+        ReportFormat::Table => report.write_table(&mut out),
+    }
+    .and_then(|_| out.flush());
+
+    match result {
+        // Вывод оборван (например, передан в head) - это не ошибка
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        other => other.context("Failed to write diff output"),
+    }
+}
+
+/// Сравнивает профили захватов
+fn compare(a: &CaptureProfile, b: &CaptureProfile) -> DiffReport {
+    let per_second = a.duration_secs.is_some() && b.duration_secs.is_some();
+    let only = |left: &CaptureProfile, right: &CaptureProfile| -> (Vec<String>, Vec<String>) {
+        let devices = left.devices.difference(&right.devices).map(|&device| device_name(device)).collect();
+        let params = left.params.keys()
+            .filter(|key| !right.params.contains_key(key))
+            .map(|&(device, prm_id)| param_name(device, prm_id))
+            .collect();
+        (devices, params)
+    };
+    let (devices_only_in_a, params_only_in_a) = only(a, b);
+    let (devices_only_in_b, params_only_in_b) = only(b, a);
+
+    let mut rate_changes = Vec::new();
+    let mut value_shifts = Vec::new();
+    let mut route_changes = Vec::new();
+    for (key, param_a) in &a.params {
+        let Some(param_b) = b.params.get(key) else {
+            continue;
+        };
+        let param = param_name(key.0, key.1);
+
+        let rate_a = a.rate(param_a.count, per_second);
+        let rate_b = b.rate(param_b.count, per_second);
+        let change = (rate_b - rate_a) / rate_a;
+        if change.abs() >= RATE_CHANGE_RATIO {
+            rate_changes.push(RateChange { param: param.clone(), rate_a, rate_b, change });
+        }
+
+        if let (Some(dist_a), Some(dist_b)) = (Distribution::of(&param_a.values), Distribution::of(&param_b.values)) {
+            let spread = dist_a.stddev.max(dist_b.stddev);
+            let delta = (dist_b.mean - dist_a.mean).abs();
+            let shift_sigma = (spread > 0.0).then(|| delta / spread);
+            let shifted = match shift_sigma {
+                Some(sigma) => sigma >= SHIFT_SIGMA,
+                None => delta > 0.0,
+            };
+            if shifted {
+                value_shifts.push(ValueShift { param: param.clone(), a: dist_a, b: dist_b, shift_sigma });
+            }
+        }
+
+        let dominant = |routes: &BTreeMap<&'static str, u64>| {
+            routes.iter().max_by_key(|(_, count)| **count).map(|(route, _)| *route)
+        };
+        if param_a.routes.keys().ne(param_b.routes.keys()) || dominant(&param_a.routes) != dominant(&param_b.routes) {
+            route_changes.push(RouteChange {
+                param,
+                routes_a: param_a.routes.clone(),
+                routes_b: param_b.routes.clone(),
+            });
+        }
+    }
+
+    DiffReport {
+        rate_unit: if per_second { "per_second" } else { "per_1000_frames" },
+        a: a.summary(),
+        b: b.summary(),
+        crc_error_rate_change: b.crc_error_rate() - a.crc_error_rate(),
+        devices_only_in_a,
+        devices_only_in_b,
+        params_only_in_a,
+        params_only_in_b,
+        rate_changes,
+        value_shifts,
+        route_changes,
+    }
+}
+
+impl DiffReport {
+    /// Печатает отчет для чтения человеком
+    fn write_table(&self, out: &mut impl Write) -> io::Result<()> {
+        for (label, capture) in [("A", &self.a), ("B", &self.b)] {
+            let duration = capture.duration_secs.map_or(String::from("-"), |secs| format!("{:.1}s", secs));
+            writeln!(out, "Capture {}: {} (frames {}, CRC errors {} ({:.2}%), parse errors {}, devices {}, params {}, duration {})",
+                     label, capture.path, capture.frames, capture.crc_fail, capture.crc_error_rate * 100.0,
+                     capture.parse_errors, capture.devices, capture.params, duration)?;
+        }
+        writeln!(out, "CRC error rate: {:.2}% -> {:.2}% ({:+.2} pp)",
+                 self.a.crc_error_rate * 100.0, self.b.crc_error_rate * 100.0, self.crc_error_rate_change * 100.0)?;
+
+        let names = |title: &str, names: &[String], out: &mut dyn Write| -> io::Result<()> {
+            writeln!(out, "\n{}:", title)?;
+            if names.is_empty() {
+                writeln!(out, "  none")?;
+            }
+            for name in names {
+                writeln!(out, "  {}", name)?;
+            }
+            Ok(())
+        };
+        names("DEVICES ONLY IN A", &self.devices_only_in_a, out)?;
+        names("DEVICES ONLY IN B", &self.devices_only_in_b, out)?;
+        names("PARAMETERS ONLY IN A", &self.params_only_in_a, out)?;
+        names("PARAMETERS ONLY IN B", &self.params_only_in_b, out)?;
+
+        let unit = if self.rate_unit == "per_second" { "per second" } else { "per 1000 frames" };
+        writeln!(out, "\nUPDATE RATE CHANGES ({}, >= {:.0}%):", unit, RATE_CHANGE_RATIO * 100.0)?;
+        if self.rate_changes.is_empty() {
+            writeln!(out, "  none")?;
+        }
+        for change in &self.rate_changes {
+            writeln!(out, "  {:24} {:>10.3} -> {:>10.3} ({:+.1}%)",
+                     change.param, change.rate_a, change.rate_b, change.change * 100.0)?;
+        }
+
+        writeln!(out, "\nVALUE DISTRIBUTION SHIFTS (mean shift >= {} sigma):", SHIFT_SIGMA)?;
+        if self.value_shifts.is_empty() {
+            writeln!(out, "  none")?;
+        }
+        for shift in &self.value_shifts {
+            let sigma = shift.shift_sigma.map_or(String::from("constant"), |sigma| format!("{:.1} sigma", sigma));
+            writeln!(out, "  {:24} mean {:.3}±{:.3} [{:.3}..{:.3}] -> {:.3}±{:.3} [{:.3}..{:.3}] ({})",
+                     shift.param,
+                     shift.a.mean, shift.a.stddev, shift.a.min, shift.a.max,
+                     shift.b.mean, shift.b.stddev, shift.b.min, shift.b.max,
+                     sigma)?;
+        }
+
+        writeln!(out, "\nROUTE CHANGES:")?;
+        if self.route_changes.is_empty() {
+            writeln!(out, "  none")?;
+        }
+        let routes = |routes: &BTreeMap<&'static str, u64>| {
+            routes.iter().map(|(route, count)| format!("{}={}", route, count)).collect::<Vec<_>>().join(",")
+        };
+        for change in &self.route_changes {
+            writeln!(out, "  {:24} {} -> {}", change.param, routes(&change.routes_a), routes(&change.routes_b))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Параметр захвата: устройство, prm_id, записей, значения, маршруты
+    type Param<'a> = (DeviceKey, u16, u64, &'a [f64], &'a [(&'static str, u64)]);
+
+    fn profile(frames: u64, duration_secs: Option<f64>, params: &[Param]) -> CaptureProfile {
+        CaptureProfile {
+            path: String::from("capture"),
+            frames,
+            crc_fail: 0,
+            parse_errors: 0,
+            duration_secs,
+            devices: params.iter().map(|param| param.0).collect(),
+            params: params.iter()
+                .map(|&(device, prm_id, count, values, routes)| {
+                    let profile = ParamProfile {
+                        count,
+                        values: values.to_vec(),
+                        routes: routes.iter().copied().collect(),
+                    };
+                    ((device, prm_id), profile)
+                })
+                .collect(),
+        }
+    }
+
+    const DEV1: DeviceKey = (3, 1, 1);
+    const T: &[(&str, u64)] = &[("TMonitor", 1)];
+
+    #[test]
+    fn compare_captures() {
+        let a = profile(1000, None, &[
+            (DEV1, 10, 100, &[40.0, 41.0, 42.0, 41.0], T),
+            (DEV1, 11, 100, &[50.0, 50.0], T),
+            (DEV1, 12, 100, &[60.0, 60.0], T),
+            (DEV1, 14, 100, &[10.0, 12.0], T),
+            (DEV1, 20, 10, &[], &[("SMonitor", 10)]),
+            (DEV1, 21, 10, &[], &[("TMonitor", 6), ("CMonitor", 4)]),
+            ((3, 1, 2), 10, 50, &[], T),
+        ]);
+        let b = profile(1000, None, &[
+            (DEV1, 10, 119, &[41.5, 42.5, 43.5, 42.5], T),
+            (DEV1, 11, 120, &[51.0, 51.0], T),
+            (DEV1, 12, 100, &[60.0, 60.0], T),
+            (DEV1, 13, 100, &[], T),
+            (DEV1, 14, 100, &[10.5, 12.5], T),
+            (DEV1, 20, 10, &[], &[("SMonitor", 6), ("OMonitor", 4)]),
+            (DEV1, 21, 10, &[], &[("TMonitor", 4), ("CMonitor", 6)]),
+            ((4, 1, 1), 10, 50, &[], T),
+        ]);
+        let report = compare(&a, &b);
+
+        assert_eq!(report.rate_unit, "per_1000_frames");
+        assert_eq!(report.devices_only_in_a, ["BM3.MCU1.DEV2"]);
+        assert_eq!(report.devices_only_in_b, ["BM4.MCU1.DEV1"]);
+        assert_eq!(report.params_only_in_a, ["BM3.MCU1.DEV2.prm10"]);
+        assert_eq!(report.params_only_in_b, ["BM3.MCU1.DEV1.prm13", "BM4.MCU1.DEV1.prm10"]);
+
+        // +19% - ниже порога, +20% - на пороге
+        let rates: Vec<(&str, f64)> = report.rate_changes.iter().map(|change| (change.param.as_str(), change.change)).collect();
+        assert_eq!(rates, [("BM3.MCU1.DEV1.prm11", 0.2)]);
+
+        // prm10: сдвиг среднего на 1.5 при отклонении 0.707 (около 2.1 сигмы);
+        // prm11: постоянные значения изменились; prm12: не изменились; prm14: сдвиг 0.5 сигмы
+        let shifts: Vec<(&str, Option<f64>)> = report.value_shifts.iter()
+            .map(|shift| (shift.param.as_str(), shift.shift_sigma))
+            .collect();
+        assert_eq!(shifts.len(), 2);
+        assert_eq!(shifts[0].0, "BM3.MCU1.DEV1.prm10");
+        assert!((shifts[0].1.unwrap() - 1.5 / 0.5f64.sqrt()).abs() < 1e-9);
+        assert_eq!(shifts[1], ("BM3.MCU1.DEV1.prm11", None));
+        assert_eq!(report.value_shifts[1].b.mean, 51.0);
+
+        // prm20: новый маршрут; prm21: те же маршруты, но сменился основной
+        let routes: Vec<&str> = report.route_changes.iter().map(|change| change.param.as_str()).collect();
+        assert_eq!(routes, ["BM3.MCU1.DEV1.prm20", "BM3.MCU1.DEV1.prm21"]);
+    }
+
+    #[test]
+    fn rates_per_second_with_timestamps() {
+        let a = profile(1000, Some(10.0), &[(DEV1, 10, 100, &[], T)]);
+        let b = profile(1000, Some(20.0), &[(DEV1, 10, 100, &[], T)]);
+        let report = compare(&a, &b);
+        assert_eq!(report.rate_unit, "per_second");
+        assert_eq!(report.rate_changes.len(), 1);
+        assert_eq!((report.rate_changes[0].rate_a, report.rate_changes[0].rate_b), (10.0, 5.0));
+        assert_eq!(report.rate_changes[0].change, -0.5);
+
+        // Метки времени только в одном захвате - частоты на 1000 кадров
+        let b = profile(2000, None, &[(DEV1, 10, 200, &[], T)]);
+        let report = compare(&a, &b);
+        assert_eq!(report.rate_unit, "per_1000_frames");
+        assert!(report.rate_changes.is_empty());
+    }
+}
@@ -2,8 +2,13 @@
 use anyhow::{Context, Result};
 use std::fs::File;
 use std::io::Read;
-use tokio::time::{sleep, Duration};
+use tracing::{debug, error, info};
+
 use crate::channels::PackageSender;
+use crate::metrics::METRICS;
+
+/// Имя источника для метрик
+const SOURCE: &str = "dump";
 
 /// Структура для чтения и обработки дамп-файлов
 /// Разбивает данные на пакеты по разделителю 0xC0 и отправляет через канал
@@ -40,48 +45,52 @@ impl DumpReader {
         file.read_to_end(&mut buffer)
             .context("Failed to read dump file")?;
 
-        println!("Dump file loaded, size: {} bytes", buffer.len());
+        info!(size = buffer.len(), "Dump file loaded");
+        METRICS.source_bytes.add(&[SOURCE], buffer.len() as u64);
         
         // Обрабатываем байты потоково
         let mut current_packet = Vec::new();
         
         // Проходим по всем байтам файла
-        for (i, &byte) in buffer.iter().enumerate() {                        
+        for &byte in buffer.iter() {
             // Добавляем байт в текущий пакет, если это не разделитель
             if byte != 0xC0 {
                 current_packet.push(byte);
             }
 
-            // Проверяем, является ли этот байт разделителем 0xC0
-            if byte == 0xC0 {
-                // Отправляем готовый пакет через канал, если он не пустой
-                if !current_packet.is_empty() {
-                    if let Err(e) = self.package_sender.send(current_packet.clone()) {
-                        eprintln!("Failed to send dump package: {}", e);
-                    } else {
-                        // Логируем только каждые 100 пакетов для уменьшения шума
-                        if self.send_package_counter % 100 == 0 {
-                            println!("Dump packet {} sent", self.send_package_counter + 1);
-                        }
-                        self.send_package_counter += 1;
+            // Проверяем, является ли этот байт разделителем 0xC0,
+            // и отправляем готовый пакет через канал, если он не пустой
+            if byte == 0xC0 && !current_packet.is_empty() {
+                METRICS.source_frames.inc(&[SOURCE]);
+                if let Err(e) = self.package_sender.send(current_packet.clone()) {
+                    METRICS.dropped_packages.inc(&[SOURCE]);
+                    error!("Failed to send dump package: {}", e);
+                } else {
+                    // Логируем только каждые 100 пакетов для уменьшения шума
+                    if self.send_package_counter.is_multiple_of(100) {
+                        debug!("Dump packet {} sent", self.send_package_counter + 1);
                     }
-                    // Начинаем новый пакет после отправки
-                    current_packet.clear();
+                    self.send_package_counter += 1;
                 }
+                // Начинаем новый пакет после отправки
+                current_packet.clear();
             }
         }
         
         // Отправляем последний пакет, если он есть (последний байт не был разделителем)
         if !current_packet.is_empty() {
+            METRICS.source_frames.inc(&[SOURCE]);
+            METRICS.framing_errors.inc(&[SOURCE, "unterminated"]);
             if let Err(e) = self.package_sender.send(current_packet) {
-                eprintln!("Failed to send final dump package: {}", e);
+                METRICS.dropped_packages.inc(&[SOURCE]);
+                error!("Failed to send final dump package: {}", e);
             } else {
-                println!("Final dump packet {} sent", self.send_package_counter + 1);
+                debug!("Final dump packet {} sent", self.send_package_counter + 1);
                 self.send_package_counter += 1;
             }
         }
         
-        println!("Dump processing completed. Total packets sent: {}", self.send_package_counter);
+        info!(total = self.send_package_counter, "Dump processing completed");
         
         // Канал автоматически закроется когда DumpReader выйдет из области видимости
         Ok(())
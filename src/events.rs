@@ -0,0 +1,62 @@
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::trace;
+
+use crate::alarm_engine::AlarmEvent;
+use crate::hw_alarms::HwAlarmEvent;
+use crate::metrics::METRICS;
+use crate::ws_stream::{StreamHub, StreamItem};
+use crate::zmq_sender::ZmqSender;
+
+/// Имя маршрута событий (EMonitor) для метрик и логов
+pub const EVENTS_ROUTE: &str = "EMonitor";
+
+/// События, формируемые при обработке пакетов
+/// Каждый вариант сам содержит поле "event" с типом события
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Event {
+    /// Смена состояния пороговой тревоги
+    ThresholdAlarm(AlarmEvent),
+    /// Установка или снятие аппаратного аварийного бита
+    HardwareAlarm(HwAlarmEvent),
+}
+
+/// Публикует события (JSON) на выделенный маршрут событий и в поток WebSocket
+#[derive(Clone)]
+pub struct EventPublisher {
+    sender: Arc<ZmqSender>,    // ZMQ отправитель маршрута событий
+    stream: StreamHub,         // Поток WebSocket
+}
+
+impl EventPublisher {
+    /// Создает публикатор поверх ZMQ отправителя и потока WebSocket
+    pub fn new(sender: Arc<ZmqSender>, stream: StreamHub) -> Self {
+        Self { sender, stream }
+    }
+
+    /// Сериализует событие в JSON и отправляет его
+    /// Ошибки отправки логируются в ZmqSender и учитываются в метриках маршрута
+    pub fn publish<T: Serialize>(&self, event: &T) {
+        let payload = match serde_json::to_vec(event) {
+            Ok(payload) => payload,
+            Err(e) => {
+                tracing::error!("Failed to serialize event: {}", e);
+                return;
+            }
+        };
+
+        if self.stream.has_subscribers() {
+            if let Ok(value) = serde_json::from_slice(&payload) {
+                self.stream.publish(StreamItem::Event(value));
+            }
+        }
+
+        if self.sender.send_package(&payload).is_ok() {
+            METRICS.route_sent.inc(&[EVENTS_ROUTE]);
+            trace!(event = %String::from_utf8_lossy(&payload), "Event published");
+        } else {
+            METRICS.route_failed.inc(&[EVENTS_ROUTE]);
+        }
+    }
+}
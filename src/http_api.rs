@@ -0,0 +1,391 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::alarm_engine::SharedAlarmEngine;
+use crate::channels::CommandSender;
+use crate::config::Config;
+use crate::convert::CaptureFormat;
+use crate::device_stats::SharedDeviceStats;
+use crate::hw_alarms::SharedHwAlarms;
+use crate::http_server::{HttpHandler, HttpRequest, HttpResponse};
+use crate::include::byte_stuffing;
+use crate::metrics::METRICS;
+use crate::param_dict::ParamDictionary;
+use crate::psorter::PSorter;
+use crate::recorder::{self, SharedRecorder};
+use crate::state_store::{ParamSnapshot, SharedStateStore};
+
+/// Разделитель кадров в потоке
+const DELIMITER: u8 = 0xC0;
+
+/// Тело POST /commands
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CommandRequest {
+    frame: String,                   // Пакет (hex) без байт-стаффинга и разделителей
+    #[serde(default)]
+    append_crc: bool,                // Дописать CRC текущего профиля протокола
+}
+
+/// Тело POST /recording/start
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RecordingRequest {
+    path: String,                    // Файл захвата относительно каталога записей
+    format: Option<String>,          // raw / capture / pcapng / hex (по умолчанию по расширению)
+}
+
+/// Фильтр параметров GET /state: bm, mcu, dev_id, src_id, prm_id из строки запроса
+#[derive(Debug, Default)]
+struct StateFilter {
+    bm: Option<u8>,
+    mcu: Option<u8>,
+    dev_id: Option<u8>,
+    src_id: Option<u8>,
+    prm_id: Option<u16>,
+}
+
+impl StateFilter {
+    fn from_query(request: &HttpRequest) -> Result<Self> {
+        Ok(Self {
+            bm: request.query_param("bm")?,
+            mcu: request.query_param("mcu")?,
+            dev_id: request.query_param("dev_id")?,
+            src_id: request.query_param("src_id")?,
+            prm_id: request.query_param("prm_id")?,
+        })
+    }
+
+    fn matches(&self, param: &ParamSnapshot) -> bool {
+        self.bm.is_none_or(|bm| param.key.module_addr_bm == bm)
+            && self.mcu.is_none_or(|mcu| param.key.module_addr_mcu == mcu)
+            && self.dev_id.is_none_or(|dev_id| param.key.dev_id == dev_id)
+            && self.src_id.is_none_or(|src_id| param.key.src_id == src_id)
+            && self.prm_id.is_none_or(|prm_id| param.key.prm_id == prm_id)
+    }
+}
+
+/// Локальный HTTP/JSON API поверх компонентов контроллера
+///
+/// GET: /status, /stats, /devices, /devices/{bm}/{mcu}/{dev_id}, /state, /alarms,
+/// /alarms/history, /params, /config, /recording, /metrics
+/// POST: /commands, /recording/start, /recording/stop
+pub struct HttpApi {
+    source: &'static str,                // Источник пакетов (uart, dump, ...)
+    started: DateTime<Utc>,              // Время запуска
+    config: Arc<Config>,                 // Действующая конфигурация
+    sorter: Arc<Mutex<PSorter>>,         // Сортировщик (счетчики, профиль CRC)
+    device_stats: SharedDeviceStats,     // Статистика устройств
+    state_store: SharedStateStore,       // Последние значения параметров
+    alarm_engine: SharedAlarmEngine,     // Пороговые тревоги
+    hw_alarms: SharedHwAlarms,           // Аппаратные аварийные биты
+    param_dict: Arc<ParamDictionary>,    // Словарь параметров
+    command_sender: Option<CommandSender>,  // Канал команд на запись (None - режим без писателя)
+    recorder: SharedRecorder,            // Запись захвата
+}
+
+impl HttpApi {
+    /// Создает API; разделяемые компоненты берутся из сортировщика
+    pub async fn new(
+        source: &'static str,
+        config: Config,
+        sorter: Arc<Mutex<PSorter>>,
+        command_sender: Option<CommandSender>,
+        recorder: SharedRecorder,
+    ) -> Self {
+        let (device_stats, state_store, alarm_engine, hw_alarms, param_dict) = {
+            let sorter = sorter.lock().await;
+            (sorter.device_stats(), sorter.state_store(), sorter.alarm_engine(),
+             sorter.hw_alarms(), sorter.param_dict())
+        };
+        Self {
+            source,
+            started: Utc::now(),
+            config: Arc::new(config),
+            sorter,
+            device_stats,
+            state_store,
+            alarm_engine,
+            hw_alarms,
+            param_dict,
+            command_sender,
+            recorder,
+        }
+    }
+
+    /// Обработчик запросов для HttpServer
+    pub fn into_handler(self) -> Arc<HttpHandler> {
+        let api = Arc::new(self);
+        Arc::new(move |request: HttpRequest| {
+            let api = Arc::clone(&api);
+            Box::pin(async move { api.handle(request).await })
+        })
+    }
+
+    /// Сопоставляет запрос с эндпоинтом; ошибки разбора запроса - ответ 400
+    async fn handle(&self, request: HttpRequest) -> HttpResponse {
+        let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+        let result = match (request.method.as_str(), segments.as_slice()) {
+            ("GET", ["metrics"]) => Ok(HttpResponse::ok("text/plain; version=0.0.4", METRICS.render())),
+            ("GET", ["status"]) => Ok(self.status().await),
+            ("GET", ["stats"]) => Ok(HttpResponse::json(&self.sorter.lock().await.stats())),
+            ("GET", ["devices"]) => {
+                let entries = self.device_stats.lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .entries();
+                Ok(HttpResponse::json(&entries))
+            }
+            ("GET", ["devices", bm, mcu, dev_id]) => self.device(bm, mcu, dev_id),
+            ("GET", ["state"]) => StateFilter::from_query(&request).map(|filter| {
+                let params: Vec<_> = self.state_store.snapshot()
+                    .into_iter()
+                    .filter(|param| filter.matches(param))
+                    .collect();
+                HttpResponse::json(&params)
+            }),
+            ("GET", ["alarms"]) => {
+                let threshold = self.alarm_engine.lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .active_alarms();
+                let hardware = self.hw_alarms.lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .active_summary();
+                Ok(HttpResponse::json(&serde_json::json!({
+                    "threshold": threshold,
+                    "hardware": hardware,
+                })))
+            }
+            ("GET", ["alarms", "history"]) => {
+                let history = self.hw_alarms.lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .history();
+                Ok(HttpResponse::json(&history))
+            }
+            ("GET", ["params"]) => Ok(HttpResponse::json(&self.param_dict.entries())),
+            ("GET", ["config"]) => Ok(HttpResponse::json(&*self.config)),
+            ("GET", ["recording"]) => Ok(HttpResponse::json(&self.recording_status())),
+            ("POST", ["commands"]) => self.send_command(&request).await,
+            ("POST", ["recording", "start"]) => self.start_recording(&request),
+            ("POST", ["recording", "stop"]) => self.stop_recording(),
+            _ => return HttpResponse::not_found(),
+        };
+        result.unwrap_or_else(|e| HttpResponse::error(400, format!("{:#}", e)))
+    }
+
+    /// Общее состояние: источник, время работы, счетчики, тревоги и запись
+    async fn status(&self) -> HttpResponse {
+        let stats = self.sorter.lock().await.stats();
+        let threshold_alarms = self.alarm_engine.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .active_alarms()
+            .len();
+        let hardware_alarms = self.hw_alarms.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .active_summary()
+            .len();
+        let devices = self.device_stats.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entries()
+            .len();
+        let now = Utc::now();
+        HttpResponse::json(&serde_json::json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "source": self.source,
+            "started": self.started,
+            "uptime_secs": (now - self.started).num_milliseconds() as f64 / 1000.0,
+            "packages": stats.input_packages,
+            "crc_correct": stats.crc_correct,
+            "crc_incorrect": stats.crc_incorrect,
+            "devices": devices,
+            "active_alarms": threshold_alarms + hardware_alarms,
+            "recording": self.recording_status(),
+        }))
+    }
+
+    /// Статистика и последние значения параметров одного устройства
+    fn device(&self, bm: &str, mcu: &str, dev_id: &str) -> Result<HttpResponse> {
+        let bm: u8 = bm.parse().context(format!("Invalid BM address: {}", bm))?;
+        let mcu: u8 = mcu.parse().context(format!("Invalid MCU address: {}", mcu))?;
+        let dev_id: u8 = dev_id.parse().context(format!("Invalid device id: {}", dev_id))?;
+
+        let stats: Vec<_> = self.device_stats.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entries()
+            .into_iter()
+            .filter(|entry| {
+                entry.key.module_addr_bm == bm && entry.key.module_addr_mcu == mcu && entry.key.dev_id == dev_id
+            })
+            .collect();
+        let filter = StateFilter { bm: Some(bm), mcu: Some(mcu), dev_id: Some(dev_id), ..Default::default() };
+        let params: Vec<_> = self.state_store.snapshot()
+            .into_iter()
+            .filter(|param| filter.matches(param))
+            .collect();
+
+        if stats.is_empty() && params.is_empty() {
+            return Ok(HttpResponse::error(404, format!("Device BM{}.MCU{}.DEV{} not seen", bm, mcu, dev_id)));
+        }
+        Ok(HttpResponse::json(&serde_json::json!({
+            "bm": bm,
+            "mcu": mcu,
+            "dev_id": dev_id,
+            "stats": stats,
+            "params": params,
+        })))
+    }
+
+    /// Ставит пакет в очередь команд: байт-стаффинг и разделитель добавляются здесь
+    async fn send_command(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        let Some(command_sender) = &self.command_sender else {
+            return Ok(HttpResponse::error(503, format!("Commands are not supported in {} mode", self.source)));
+        };
+        let command: CommandRequest = request.json()?;
+        let mut package = hex::decode(command.frame.trim()).context("Invalid frame hex")?;
+        if package.is_empty() {
+            return Err(anyhow::anyhow!("Empty frame"));
+        }
+        if command.append_crc {
+            self.sorter.lock().await.crc_check().append(&mut package);
+        }
+
+        let mut wire = package.clone();
+        byte_stuffing::request_byte_stuffing(&mut wire);
+        wire.push(DELIMITER);
+
+        let size = wire.len();
+        let encoded = hex::encode(&wire);
+        if command_sender.send(wire).is_err() {
+            return Ok(HttpResponse::error(503, "Command channel closed"));
+        }
+        Ok(HttpResponse::json_status(202, &serde_json::json!({
+            "queued": true,
+            "package": hex::encode(&package),
+            "wire": encoded,
+            "bytes": size,
+        })))
+    }
+
+    /// Состояние записи (null, если запись не ведется)
+    fn recording_status(&self) -> Option<crate::recorder::RecordingStatus> {
+        self.recorder.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .status()
+    }
+
+    /// Начинает запись принимаемых кадров в файл захвата (только внутри каталога записей)
+    fn start_recording(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        let recording: RecordingRequest = request.json()?;
+        let format = match &recording.format {
+            Some(name) => CaptureFormat::parse(name)?,
+            None => CaptureFormat::from_path(&recording.path),
+        };
+        let path = recorder::capture_path(&self.config.recording.directory, &recording.path)?;
+
+        let mut recorder = self.recorder.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(active) = recorder.status() {
+            return Ok(HttpResponse::error(409, format!("Recording already active: {}", active.path)));
+        }
+        Ok(HttpResponse::json(&recorder.start(&path.to_string_lossy(), format)?))
+    }
+
+    /// Завершает запись и возвращает ее итог
+    fn stop_recording(&self) -> Result<HttpResponse> {
+        let stopped = self.recorder.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .stop()?;
+        Ok(match stopped {
+            Some(status) => HttpResponse::json(&status),
+            None => HttpResponse::error(409, "No active recording"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channels::command_channel;
+    use crate::http_server::HttpServer;
+    use crate::recorder::Recorder;
+    use std::net::SocketAddr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    /// Отправляет запрос локальному серверу; возвращает код статуса и тело
+    async fn request(addr: SocketAddr, method: &str, target: &str, body: &str) -> (u16, serde_json::Value) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            method, target, body.len(), body
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+        (status, serde_json::from_str(body).unwrap_or(serde_json::Value::Null))
+    }
+
+    #[tokio::test]
+    async fn status_commands_and_recording() {
+        let (command_sender, mut command_receiver) = command_channel();
+        let sorter = Arc::new(Mutex::new(PSorter::new()));
+        let directory = std::env::temp_dir().join(format!("hwmon-api-test-{}", std::process::id()));
+        let mut config = Config::default();
+        config.recording.directory = directory.display().to_string();
+        let api = HttpApi::new("uart", config, sorter, Some(command_sender), Recorder::shared()).await;
+        let server = HttpServer::bind("127.0.0.1:0", api.into_handler()).await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(server.run());
+
+        let (status, body) = request(addr, "GET", "/status", "").await;
+        assert_eq!(status, 200);
+        assert_eq!(body["source"], "uart");
+        assert!(body["recording"].is_null());
+
+        let (status, _) = request(addr, "GET", "/state?bm=1&prm_id=x", "").await;
+        assert_eq!(status, 400);
+        let (status, _) = request(addr, "GET", "/devices/1/1/2", "").await;
+        assert_eq!(status, 404);
+
+        // Стаффинг байта 0xC0 и разделитель в конце
+        let (status, body) = request(addr, "POST", "/commands", r#"{"frame": "01c002"}"#).await;
+        assert_eq!(status, 202);
+        assert_eq!(body["wire"], "01dbdc02c0");
+        assert_eq!(command_receiver.recv().await.unwrap(), vec![0x01, 0xDB, 0xDC, 0x02, 0xC0]);
+
+        // Файл захвата - только внутри каталога записей
+        for path in ["/tmp/hwmon.cap", "../hwmon.cap", "a/../../hwmon.cap", ""] {
+            let start = format!(r#"{{"path": "{}"}}"#, path);
+            let (status, _) = request(addr, "POST", "/recording/start", &start).await;
+            assert_eq!(status, 400, "{}", path);
+        }
+        let start = r#"{"path": "test.cap"}"#;
+        let (status, body) = request(addr, "POST", "/recording/start", start).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["format"], "capture");
+        assert_eq!(body["path"], directory.join("test.cap").display().to_string());
+        let (status, _) = request(addr, "POST", "/recording/start", start).await;
+        assert_eq!(status, 409);
+        let (status, body) = request(addr, "POST", "/recording/stop", "").await;
+        assert_eq!(status, 200);
+        assert_eq!(body["frames"], 0);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn commands_unavailable_without_writer() {
+        let sorter = Arc::new(Mutex::new(PSorter::new()));
+        let api = HttpApi::new("dump", Config::default(), sorter, None, Recorder::shared()).await;
+        let server = HttpServer::bind("127.0.0.1:0", api.into_handler()).await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(server.run());
+
+        let (status, body) = request(addr, "POST", "/commands", r#"{"frame": "01c002"}"#).await;
+        assert_eq!(status, 503);
+        assert_eq!(body["error"], "Commands are not supported in dump mode");
+    }
+}
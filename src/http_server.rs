@@ -0,0 +1,280 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+/// Максимальный размер заголовков запроса
+const MAX_HEADER_SIZE: usize = 16 * 1024;
+
+/// Максимальный размер тела запроса
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// Разобранный HTTP-запрос
+#[derive(Debug, Default)]
+pub struct HttpRequest {
+    pub method: String,                  // Метод (GET, POST, ...)
+    pub path: String,                    // Путь без строки запроса
+    pub query: HashMap<String, String>,  // Параметры строки запроса (декодированные)
+    pub body: Vec<u8>,                   // Тело запроса (по Content-Length)
+}
+
+impl HttpRequest {
+    /// Значение параметра строки запроса, преобразованное в T
+    pub fn query_param<T: FromStr>(&self, name: &str) -> Result<Option<T>> {
+        self.query.get(name)
+            .map(|value| value.parse::<T>()
+                .map_err(|_| anyhow::anyhow!("Invalid query parameter {}: {}", name, value)))
+            .transpose()
+    }
+
+    /// Тело запроса в формате JSON (пустое тело - пустой объект)
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> Result<T> {
+        let body = if self.body.iter().all(u8::is_ascii_whitespace) { b"{}".as_slice() } else { &self.body };
+        serde_json::from_slice(body).context("Invalid JSON body")
+    }
+}
+
+/// HTTP-ответ
+#[derive(Debug)]
+pub struct HttpResponse {
+    pub status: u16,                     // Код статуса
+    pub content_type: &'static str,      // Тип содержимого
+    pub body: Vec<u8>,                   // Тело ответа
+}
+
+impl HttpResponse {
+    /// Ответ 200 с текстовым телом указанного типа
+    pub fn ok(content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Self { status: 200, content_type, body: body.into() }
+    }
+
+    /// Ответ 200 с телом в формате JSON
+    pub fn json<T: serde::Serialize>(value: &T) -> Self {
+        match serde_json::to_vec(value) {
+            Ok(body) => Self::ok("application/json", body),
+            Err(e) => Self {
+                status: 500,
+                content_type: "text/plain",
+                body: format!("Serialization error: {}\n", e).into_bytes(),
+            },
+        }
+    }
+
+    /// Ответ с кодом статуса и телом в формате JSON
+    pub fn json_status<T: serde::Serialize>(status: u16, value: &T) -> Self {
+        Self { status, ..Self::json(value) }
+    }
+
+    /// Ответ с ошибкой: {"error": "..."}
+    pub fn error(status: u16, message: impl std::fmt::Display) -> Self {
+        Self::json_status(status, &serde_json::json!({ "error": message.to_string() }))
+    }
+
+    /// Ответ 404
+    pub fn not_found() -> Self {
+        Self { status: 404, content_type: "text/plain", body: b"Not Found\n".to_vec() }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            202 => "Accepted",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            409 => "Conflict",
+            413 => "Payload Too Large",
+            500 => "Internal Server Error",
+            503 => "Service Unavailable",
+            _ => "Unknown",
+        }
+    }
+}
+
+/// Ответ обработчика (обработчик может ждать асинхронные блокировки)
+pub type HttpFuture = Pin<Box<dyn Future<Output = HttpResponse> + Send>>;
+
+/// Обработчик запросов: сопоставляет запрос с ответом
+pub type HttpHandler = dyn Fn(HttpRequest) -> HttpFuture + Send + Sync;
+
+/// Простой локальный HTTP/1.1 сервер (одно соединение - один запрос)
+pub struct HttpServer {
+    listener: TcpListener,
+    handler: Arc<HttpHandler>,
+}
+
+impl HttpServer {
+    /// Привязывает сервер к адресу
+    pub async fn bind(addr: &str, handler: Arc<HttpHandler>) -> Result<Self> {
+        let listener = TcpListener::bind(addr).await
+            .context(format!("Failed to bind HTTP server to {}", addr))?;
+        let server = Self { listener, handler };
+        // Фактический адрес: при порте 0 порт выбирает система
+        info!(addr = %server.local_addr()?, "HTTP server listening");
+        Ok(server)
+    }
+
+    /// Адрес, к которому привязан сервер (в том числе выбранный системой порт)
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Основной цикл приема соединений
+    pub async fn run(self) {
+        loop {
+            let (stream, peer) = match self.listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("HTTP accept error: {}", e);
+                    continue;
+                }
+            };
+
+            let handler = Arc::clone(&self.handler);
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(stream, handler).await {
+                    debug!(%peer, "HTTP connection error: {}", e);
+                }
+            });
+        }
+    }
+}
+
+/// Читает один запрос, вызывает обработчик и отправляет ответ
+async fn handle_connection(mut stream: TcpStream, handler: Arc<HttpHandler>) -> Result<()> {
+    let response = match read_request(&mut stream).await {
+        Ok(request) => handler(request).await,
+        Err(e) => HttpResponse {
+            status: if e.is::<BodyTooLarge>() { 413 } else { 400 },
+            content_type: "text/plain",
+            body: format!("{}\n", e).into_bytes(),
+        },
+    };
+
+    let header = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.reason(),
+        response.content_type,
+        response.body.len()
+    );
+    stream.write_all(header.as_bytes()).await?;
+    stream.write_all(&response.body).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Читает и разбирает HTTP-запрос из потока
+async fn read_request(stream: &mut TcpStream) -> Result<HttpRequest> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 1024];
+
+    // Читаем до конца заголовков
+    let header_end = loop {
+        if let Some(pos) = find_header_end(&buffer) {
+            break pos;
+        }
+        if buffer.len() > MAX_HEADER_SIZE {
+            return Err(anyhow::anyhow!("Request headers too large"));
+        }
+        let size = stream.read(&mut chunk).await?;
+        if size == 0 {
+            return Err(anyhow::anyhow!("Connection closed before end of headers"));
+        }
+        buffer.extend_from_slice(&chunk[..size]);
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let request_line = lines.next().unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().context("Missing HTTP method")?.to_string();
+    let target = parts.next().context("Missing HTTP path")?;
+
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), parse_query(query)),
+        None => (target.to_string(), HashMap::new()),
+    };
+
+    // Тело запроса читается по Content-Length
+    let content_length = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .map(|(_, value)| value.trim().parse::<usize>().context("Invalid Content-Length"))
+        .transpose()?
+        .unwrap_or(0);
+    if content_length > MAX_BODY_SIZE {
+        return Err(BodyTooLarge(content_length).into());
+    }
+    let mut body = buffer.split_off(header_end + 4);
+    while body.len() < content_length {
+        let size = stream.read(&mut chunk).await?;
+        if size == 0 {
+            return Err(anyhow::anyhow!("Connection closed before end of body"));
+        }
+        body.extend_from_slice(&chunk[..size]);
+    }
+    body.truncate(content_length);
+
+    Ok(HttpRequest { method, path, query, body })
+}
+
+/// Тело запроса больше MAX_BODY_SIZE
+#[derive(Debug)]
+struct BodyTooLarge(usize);
+
+impl std::fmt::Display for BodyTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Request body too large: {} bytes (limit {})", self.0, MAX_BODY_SIZE)
+    }
+}
+
+impl std::error::Error for BodyTooLarge {}
+
+/// Разбирает строку запроса: name=value&flag (без значения - пустая строка)
+pub fn parse_query(query: &str) -> HashMap<String, String> {
+    query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(name), percent_decode(value))
+        })
+        .collect()
+}
+
+/// Декодирует %XX и '+' (пробел) в компоненте строки запроса
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match hex {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Находит позицию разделителя заголовков и тела
+fn find_header_end(buffer: &[u8]) -> Option<usize> {
+    buffer.windows(4).position(|window| window == b"\r\n\r\n")
+}
@@ -0,0 +1,289 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::config::{HwAlarmBitConfig, HwAlarmsConfig};
+use crate::state_store::ParamKey;
+
+/// Разделяемый трекер аппаратных тревог
+pub type SharedHwAlarms = Arc<Mutex<HwAlarmTracker>>;
+
+/// Количество аварийных битов в поле data_type
+const ALARM_BITS: u8 = 4;
+
+/// Событие установки или снятия аппаратного аварийного бита
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HwAlarmEvent {
+    pub event: String,               // "hw_alarm_set" или "hw_alarm_clear"
+    pub name: String,                // Имя условия из словаря
+    pub bit: u8,                     // Номер бита (0-3)
+    #[serde(flatten)]
+    pub key: ParamKey,
+    pub alarms: u8,                  // Полное значение поля alarms
+    pub timestamp: DateTime<Utc>,    // Время перехода
+}
+
+/// Активное аппаратное условие для сводки
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveHwAlarm {
+    pub name: String,
+    pub bit: u8,
+    #[serde(flatten)]
+    pub key: ParamKey,
+    pub since: DateTime<Utc>,
+}
+
+/// Состояние, сохраняемое между перезапусками
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PersistedState {
+    active: Vec<PersistedActive>,
+    history: Vec<HwAlarmEvent>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PersistedActive {
+    #[serde(flatten)]
+    key: ParamKey,
+    bits: u8,
+    since: Vec<DateTime<Utc>>,
+}
+
+/// Активные биты параметра и время их установки
+#[derive(Debug, Clone)]
+struct ActiveBits {
+    bits: u8,
+    since: [DateTime<Utc>; ALARM_BITS as usize],
+}
+
+/// Отслеживает переходы аварийных битов по каждому параметру устройства
+/// и хранит историю переходов в файле, чтобы она переживала перезапуск
+pub struct HwAlarmTracker {
+    dictionary: Vec<HwAlarmBitConfig>,       // Имена условий по битам
+    active: HashMap<ParamKey, ActiveBits>,   // Текущие установленные биты
+    history: VecDeque<HwAlarmEvent>,         // Последние переходы
+    history_size: usize,                     // Максимальный размер истории
+    history_file: Option<PathBuf>,           // Файл для сохранения состояния
+    dirty: bool,                             // Есть несохраненные переходы
+}
+
+impl HwAlarmTracker {
+    /// Создает трекер и восстанавливает состояние из файла истории (если он есть)
+    pub fn load(config: &HwAlarmsConfig) -> Result<Self> {
+        let history_file = config.history_file.as_ref()
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from);
+
+        let mut tracker = Self {
+            dictionary: config.bits.clone(),
+            active: HashMap::new(),
+            history: VecDeque::new(),
+            history_size: config.history_size,
+            history_file,
+            dirty: false,
+        };
+
+        if let Some(path) = tracker.history_file.as_ref().filter(|path| path.exists()) {
+            let text = fs::read_to_string(path)
+                .context(format!("Failed to read alarm history: {}", path.display()))?;
+            let state: PersistedState = serde_json::from_str(&text)
+                .context(format!("Failed to parse alarm history: {}", path.display()))?;
+
+            for entry in state.active {
+                let mut since = [Utc::now(); ALARM_BITS as usize];
+                for (slot, time) in since.iter_mut().zip(entry.since) {
+                    *slot = time;
+                }
+                tracker.active.insert(entry.key, ActiveBits { bits: entry.bits, since });
+            }
+            tracker.history = state.history.into();
+        }
+
+        Ok(tracker)
+    }
+
+    /// Создает разделяемый трекер без словаря и файла истории
+    pub fn shared_empty() -> SharedHwAlarms {
+        Arc::new(Mutex::new(Self {
+            dictionary: Vec::new(),
+            active: HashMap::new(),
+            history: VecDeque::new(),
+            history_size: 0,
+            history_file: None,
+            dirty: false,
+        }))
+    }
+
+    /// Учитывает значение поля alarms параметра, возвращает переходы битов
+    pub fn observe(&mut self, key: ParamKey, alarms: u8, now: DateTime<Utc>) -> Vec<HwAlarmEvent> {
+        let alarms = alarms & ((1 << ALARM_BITS) - 1);
+        let previous = self.active.get(&key).map_or(0, |active| active.bits);
+        let changed = previous ^ alarms;
+        if changed == 0 {
+            return Vec::new();
+        }
+
+        let mut events = Vec::new();
+        let entry = self.active.entry(key).or_insert(ActiveBits {
+            bits: 0,
+            since: [now; ALARM_BITS as usize],
+        });
+        for bit in 0..ALARM_BITS {
+            if changed & (1 << bit) == 0 {
+                continue;
+            }
+            let set = alarms & (1 << bit) != 0;
+            if set {
+                entry.since[bit as usize] = now;
+            }
+            events.push(HwAlarmEvent {
+                event: if set { "hw_alarm_set" } else { "hw_alarm_clear" }.to_string(),
+                name: Self::bit_name(&self.dictionary, bit, key.prm_id),
+                bit,
+                key,
+                alarms,
+                timestamp: now,
+            });
+        }
+        entry.bits = alarms;
+        if alarms == 0 {
+            self.active.remove(&key);
+        }
+
+        for event in &events {
+            self.history.push_back(event.clone());
+        }
+        while self.history.len() > self.history_size {
+            self.history.pop_front();
+        }
+        self.dirty = true;
+
+        events
+    }
+
+    /// Возвращает сводку активных аппаратных условий
+    pub fn active_summary(&self) -> Vec<ActiveHwAlarm> {
+        let mut summary: Vec<ActiveHwAlarm> = self.active.iter()
+            .flat_map(|(key, active)| {
+                (0..ALARM_BITS)
+                    .filter(move |bit| active.bits & (1 << bit) != 0)
+                    .map(move |bit| ActiveHwAlarm {
+                        name: Self::bit_name(&self.dictionary, bit, key.prm_id),
+                        bit,
+                        key: *key,
+                        since: active.since[bit as usize],
+                    })
+            })
+            .collect();
+        summary.sort_by_key(|alarm| (alarm.key, alarm.bit));
+        summary
+    }
+
+    /// Возвращает историю переходов (от старых к новым)
+    pub fn history(&self) -> Vec<HwAlarmEvent> {
+        self.history.iter().cloned().collect()
+    }
+
+    /// Сохраняет состояние, если с прошлого сохранения были переходы
+    /// Вызывается по таймеру и при завершении, а не на каждый переход
+    pub fn save_if_dirty(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        self.save()?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Сохраняет активные биты и историю в файл
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.history_file else {
+            return Ok(());
+        };
+
+        let state = PersistedState {
+            active: self.active.iter()
+                .map(|(key, active)| PersistedActive {
+                    key: *key,
+                    bits: active.bits,
+                    since: active.since.to_vec(),
+                })
+                .collect(),
+            history: self.history(),
+        };
+
+        // Запись через временный файл, чтобы не повредить историю при сбое
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec_pretty(&state)?)
+            .context(format!("Failed to write alarm history: {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, path)
+            .context(format!("Failed to replace alarm history: {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Имя условия для бита: из словаря, иначе alarm_bit<N>
+    fn bit_name(dictionary: &[HwAlarmBitConfig], bit: u8, prm_id: u16) -> String {
+        dictionary.iter()
+            .find(|entry| {
+                entry.bit == bit
+                    && entry.prm_ids.as_ref().is_none_or(|ids| ids.contains(&prm_id))
+            })
+            .map(|entry| entry.name.clone())
+            .unwrap_or_else(|| format!("alarm_bit{}", bit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let path = std::env::temp_dir().join(format!("hwmon-hw-alarms-test-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let config = HwAlarmsConfig {
+            history_file: Some(path.to_string_lossy().into_owned()),
+            history_size: 3,
+            bits: vec![HwAlarmBitConfig { bit: 1, name: String::from("over_temperature"), prm_ids: None }],
+            ..Default::default()
+        };
+        let first = ParamKey { module_addr: 9, module_addr_bm: 1, module_addr_mcu: 1, dev_id: 1, src_id: 2, prm_id: 10 };
+        let second = ParamKey { dev_id: 2, ..first };
+        let start = Utc::now();
+
+        let mut tracker = HwAlarmTracker::load(&config).unwrap();
+        tracker.observe(first, 0b0010, start);
+        tracker.observe(second, 0b0101, start + Duration::seconds(1));
+        tracker.observe(second, 0b0001, start + Duration::seconds(2));
+        // После переходов файл появляется; без новых переходов он не перезаписывается
+        tracker.save_if_dirty().unwrap();
+        assert!(path.exists());
+        let saved = fs::metadata(&path).unwrap().modified().unwrap();
+        tracker.save_if_dirty().unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), saved);
+
+        let restored = HwAlarmTracker::load(&config).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let active: Vec<(ParamKey, u8, String, DateTime<Utc>)> = restored.active_summary().into_iter()
+            .map(|alarm| (alarm.key, alarm.bit, alarm.name, alarm.since))
+            .collect();
+        assert_eq!(active, [
+            (first, 1, String::from("over_temperature"), start),
+            (second, 0, String::from("alarm_bit0"), start + Duration::seconds(1)),
+        ]);
+        // История ограничена history_size: первые переходы вытеснены
+        let history: Vec<(String, u8, u8)> = restored.history().into_iter()
+            .map(|event| (event.event, event.key.dev_id, event.bit))
+            .collect();
+        assert_eq!(history, [
+            (String::from("hw_alarm_set"), 2, 0),
+            (String::from("hw_alarm_set"), 2, 2),
+            (String::from("hw_alarm_clear"), 2, 2),
+        ]);
+    }
+}
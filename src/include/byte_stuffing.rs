@@ -1,5 +1,5 @@
-/// Функции для байт-стаффинга (byte stuffing) - алгоритма экранирования специальных байтов
-/// Используется для передачи данных с байтом-разделителем 0xC0
+//! Функции для байт-стаффинга (byte stuffing) - алгоритма экранирования специальных байтов
+//! Используется для передачи данных с байтом-разделителем 0xC0
 
 /// Восстанавливает исходные данные из стаффированного буфера
 /// Заменяет escape-последовательности на исходные байты:
@@ -41,6 +41,23 @@ pub fn byte_stuffing(byte_buffer: &[u8]) -> Vec<u8> {
     buffer_stuff
 }
 
+/// Проверяет, содержит ли стаффированный буфер некорректные escape-последовательности
+/// (байт 0xDB, за которым не следует 0xDC или 0xDD)
+pub fn has_invalid_escape(byte_buffer: &[u8]) -> bool {
+    let mut i = 0;
+    while i < byte_buffer.len() {
+        if byte_buffer[i] == 0xDB {
+            match byte_buffer.get(i + 1) {
+                Some(0xDC) | Some(0xDD) => i += 2,
+                _ => return true,
+            }
+        } else {
+            i += 1;
+        }
+    }
+    false
+}
+
 /// Применяет байт-стаффинг к команде перед отправкой
 /// Экранирует специальные байты escape-последовательностями:
 /// - \xC0 → \xDB\xDC (разделитель пакетов)
@@ -43,7 +43,12 @@ pub const CRC16_TABLE: [u16; 256] = [
 /// # Возвращает
 /// 16-битное значение контрольной суммы
 pub fn calculate_crc16(data: &[u8]) -> u16 {
-    let mut crc: u16 = 0xFFFF;  // Начальное значение CRC
+    calculate_crc16_with_init(data, 0xFFFF)  // Начальное значение CRC-16/CCITT-FALSE
+}
+
+/// Вычисляет CRC-16 с полиномом 0x1021 и заданным начальным значением
+fn calculate_crc16_with_init(data: &[u8], init: u16) -> u16 {
+    let mut crc: u16 = init;  // Начальное значение CRC
     
     for &byte in data {
         // Алгоритм CRC-16: сдвиг и XOR с табличным значением
@@ -64,4 +69,85 @@ pub fn calculate_crc16(data: &[u8]) -> u16 {
 pub fn crc16_validate(data: &[u8], expected_crc: u16) -> bool {
     let calculated_crc = calculate_crc16(data);
     calculated_crc == expected_crc
-}
\ No newline at end of file
+}
+
+/// Параметры CRC в терминах каталога Rocksoft (ширина 16 или 32 бита)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrcParams {
+    pub width: u8,        // Ширина CRC в битах
+    pub poly: u32,        // Полином (без старшего бита)
+    pub init: u32,        // Начальное значение
+    pub reflected: bool,  // Отраженные вход и выход (младший бит первым)
+    pub xorout: u32,      // Значение для XOR результата
+}
+
+/// CRC-16/CCITT-FALSE
+pub const CRC16_CCITT_FALSE: CrcParams = CrcParams { width: 16, poly: 0x1021, init: 0xFFFF, reflected: false, xorout: 0x0000 };
+/// CRC-16/XMODEM
+pub const CRC16_XMODEM: CrcParams = CrcParams { width: 16, poly: 0x1021, init: 0x0000, reflected: false, xorout: 0x0000 };
+/// CRC-16/KERMIT
+pub const CRC16_KERMIT: CrcParams = CrcParams { width: 16, poly: 0x1021, init: 0x0000, reflected: true, xorout: 0x0000 };
+/// CRC-16/MODBUS
+pub const CRC16_MODBUS: CrcParams = CrcParams { width: 16, poly: 0x8005, init: 0xFFFF, reflected: true, xorout: 0x0000 };
+/// CRC-32 (ISO-HDLC, как в Ethernet и zlib)
+pub const CRC32: CrcParams = CrcParams { width: 32, poly: 0x04C1_1DB7, init: 0xFFFF_FFFF, reflected: true, xorout: 0xFFFF_FFFF };
+
+/// Отражает младшие `width` бит числа
+fn reflect(value: u32, width: u8) -> u32 {
+    value.reverse_bits() >> (32 - width as u32)
+}
+
+/// Вычисляет CRC с произвольными параметрами (побитовый алгоритм)
+pub fn calculate_crc(params: &CrcParams, data: &[u8]) -> u32 {
+    let width = params.width as u32;
+    let mask = if width == 32 { u32::MAX } else { (1u32 << width) - 1 };
+
+    let crc = if params.reflected {
+        // Отраженный алгоритм: сдвиг вправо с отраженным полиномом
+        let poly = reflect(params.poly, params.width);
+        let mut crc = reflect(params.init, params.width);
+        for &byte in data {
+            crc ^= byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 { (crc >> 1) ^ poly } else { crc >> 1 };
+            }
+        }
+        crc
+    } else {
+        // Прямой алгоритм: сдвиг влево, старший бит CRC определяет XOR с полиномом
+        let top = 1u32 << (width - 1);
+        let mut crc = params.init;
+        for &byte in data {
+            crc ^= (byte as u32) << (width - 8);
+            for _ in 0..8 {
+                crc = if crc & top != 0 { (crc << 1) ^ params.poly } else { crc << 1 } & mask;
+            }
+        }
+        crc
+    };
+
+    (crc ^ params.xorout) & mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Контрольные значения каталога CRC для строки "123456789"
+    const CHECK: &[u8] = b"123456789";
+
+    #[test]
+    fn check_values() {
+        assert_eq!(calculate_crc(&CRC16_CCITT_FALSE, CHECK), 0x29B1);
+        assert_eq!(calculate_crc(&CRC16_XMODEM, CHECK), 0x31C3);
+        assert_eq!(calculate_crc(&CRC16_KERMIT, CHECK), 0x2189);
+        assert_eq!(calculate_crc(&CRC16_MODBUS, CHECK), 0x4B37);
+        assert_eq!(calculate_crc(&CRC32, CHECK), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn table_matches_generic() {
+        let data: Vec<u8> = (0..=255).collect();
+        assert_eq!(calculate_crc16(&data) as u32, calculate_crc(&CRC16_CCITT_FALSE, &data));
+    }
+}
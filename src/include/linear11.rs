@@ -31,4 +31,86 @@ pub fn from_linear11_f(data: u16) -> f32 {
     } else {
         (mantissa as f32) / ((1 << -exponent) as f32)  // Отрицательная экспонента - деление
     }
-}
\ No newline at end of file
+}
+
+// Диапазон 5-битной экспоненты и 11-битной мантиссы Linear11 (дополнительный код)
+const EXPONENT_MIN: i32 = -16;
+const EXPONENT_MAX: i32 = 15;
+const MANTISSA_MIN: f64 = -1024.0;
+const MANTISSA_MAX: f64 = 1023.0;
+
+// Преобразует число с плавающей точкой в 16-битное число формата Linear11
+// Выбирается наименьшая экспонента, при которой мантисса не переполняется,
+// то есть представление с наилучшей точностью (с округлением до ближайшего)
+// Значения вне диапазона [-1024 * 2^15, 1023 * 2^15] и NaN/бесконечность дают ошибку
+pub fn to_linear11(value: f32) -> anyhow::Result<u16> {
+    if !value.is_finite() {
+        return Err(anyhow::anyhow!("Linear11 cannot encode non-finite value {}", value));
+    }
+
+    let value = value as f64;
+    for exponent in EXPONENT_MIN..=EXPONENT_MAX {
+        let mantissa = (value / 2f64.powi(exponent)).round();
+        if (MANTISSA_MIN..=MANTISSA_MAX).contains(&mantissa) {
+            // Упаковываем экспоненту (биты 11-15) и мантиссу (биты 0-10) в дополнительном коде
+            return Ok((((exponent as u16) & 0x001F) << 11) | ((mantissa as i16 as u16) & 0x07FF));
+        }
+    }
+
+    Err(anyhow::anyhow!(
+        "Value {} is outside the Linear11 range [{}, {}]",
+        value,
+        MANTISSA_MIN * 2f64.powi(EXPONENT_MAX),
+        MANTISSA_MAX * 2f64.powi(EXPONENT_MAX)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Мантисса и экспонента кода Linear11 со знаком
+    fn split(code: u16) -> (i32, i32) {
+        let exponent = (((code >> 11) as i8) << 3 >> 3) as i32;
+        let mantissa = (((code & 0x07FF) << 5) as i16 >> 5) as i32;
+        (mantissa, exponent)
+    }
+
+    #[test]
+    fn round_trip_all_codes() {
+        for code in 0..=u16::MAX {
+            let value = from_linear11_f(code);
+            let encoded = to_linear11(value)
+                .unwrap_or_else(|e| panic!("code {:#06x} ({}) failed to encode: {}", code, value, e));
+            assert_eq!(from_linear11_f(encoded), value, "code {:#06x} -> {:#06x}", code, encoded);
+
+            // Наилучшая точность: при меньшей экспоненте мантисса бы переполнилась
+            let (mantissa, exponent) = split(encoded);
+            if exponent > EXPONENT_MIN {
+                assert!(!(-512..512).contains(&mantissa), "code {:#06x} is not the most precise", encoded);
+            }
+        }
+    }
+
+    #[test]
+    fn encodes_negative_values() {
+        assert_eq!(from_linear11_f(to_linear11(-40.5).unwrap()), -40.5);
+        assert_eq!(from_linear11_f(to_linear11(-1024.0 * 32768.0).unwrap()), -1024.0 * 32768.0);
+    }
+
+    #[test]
+    fn rounds_to_nearest() {
+        let encoded = to_linear11(85.3).unwrap();
+        assert!((from_linear11_f(encoded) - 85.3).abs() <= 85.3 / 1023.0);
+        assert_eq!(split(encoded).1, -3);
+    }
+
+    #[test]
+    fn rejects_out_of_range() {
+        assert!(to_linear11(1023.0 * 32768.0).is_ok());
+        assert!(to_linear11(1024.0 * 32768.0).is_err());
+        assert!(to_linear11(-1025.0 * 32768.0).is_err());
+        assert!(to_linear11(f32::NAN).is_err());
+        assert!(to_linear11(f32::INFINITY).is_err());
+    }
+}
@@ -1,3 +1,4 @@
 pub mod byte_stuffing;
 pub mod crc;
-pub mod linear11;
\ No newline at end of file
+pub mod linear11;
+pub mod pmbus;
\ No newline at end of file
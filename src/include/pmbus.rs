@@ -0,0 +1,241 @@
+//! Числовые форматы PMBus: LINEAR11, LINEAR16 (с экспонентой из VOUT_MODE) и DIRECT
+//!
+//! LINEAR11 - 5 бит экспоненты и 11 бит мантиссы, оба в дополнительном коде.
+//! LINEAR16 - беззнаковая 16-битная мантисса, экспонента задается командой VOUT_MODE.
+//! DIRECT   - X = (Y * 10^-R - b) / m, коэффициенты m, b, R берутся из документации устройства.
+
+use anyhow::Result;
+use std::collections::HashMap;
+
+use crate::include::linear11;
+
+/// Преобразует значение LINEAR11 в число с плавающей точкой
+pub fn from_linear11(raw: u16) -> f64 {
+    linear11::from_linear11_f(raw) as f64
+}
+
+/// Кодирует значение в LINEAR11, выбирая экспоненту с наилучшей точностью
+/// Значения вне представимого диапазона дают ошибку
+#[cfg(test)]
+pub fn to_linear11(value: f64) -> Result<u16> {
+    linear11::to_linear11(value as f32)
+}
+
+/// Преобразует значение LINEAR16 с заданной экспонентой в число с плавающей точкой
+pub fn from_linear16(raw: u16, exponent: i8) -> f64 {
+    raw as f64 * 2f64.powi(exponent as i32)
+}
+
+/// Кодирует значение в LINEAR16 с заданной экспонентой (округление до ближайшего)
+#[cfg(test)]
+pub fn to_linear16(value: f64, exponent: i8) -> Result<u16> {
+    if !value.is_finite() {
+        return Err(anyhow::anyhow!("LINEAR16 cannot encode {}", value));
+    }
+
+    let mantissa = (value / 2f64.powi(exponent as i32)).round();
+    if !(0.0..=u16::MAX as f64).contains(&mantissa) {
+        return Err(anyhow::anyhow!(
+            "Value {} is outside the LINEAR16 range 0..={} for exponent {}",
+            value,
+            from_linear16(u16::MAX, exponent),
+            exponent
+        ));
+    }
+    Ok(mantissa as u16)
+}
+
+/// Коэффициенты формата DIRECT: X = (Y * 10^-R - b) / m
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DirectCoefficients {
+    pub m: i16,    // Наклон
+    pub b: i16,    // Смещение
+    pub r: i8,     // Десятичная экспонента
+}
+
+/// Преобразует значение DIRECT (Y в дополнительном коде) в число с плавающей точкой
+pub fn from_direct(raw: u16, coefficients: DirectCoefficients) -> Result<f64> {
+    if coefficients.m == 0 {
+        return Err(anyhow::anyhow!("DIRECT coefficient m must not be zero"));
+    }
+    let y = raw as i16 as f64;
+    let scale = 10f64.powi(-(coefficients.r as i32));
+    Ok((y * scale - coefficients.b as f64) / coefficients.m as f64)
+}
+
+/// Кодирует значение в DIRECT: Y = (m * X + b) * 10^R (округление до ближайшего)
+#[cfg(test)]
+pub fn to_direct(value: f64, coefficients: DirectCoefficients) -> Result<u16> {
+    if !value.is_finite() {
+        return Err(anyhow::anyhow!("DIRECT cannot encode {}", value));
+    }
+    let y = ((coefficients.m as f64 * value + coefficients.b as f64)
+        * 10f64.powi(coefficients.r as i32))
+        .round();
+    if !(i16::MIN as f64..=i16::MAX as f64).contains(&y) {
+        return Err(anyhow::anyhow!("Value {} is outside the DIRECT range for {:?}", value, coefficients));
+    }
+    Ok(y as i16 as u16)
+}
+
+/// Режим выходного напряжения из ответа на команду VOUT_MODE (0x20)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VoutMode {
+    /// LINEAR16 с экспонентой (биты 4-0, дополнительный код)
+    Linear { exponent: i8 },
+    /// Коды VID (биты 4-0 - тип VID)
+    Vid { code: u8 },
+    /// Формат DIRECT (коэффициенты задаются отдельно)
+    Direct,
+    /// IEEE 754 половинной точности (PMBus 1.3)
+    IeeeHalf,
+}
+
+impl VoutMode {
+    /// Разбирает байт VOUT_MODE: биты 7-5 - режим, биты 4-0 - параметр
+    pub fn parse(byte: u8) -> Result<Self> {
+        let parameter = byte & 0x1F;
+        match byte >> 5 {
+            0b000 => Ok(VoutMode::Linear { exponent: sign_extend5(parameter) }),
+            0b001 => Ok(VoutMode::Vid { code: parameter }),
+            0b010 => Ok(VoutMode::Direct),
+            0b011 => Ok(VoutMode::IeeeHalf),
+            mode => Err(anyhow::anyhow!("Unsupported VOUT_MODE mode bits {:03b}", mode)),
+        }
+    }
+
+    /// Кодирует режим обратно в байт VOUT_MODE
+    #[cfg(test)]
+    pub fn to_byte(self) -> u8 {
+        match self {
+            VoutMode::Linear { exponent } => (exponent as u8) & 0x1F,
+            VoutMode::Vid { code } => 0x20 | (code & 0x1F),
+            VoutMode::Direct => 0x40,
+            VoutMode::IeeeHalf => 0x60,
+        }
+    }
+}
+
+/// Расширяет знак 5-битного числа
+fn sign_extend5(value: u8) -> i8 {
+    ((value << 3) as i8) >> 3
+}
+
+/// Адрес устройства для кэша VOUT_MODE: (адрес модуля, идентификатор устройства)
+pub type DeviceAddr = (u8, u8);
+
+/// Кэш режимов VOUT_MODE по устройствам
+/// Заполняется ответами на VOUT_MODE и используется для декодирования LINEAR16
+#[derive(Debug, Clone, Default)]
+pub struct VoutModeCache {
+    modes: HashMap<DeviceAddr, VoutMode>,
+}
+
+impl VoutModeCache {
+    /// Запоминает ответ VOUT_MODE устройства, возвращает разобранный режим
+    pub fn update(&mut self, device: DeviceAddr, vout_mode: u8) -> Result<VoutMode> {
+        let mode = VoutMode::parse(vout_mode)?;
+        self.modes.insert(device, mode);
+        Ok(mode)
+    }
+
+    /// Возвращает экспоненту LINEAR16 устройства, если известен линейный режим
+    pub fn exponent(&self, device: DeviceAddr) -> Option<i8> {
+        match self.modes.get(&device) {
+            Some(VoutMode::Linear { exponent }) => Some(*exponent),
+            _ => None,
+        }
+    }
+
+    /// Декодирует значение VOUT_* устройства по закэшированному режиму
+    #[cfg(test)]
+    pub fn decode_vout(&self, device: DeviceAddr, raw: u16) -> Option<f64> {
+        self.exponent(device).map(|exponent| from_linear16(raw, exponent))
+    }
+
+    /// Кодирует уставку напряжения для устройства по закэшированному режиму
+    #[cfg(test)]
+    pub fn encode_vout(&self, device: DeviceAddr, value: f64) -> Result<u16> {
+        match self.exponent(device) {
+            Some(exponent) => to_linear16(value, exponent),
+            None => Err(anyhow::anyhow!(
+                "No LINEAR VOUT_MODE known for device {:?}", device
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // Диапазон 5-битной экспоненты в дополнительном коде
+    fn exponents() -> impl Strategy<Value = i8> {
+        -16i8..=15
+    }
+
+    fn coefficients() -> impl Strategy<Value = DirectCoefficients> {
+        (prop_oneof![-2000i16..=-1, 1i16..=2000], -1000i16..=1000, -4i8..=4)
+            .prop_map(|(m, b, r)| DirectCoefficients { m, b, r })
+    }
+
+    proptest! {
+        #[test]
+        fn linear11_value_round_trip(raw in any::<u16>()) {
+            let value = from_linear11(raw);
+            prop_assert_eq!(from_linear11(to_linear11(value).unwrap()), value);
+        }
+
+        #[test]
+        fn linear11_encode_error_bounded(value in -1023.0f64 * 32768.0..=1023.0 * 32768.0) {
+            let decoded = from_linear11(to_linear11(value).unwrap());
+            // Погрешность не больше половины шага выбранной экспоненты
+            let step = (value.abs() / 1023.0).max(2f64.powi(-16));
+            prop_assert!((decoded - value).abs() <= step);
+        }
+
+        #[test]
+        fn linear16_code_round_trip(raw in any::<u16>(), exponent in exponents()) {
+            prop_assert_eq!(to_linear16(from_linear16(raw, exponent), exponent).unwrap(), raw);
+        }
+
+        #[test]
+        fn direct_code_round_trip(raw in any::<u16>(), coefficients in coefficients()) {
+            let value = from_direct(raw, coefficients).unwrap();
+            prop_assert_eq!(to_direct(value, coefficients).unwrap(), raw);
+        }
+
+        // Для DIRECT и IEEE биты параметра не используются, поэтому проверяем LINEAR и VID
+        #[test]
+        fn vout_mode_byte_round_trip(byte in 0u8..0x40) {
+            prop_assert_eq!(VoutMode::parse(byte).unwrap().to_byte(), byte);
+        }
+    }
+
+    #[test]
+    fn linear11_rejects_out_of_range() {
+        assert!(to_linear11(1023.0 * 32768.0 + 20000.0).is_err());
+        assert!(to_linear11(-1025.0 * 32768.0).is_err());
+        assert!(to_linear11(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn linear16_rejects_out_of_range() {
+        assert!(to_linear16(-1.0, -12).is_err());
+        assert!(to_linear16(16.0, -12).is_err());
+    }
+
+    #[test]
+    fn vout_mode_cache_feeds_linear16() {
+        let mut cache = VoutModeCache::default();
+        assert_eq!(cache.decode_vout((1, 2), 0x0CCD), None);
+
+        // VOUT_MODE = 0x14: линейный режим, экспонента -12
+        cache.update((1, 2), 0x14).unwrap();
+        assert_eq!(cache.exponent((1, 2)), Some(-12));
+        assert_eq!(cache.encode_vout((1, 2), 0.8).unwrap(), 0x0CCD);
+        let decoded = cache.decode_vout((1, 2), 0x0CCD).unwrap();
+        assert!((decoded - 0.8).abs() < 2f64.powi(-12));
+    }
+}
@@ -0,0 +1,782 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use crate::channels::{record_channel, RecordReceiver, RecordSender, RECORD_TAP_CAPACITY};
+use crate::config::InfluxConfig;
+use crate::logging::RateLimiter;
+use crate::metrics::METRICS;
+use crate::record::FrameRecord;
+
+/// Имя маршрута InfluxDB для метрик и логов
+const INFLUX_ROUTE: &str = "INFLUX";
+
+/// Интервал предупреждений об ошибках записи
+const WARN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Пакетов в очереди отправки по HTTP; при заполнении пакеты откладываются в файл
+const BATCH_QUEUE_CAPACITY: usize = 4;
+
+/// Максимальный размер ответа сервера, который читается целиком
+const MAX_RESPONSE_SIZE: usize = 64 * 1024;
+
+/// Экранирует имя измерения (запятые и пробелы)
+fn escape_measurement(text: &str) -> String {
+    text.replace('\\', "\\\\").replace(',', "\\,").replace(' ', "\\ ")
+}
+
+/// Экранирует ключ или значение тега (запятые, '=' и пробелы)
+fn escape_tag(text: &str) -> String {
+    escape_measurement(text).replace('=', "\\=")
+}
+
+/// Формирует строку line protocol для записи с корректным CRC и значением
+/// Теги: source, bm, mcu, dev_id, param; поля: value, min, max, alarms
+/// Записи без значения или с нечисловым значением (NaN, бесконечность) пропускаются
+pub fn format_line(measurement: &str, source: &str, record: &FrameRecord) -> Option<String> {
+    if record.crc != "ok" {
+        return None;
+    }
+    let value = record.value.filter(|value| value.is_finite())?;
+
+    let mut line = escape_measurement(measurement);
+    line.push_str(&format!(",source={}", escape_tag(source)));
+    if let Some(bm) = record.bm {
+        line.push_str(&format!(",bm={}", bm));
+    }
+    if let Some(mcu) = record.mcu {
+        line.push_str(&format!(",mcu={}", mcu));
+    }
+    if let Some(dev_id) = record.dev_id {
+        line.push_str(&format!(",dev_id={}", dev_id));
+    }
+    // Без словаря параметр называется по идентификатору
+    let param = record.param.clone()
+        .or_else(|| record.prm_id.map(|prm_id| format!("prm{}", prm_id)));
+    if let Some(param) = param.filter(|param| !param.is_empty()) {
+        line.push_str(&format!(",param={}", escape_tag(&param)));
+    }
+
+    line.push_str(&format!(" value={}", value));
+    if let Some(min) = record.value_min.filter(|min| min.is_finite()) {
+        line.push_str(&format!(",min={}", min));
+    }
+    if let Some(max) = record.value_max.filter(|max| max.is_finite()) {
+        line.push_str(&format!(",max={}", max));
+    }
+    if let Some(alarms) = record.alarms {
+        line.push_str(&format!(",alarms={}i", alarms));
+    }
+
+    let timestamp = record.timestamp.unwrap_or_else(Utc::now);
+    if let Some(nanos) = timestamp.timestamp_nanos_opt() {
+        line.push_str(&format!(" {}", nanos));
+    }
+    Some(line)
+}
+
+/// Адрес записи по HTTP
+#[derive(Debug, Clone)]
+struct HttpTarget {
+    host: String,                        // Хост и порт для подключения
+    path: String,                        // Путь со строкой запроса
+    token: Option<String>,               // Токен авторизации
+    timeout: Duration,                   // Таймаут запроса
+}
+
+/// Ошибка отправки пакета по HTTP
+enum SendError {
+    Retry(anyhow::Error),                // Сервер недоступен - повторить позже
+    Rejected(anyhow::Error),             // Сервер отверг данные - повтор не поможет
+}
+
+impl HttpTarget {
+    /// Разбирает адрес вида http://host[:port]/path?query
+    fn parse(url: &str, token: Option<String>, timeout: Duration) -> Result<Self> {
+        let rest = url.strip_prefix("http://")
+            .context(format!("InfluxDB url must start with http://: {}", url))?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        if authority.is_empty() {
+            return Err(anyhow::anyhow!("InfluxDB url has no host: {}", url));
+        }
+        let host = if authority.contains(':') { authority.to_string() } else { format!("{}:80", authority) };
+        Ok(Self { host, path: path.to_string(), token, timeout })
+    }
+
+    /// Отправляет строки одним POST-запросом; успех - любой код 2xx
+    async fn send(&self, body: &str) -> std::result::Result<(), SendError> {
+        match tokio::time::timeout(self.timeout, self.request(body)).await {
+            Ok(Ok(status)) if (200..300).contains(&status) => Ok(()),
+            // Ошибки в данных (кроме 408 и 429) повторять бессмысленно
+            Ok(Ok(status)) if (400..500).contains(&status) && status != 408 && status != 429 =>
+                Err(SendError::Rejected(anyhow::anyhow!("HTTP status {}", status))),
+            Ok(Ok(status)) => Err(SendError::Retry(anyhow::anyhow!("HTTP status {}", status))),
+            Ok(Err(e)) => Err(SendError::Retry(e)),
+            Err(_) => Err(SendError::Retry(anyhow::anyhow!("Timeout after {:?}", self.timeout))),
+        }
+    }
+
+    /// Выполняет запрос и возвращает код статуса ответа
+    async fn request(&self, body: &str) -> Result<u16> {
+        let mut stream = TcpStream::connect(&self.host).await
+            .context(format!("Failed to connect to {}", self.host))?;
+
+        let mut head = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.path, self.host, body.len()
+        );
+        if let Some(token) = &self.token {
+            head.push_str(&format!("Authorization: Token {}\r\n", token));
+        }
+        head.push_str("\r\n");
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(body.as_bytes()).await?;
+
+        // Нужна только строка статуса; ответ читается до закрытия соединения
+        let mut response = Vec::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            let n = stream.read(&mut buf).await?;
+            if n == 0 || response.len() >= MAX_RESPONSE_SIZE {
+                break;
+            }
+            response.extend_from_slice(&buf[..n]);
+        }
+        let status_line = response.split(|&b| b == b'\n').next().unwrap_or_default();
+        String::from_utf8_lossy(status_line)
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .context("Invalid HTTP response")
+    }
+}
+
+/// Файл строк, не отправленных по HTTP
+/// Строки дописываются, пока сервер недоступен или очередь отправки заполнена,
+/// и отправляются заново по порядку
+struct Spool {
+    path: String,                        // Путь к файлу
+    max_bytes: u64,                      // Предельный размер файла
+    bytes: u64,                          // Текущий размер файла
+}
+
+impl Spool {
+    /// Открывает файл; строки, оставшиеся с прошлого запуска, будут отправлены
+    fn open(path: &str, max_bytes: u64) -> Self {
+        let bytes = fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+        if bytes > 0 {
+            info!(path, bytes, "InfluxDB spool has pending lines");
+        }
+        Self { path: path.to_string(), max_bytes, bytes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes == 0
+    }
+
+    /// Дописывает строки; при превышении предельного размера возвращает ошибку
+    fn append(&mut self, lines: &str) -> Result<()> {
+        if self.bytes + lines.len() as u64 > self.max_bytes {
+            return Err(anyhow::anyhow!("Spool file is full ({} bytes): {}", self.bytes, self.path));
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)
+            .context(format!("Failed to open spool file: {}", self.path))?;
+        file.write_all(lines.as_bytes())
+            .context(format!("Failed to write spool file: {}", self.path))?;
+        self.bytes += lines.len() as u64;
+        Ok(())
+    }
+
+    /// Читает первые len байт файла пакетами по batch_size строк
+    /// Читается без блокировки выходов: строки, дописанные позже, не затрагиваются
+    fn read_batches(path: &str, len: u64, batch_size: usize) -> Result<Vec<String>> {
+        let file = File::open(path).context(format!("Failed to open spool file: {}", path))?;
+        let mut batches = Vec::new();
+        let mut batch = String::new();
+        let mut count = 0;
+        for line in BufReader::new(file.take(len)).lines() {
+            let line = line.context(format!("Failed to read spool file: {}", path))?;
+            if line.is_empty() {
+                continue;
+            }
+            batch.push_str(&line);
+            batch.push('\n');
+            count += 1;
+            if count == batch_size {
+                batches.push(std::mem::take(&mut batch));
+                count = 0;
+            }
+        }
+        if !batch.is_empty() {
+            batches.push(batch);
+        }
+        Ok(batches)
+    }
+
+    /// Убирает отправленные пакеты из первых len байт файла; строки, дописанные
+    /// после чтения, остаются за неотправленными (пустой остаток - удаление файла)
+    fn consume(&mut self, batches: &[String], sent: usize, len: u64) -> Result<()> {
+        if sent == 0 {
+            return Ok(());
+        }
+        let mut tail = String::new();
+        let mut file = File::open(&self.path).context(format!("Failed to open spool file: {}", self.path))?;
+        file.seek(SeekFrom::Start(len))?;
+        file.read_to_string(&mut tail).context(format!("Failed to read spool file: {}", self.path))?;
+
+        let content = batches[sent..].concat() + &tail;
+        if content.is_empty() {
+            fs::remove_file(&self.path).context(format!("Failed to remove spool file: {}", self.path))?;
+            self.bytes = 0;
+            return Ok(());
+        }
+        let temp = format!("{}.tmp", self.path);
+        fs::write(&temp, &content).context(format!("Failed to write spool file: {}", temp))?;
+        fs::rename(&temp, &self.path).context(format!("Failed to replace spool file: {}", self.path))?;
+        self.bytes = content.len() as u64;
+        Ok(())
+    }
+}
+
+/// Локальные выходы экспорта: файл строк и файл отложенных строк
+/// Общие для сборщика пакетов, отправки по HTTP и канала записей
+struct Outputs {
+    measurement: String,                 // Имя измерения
+    source: &'static str,                // Источник пакетов (uart, dump, ...)
+    file: Option<(String, File)>,        // Файл для дописывания строк
+    spool: Spool,                        // Файл отложенных строк
+    http: bool,                          // Строки отправляются по HTTP
+    warn_limiter: RateLimiter,           // Ограничение предупреждений
+}
+
+type SharedOutputs = Arc<Mutex<Outputs>>;
+
+/// Блокирует выходы; файлы остаются пригодными и после паники другой задачи
+fn lock(outputs: &SharedOutputs) -> MutexGuard<'_, Outputs> {
+    outputs.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl Outputs {
+    /// Дописывает строки в файл экспорта
+    fn write_file(&mut self, lines: &str) {
+        if let Some((path, file)) = &mut self.file {
+            if let Err(e) = file.write_all(lines.as_bytes()) {
+                let message = format!("Failed to write InfluxDB export file {}: {}", path, e);
+                self.warn(format_args!("{}", message));
+            }
+        }
+    }
+
+    /// Откладывает строки до отправки по HTTP
+    fn spool(&mut self, lines: &str, count: usize) {
+        if let Err(e) = self.spool.append(lines) {
+            METRICS.route_failed.add(&[INFLUX_ROUTE], count as u64);
+            self.warn(format_args!("{} lines dropped: {}", count, e));
+        }
+    }
+
+    /// Записывает строки, не попавшие в очередь отправки: в файл и в отложенные строки
+    fn overflow(&mut self, lines: &str, count: usize) {
+        self.write_file(lines);
+        if self.http {
+            self.spool(lines, count);
+        } else {
+            METRICS.route_sent.add(&[INFLUX_ROUTE], count as u64);
+        }
+    }
+
+    fn warn(&mut self, message: std::fmt::Arguments) {
+        if let Some(suppressed) = self.warn_limiter.check() {
+            warn!(suppressed, "{}", message);
+        }
+    }
+}
+
+/// Канал декодированных записей от обработчика пакетов к экспорту
+/// Для дампа обработчик ждет места в канале, и экспортируется каждая запись;
+/// живой источник не ждет: записи, не поместившиеся в канал, сразу пишутся в выходы
+#[derive(Clone)]
+pub struct ExportTap {
+    sender: RecordSender,                // Канал к сборщику пакетов
+    backpressure: bool,                  // Ждать места в канале (дамп)
+    outputs: SharedOutputs,              // Выходы для записей при переполнении
+}
+
+impl ExportTap {
+    /// Передает запись на экспорт
+    pub async fn export(&self, record: FrameRecord) {
+        if self.backpressure {
+            // Канал закрыт только после остановки экспорта
+            let _ = self.sender.send(record).await;
+            return;
+        }
+        if let Err(TrySendError::Full(record)) = self.sender.try_send(record) {
+            let mut outputs = lock(&self.outputs);
+            if let Some(line) = format_line(&outputs.measurement, outputs.source, &record) {
+                outputs.overflow(&format!("{}\n", line), 1);
+            }
+        }
+    }
+}
+
+/// Запущенный экспорт; при остановке сбрасывает накопленный пакет
+pub struct InfluxHandle {
+    stop: oneshot::Sender<()>,           // Сигнал остановки сборщика
+    tasks: Vec<JoinHandle<()>>,          // Сборщик пакетов и отправка по HTTP
+}
+
+impl InfluxHandle {
+    /// Останавливает экспорт, дождавшись сброса последнего пакета и отправки очереди
+    /// (не дольше timeout; неотправленные пакеты очереди при этом теряются)
+    pub async fn finish(self, timeout: Duration) {
+        let _ = self.stop.send(());
+        let aborts: Vec<_> = self.tasks.iter().map(|task| task.abort_handle()).collect();
+        let tasks = async {
+            for task in self.tasks {
+                let _ = task.await;
+            }
+        };
+        if tokio::time::timeout(timeout, tasks).await.is_err() {
+            warn!("InfluxDB export did not finish in {:?}", timeout);
+            aborts.iter().for_each(|abort| abort.abort());
+        }
+    }
+}
+
+/// Экспорт значений параметров в InfluxDB line protocol
+///
+/// Сборщик копит строки в пакете и сбрасывает его по заполнении batch_size или раз
+/// в flush_interval: дописывает в файл и ставит в очередь отправки на url. Сборщик
+/// не ждет сеть: если очередь заполнена, пакет дописывается в файл отложенных строк.
+/// Отдельная задача отправляет пакеты POST-запросами; пока файл отложенных строк
+/// не пуст, новые пакеты идут туда же. Раз в retry_delay после ошибки
+/// (и сразу, если строки отложены из-за заполненной очереди) они отправляются заново
+pub struct InfluxExporter {
+    outputs: SharedOutputs,              // Файл строк и файл отложенных строк
+    http: Option<HttpTarget>,            // Адрес записи по HTTP
+    batch_size: usize,                   // Строк в пакете
+    flush_interval: Duration,            // Период сброса неполного пакета
+    retry_delay: Duration,               // Пауза перед повторной отправкой
+}
+
+impl InfluxExporter {
+    /// Создает экспорт по конфигурации; None, если не заданы ни файл, ни url
+    pub fn from_config(config: &InfluxConfig, source: &'static str) -> Result<Option<Self>> {
+        let file_path = config.file.as_deref().filter(|path| !path.is_empty());
+        let url = config.url.as_deref().filter(|url| !url.is_empty());
+        if file_path.is_none() && url.is_none() {
+            return Ok(None);
+        }
+        if config.batch_size == 0 {
+            return Err(anyhow::anyhow!("InfluxDB batch_size must be positive"));
+        }
+        if config.measurement.is_empty() {
+            return Err(anyhow::anyhow!("InfluxDB measurement must not be empty"));
+        }
+
+        let file = file_path
+            .map(|path| {
+                OpenOptions::new().create(true).append(true).open(path)
+                    .context(format!("Failed to open InfluxDB export file: {}", path))
+                    .map(|file| (path.to_string(), file))
+            })
+            .transpose()?;
+        let timeout = Duration::from_secs_f64(config.timeout_secs.max(0.1));
+        let http = url
+            .map(|url| HttpTarget::parse(url, config.token.clone(), timeout))
+            .transpose()?;
+        let outputs = Outputs {
+            measurement: config.measurement.clone(),
+            source,
+            file,
+            spool: Spool::open(&config.spool_file, config.spool_max_bytes),
+            http: http.is_some(),
+            warn_limiter: RateLimiter::new(WARN_INTERVAL),
+        };
+
+        Ok(Some(Self {
+            outputs: Arc::new(Mutex::new(outputs)),
+            http,
+            batch_size: config.batch_size,
+            flush_interval: Duration::from_secs_f64(config.flush_interval_secs.max(0.01)),
+            retry_delay: Duration::from_secs_f64(config.retry_delay_secs.max(0.1)),
+        }))
+    }
+
+    /// Запускает сборщик пакетов и отправку по HTTP; возвращает канал записей для
+    /// обработчика пакетов (backpressure - ждать места в канале, для дампа)
+    pub fn start(self, backpressure: bool) -> (InfluxHandle, ExportTap) {
+        info!(
+            file = lock(&self.outputs).file.as_ref().map(|(path, _)| path.clone()),
+            host = self.http.as_ref().map(|http| http.host.as_str()),
+            batch_size = self.batch_size,
+            backpressure,
+            "InfluxDB export enabled"
+        );
+        let (record_sender, records) = record_channel(RECORD_TAP_CAPACITY);
+        let (batch_sender, batches) = mpsc::channel(BATCH_QUEUE_CAPACITY);
+        let (stop, stop_receiver) = oneshot::channel();
+
+        let mut tasks = Vec::new();
+        let queue = self.uploader().map(|uploader| {
+            tasks.push(tokio::spawn(uploader.run(batches, self.flush_interval)));
+            batch_sender
+        });
+        let batcher = Batcher {
+            outputs: Arc::clone(&self.outputs),
+            queue,
+            batch_size: self.batch_size,
+        };
+        tasks.insert(0, tokio::spawn(batcher.run(records, stop_receiver, self.flush_interval)));
+
+        let tap = ExportTap { sender: record_sender, backpressure, outputs: self.outputs };
+        (InfluxHandle { stop, tasks }, tap)
+    }
+
+    /// Отправка по HTTP, если задан url
+    fn uploader(&self) -> Option<Uploader> {
+        Some(Uploader {
+            http: self.http.clone()?,
+            outputs: Arc::clone(&self.outputs),
+            batch_size: self.batch_size,
+            retry_delay: self.retry_delay,
+            retry_at: None,
+        })
+    }
+}
+
+/// Сборщик пакетов строк: только файлы и очередь, без ожидания сети
+struct Batcher {
+    outputs: SharedOutputs,                          // Выходы экспорта
+    queue: Option<mpsc::Sender<(String, usize)>>,    // Очередь отправки по HTTP (пакет, строк)
+    batch_size: usize,                               // Строк в пакете
+}
+
+impl Batcher {
+    async fn run(mut self, mut records: RecordReceiver, mut stop: oneshot::Receiver<()>, flush_interval: Duration) {
+        let mut interval = tokio::time::interval(flush_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut batch = String::new();
+        let mut count = 0;
+        loop {
+            tokio::select! {
+                record = records.recv() => match record {
+                    Some(record) => self.push(&record, &mut batch, &mut count),
+                    None => break,
+                },
+                _ = interval.tick() => {
+                    if count > 0 {
+                        self.flush(&std::mem::take(&mut batch), count);
+                        count = 0;
+                    }
+                }
+                _ = &mut stop => {
+                    // Забираем записи, уже стоящие в очереди
+                    while let Ok(record) = records.try_recv() {
+                        self.push(&record, &mut batch, &mut count);
+                    }
+                    break;
+                }
+            }
+        }
+        if count > 0 {
+            self.flush(&batch, count);
+        }
+    }
+
+    /// Добавляет строку записи в пакет; заполненный пакет сбрасывается
+    fn push(&mut self, record: &FrameRecord, batch: &mut String, count: &mut usize) {
+        let line = {
+            let outputs = lock(&self.outputs);
+            format_line(&outputs.measurement, outputs.source, record)
+        };
+        let Some(line) = line else {
+            return;
+        };
+        batch.push_str(&line);
+        batch.push('\n');
+        *count += 1;
+        if *count >= self.batch_size {
+            self.flush(&std::mem::take(batch), *count);
+            *count = 0;
+        }
+    }
+
+    /// Дописывает пакет в файл и ставит в очередь отправки; при заполненной
+    /// очереди пакет откладывается
+    fn flush(&mut self, batch: &str, count: usize) {
+        let mut outputs = lock(&self.outputs);
+        outputs.write_file(batch);
+        let Some(queue) = &self.queue else {
+            METRICS.route_sent.add(&[INFLUX_ROUTE], count as u64);
+            return;
+        };
+        if queue.try_send((batch.to_string(), count)).is_err() {
+            outputs.spool(batch, count);
+        }
+    }
+}
+
+/// Отправка пакетов и отложенных строк по HTTP
+struct Uploader {
+    http: HttpTarget,                    // Адрес записи
+    outputs: SharedOutputs,              // Выходы экспорта (файл отложенных строк)
+    batch_size: usize,                   // Строк в пакете при повторной отправке
+    retry_delay: Duration,               // Пауза перед повторной отправкой после ошибки
+    retry_at: Option<Instant>,           // Время следующей попытки после ошибки
+}
+
+impl Uploader {
+    async fn run(mut self, mut batches: mpsc::Receiver<(String, usize)>, check_interval: Duration) {
+        let mut interval = tokio::time::interval(check_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                batch = batches.recv() => match batch {
+                    Some((batch, count)) => self.send(&batch, count).await,
+                    None => break,
+                },
+                _ = interval.tick() => self.retry_spool().await,
+            }
+        }
+    }
+
+    /// Отправляет пакет; при ошибке связи откладывает его
+    async fn send(&mut self, batch: &str, count: usize) {
+        // Пока есть отложенные строки, новые встают за ними
+        {
+            let mut outputs = lock(&self.outputs);
+            if !outputs.spool.is_empty() {
+                outputs.spool(batch, count);
+                return;
+            }
+        }
+        match self.http.send(batch).await {
+            Ok(()) => METRICS.route_sent.add(&[INFLUX_ROUTE], count as u64),
+            Err(SendError::Retry(e)) => {
+                let mut outputs = lock(&self.outputs);
+                outputs.warn(format_args!("InfluxDB write failed, spooling {} lines: {}", count, e));
+                outputs.spool(batch, count);
+                self.retry_at = Some(Instant::now() + self.retry_delay);
+            }
+            Err(SendError::Rejected(e)) => {
+                METRICS.route_failed.add(&[INFLUX_ROUTE], count as u64);
+                lock(&self.outputs).warn(format_args!("InfluxDB rejected {} lines: {}", count, e));
+            }
+        }
+    }
+
+    /// Отправляет отложенные строки, если они есть и подошло время повтора
+    async fn retry_spool(&mut self) {
+        if self.retry_at.is_some_and(|retry_at| Instant::now() < retry_at) {
+            return;
+        }
+        let (path, len) = {
+            let outputs = lock(&self.outputs);
+            if outputs.spool.is_empty() {
+                return;
+            }
+            (outputs.spool.path.clone(), outputs.spool.bytes)
+        };
+        let batches = match Spool::read_batches(&path, len, self.batch_size) {
+            Ok(batches) => batches,
+            Err(e) => {
+                lock(&self.outputs).warn(format_args!("{}", e));
+                self.retry_at = Some(Instant::now() + self.retry_delay);
+                return;
+            }
+        };
+
+        let mut sent = 0;
+        let mut failed = false;
+        for batch in &batches {
+            let count = batch.lines().count() as u64;
+            match self.http.send(batch).await {
+                Ok(()) => METRICS.route_sent.add(&[INFLUX_ROUTE], count),
+                Err(SendError::Retry(e)) => {
+                    lock(&self.outputs).warn(format_args!("InfluxDB spool resend failed: {}", e));
+                    failed = true;
+                    break;
+                }
+                Err(SendError::Rejected(e)) => {
+                    METRICS.route_failed.add(&[INFLUX_ROUTE], count);
+                    lock(&self.outputs).warn(format_args!("InfluxDB rejected {} spooled lines: {}", count, e));
+                }
+            }
+            sent += 1;
+        }
+
+        let mut outputs = lock(&self.outputs);
+        if let Err(e) = outputs.spool.consume(&batches, sent, len) {
+            outputs.warn(format_args!("{}", e));
+        }
+        if failed {
+            self.retry_at = Some(Instant::now() + self.retry_delay);
+        } else {
+            self.retry_at = None;
+            if outputs.spool.is_empty() {
+                info!(batches = sent, "InfluxDB spool delivered");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inspect::split_frames;
+    use crate::protocol::CrcCheck;
+    use crate::psorter::PSorter;
+    use crate::test_frames::{self, addr, src};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn line_protocol_format() {
+        let record = FrameRecord {
+            crc: String::from("ok"),
+            bm: Some(3),
+            mcu: Some(1),
+            dev_id: Some(2),
+            prm_id: Some(17),
+            param: Some(String::from("FPGA temp, C")),
+            value: Some(42.5),
+            value_min: Some(40.0),
+            alarms: Some(1),
+            timestamp: chrono::DateTime::from_timestamp(1, 5),
+            ..Default::default()
+        };
+        assert_eq!(
+            format_line("hwmon", "uart", &record).unwrap(),
+            "hwmon,source=uart,bm=3,mcu=1,dev_id=2,param=FPGA\\ temp\\,\\ C value=42.5,min=40,alarms=1i 1000000005"
+        );
+
+        let failed = FrameRecord { crc: String::from("fail"), ..record.clone() };
+        assert!(format_line("hwmon", "uart", &failed).is_none());
+        let nan = FrameRecord { value: Some(f32::NAN), ..record };
+        assert!(format_line("hwmon", "uart", &nan).is_none());
+    }
+
+    #[tokio::test]
+    async fn spooled_lines_are_resent() {
+        let spool_path = std::env::temp_dir().join(format!("hwmon-influx-test-{}.spool", std::process::id()));
+        let _ = fs::remove_file(&spool_path);
+
+        // Адрес без сервера: пакет уходит в файл отложенных строк
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let config = InfluxConfig {
+            url: Some(format!("http://{}/write?db=hwmon", addr)),
+            spool_file: spool_path.to_string_lossy().into_owned(),
+            retry_delay_secs: 0.1,
+            ..Default::default()
+        };
+        let exporter = InfluxExporter::from_config(&config, "test").unwrap().unwrap();
+        let mut uploader = exporter.uploader().unwrap();
+        uploader.send("hwmon value=1 1\n", 1).await;
+        uploader.send("hwmon value=2 2\n", 1).await;
+        assert_eq!(fs::read_to_string(&spool_path).unwrap(), "hwmon value=1 1\nhwmon value=2 2\n");
+
+        // Сервер появился: отложенные строки отправляются по порядку, файл удаляется
+        let listener = TcpListener::bind(addr).await.unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !request.ends_with(b"hwmon value=2 2\n") {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream.write_all(b"HTTP/1.1 204 No Content\r\n\r\n").await.unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        uploader.retry_spool().await;
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /write?db=hwmon HTTP/1.1\r\n"));
+        assert!(request.ends_with("\r\n\r\nhwmon value=1 1\nhwmon value=2 2\n"));
+        assert!(!spool_path.exists());
+    }
+
+    /// Дамп из frames кадров температуры (0xE820 = 4.0 в Linear11), каждый десятый с ошибкой CRC
+    fn dump(frames: u16) -> Vec<u8> {
+        let mut dump = Vec::new();
+        for index in 0..frames {
+            let header = [addr(2, 3, 1), 0x8000, src(2, index % 6 + 1), 10];
+            let mut package = test_frames::package(CrcCheck::default(), header, &test_frames::words(&[0xE820; 3]));
+            if index % 10 == 9 {
+                package[14] ^= 0x01;
+            }
+            dump.extend(test_frames::framed(package));
+        }
+        dump
+    }
+
+    #[tokio::test]
+    async fn full_dump_exported() {
+        let path = std::env::temp_dir().join(format!("hwmon-influx-export-{}.lp", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let config = InfluxConfig {
+            file: Some(path.to_string_lossy().into_owned()),
+            batch_size: 100,
+            ..Default::default()
+        };
+        let exporter = InfluxExporter::from_config(&config, "dump").unwrap().unwrap();
+
+        // Записей больше емкости канала: обработка ждет экспорт, как в обработчике пакетов
+        let (handle, export_tap) = exporter.start(true);
+        let mut sorter = PSorter::new();
+        sorter.set_export_tap(export_tap);
+        let dump = dump(5000);
+        for package in split_frames(&dump) {
+            sorter.slot_input_package(package, |_, _| {});
+            let (export_tap, records) = sorter.take_exports().unwrap();
+            for record in records {
+                export_tap.export(record).await;
+            }
+        }
+        handle.finish(Duration::from_secs(5)).await;
+
+        assert_eq!(sorter.crc_correct_counter(), 4500);
+        let lines = fs::read_to_string(&path).unwrap();
+        assert_eq!(lines.lines().count(), 4500);
+        assert!(lines.lines().all(|line| line.starts_with("hwmon,source=dump,bm=3,mcu=1,dev_id=")));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn live_overflow_written_directly() {
+        let path = std::env::temp_dir().join(format!("hwmon-influx-overflow-{}.lp", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let config = InfluxConfig {
+            file: Some(path.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        let exporter = InfluxExporter::from_config(&config, "uart").unwrap().unwrap();
+
+        // Сборщик не получает управления: записи сверх емкости канала пишутся сразу, без ожидания
+        let (handle, export_tap) = exporter.start(false);
+        let record = FrameRecord { crc: String::from("ok"), value: Some(1.0), ..Default::default() };
+        for _ in 0..RECORD_TAP_CAPACITY + 100 {
+            export_tap.export(record.clone()).await;
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 100);
+
+        handle.finish(Duration::from_secs(5)).await;
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), RECORD_TAP_CAPACITY + 100);
+        fs::remove_file(&path).unwrap();
+    }
+}
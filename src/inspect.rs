@@ -0,0 +1,468 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+
+use crate::psorter::{route_name, InspectedFrame, PSorter, PackageStruct};
+use crate::record::{device_name, param_name, DeviceKey, FrameRecord};
+
+/// Кадры с ошибкой CRC, разделенные не более чем таким числом кадров, относятся к одному кластеру
+const CLUSTER_MAX_DISTANCE: usize = 3;
+
+/// Пропуск считается аномальным, если он больше медианного интервала в столько раз
+const GAP_FACTOR: usize = 3;
+
+/// Формат вывода отчетов INSPECT и DIFF
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    /// Таблица для чтения человеком
+    Table,
+    /// JSON-строки (сводка и сравнение - один JSON-объект)
+    Json,
+}
+
+impl ReportFormat {
+    /// Разбирает формат из аргумента командной строки
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "table" => Ok(ReportFormat::Table),
+            "json" => Ok(ReportFormat::Json),
+            other => Err(anyhow::anyhow!("Unknown report format: {} (expected table/json)", other)),
+        }
+    }
+}
+
+/// Фильтр по результату проверки CRC
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CrcFilter {
+    /// Все кадры
+    All,
+    /// Только кадры с корректным CRC
+    Ok,
+    /// Только кадры с ошибкой CRC
+    Fail,
+}
+
+impl CrcFilter {
+    /// Разбирает фильтр из аргумента командной строки
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "all" => Ok(CrcFilter::All),
+            "ok" => Ok(CrcFilter::Ok),
+            "fail" => Ok(CrcFilter::Fail),
+            other => Err(anyhow::anyhow!("Unknown CRC filter: {} (expected all/ok/fail)", other)),
+        }
+    }
+}
+
+/// Фильтры кадров и записей; незаданные поля подходят под любое значение
+#[derive(Debug, Clone)]
+pub struct InspectFilter {
+    pub bm: Option<u8>,              // Номер BM
+    pub dev_id: Option<u8>,          // Идентификатор устройства
+    pub prm_id: Option<u16>,         // Идентификатор параметра
+    pub route: Option<String>,       // Имя маршрута (TMonitor, SMonitor, ...)
+    pub crc: CrcFilter,              // Результат проверки CRC
+}
+
+impl InspectFilter {
+    /// Подходит ли кадр под фильтры CRC и маршрута
+    fn matches_frame(&self, frame: &InspectedFrame) -> bool {
+        let crc = match self.crc {
+            CrcFilter::All => true,
+            CrcFilter::Ok => frame.crc_ok,
+            CrcFilter::Fail => !frame.crc_ok,
+        };
+        let route = self.route.as_ref().is_none_or(|route| {
+            frame.route.is_some_and(|pack_type| route_name(pack_type).eq_ignore_ascii_case(route))
+        });
+        crc && route
+    }
+
+    /// Подходит ли запись под фильтры заголовка
+    fn matches_record(&self, record: &PackageStruct) -> bool {
+        self.bm.is_none_or(|bm| record.module_addr_bm == bm)
+            && self.dev_id.is_none_or(|dev_id| record.dev_id == dev_id)
+            && self.prm_id.is_none_or(|prm_id| record.prm_id == prm_id)
+    }
+
+    /// Заданы ли фильтры заголовка (кадры без записей под них не подходят)
+    fn has_record_filters(&self) -> bool {
+        self.bm.is_some() || self.dev_id.is_some() || self.prm_id.is_some()
+    }
+}
+
+/// Сводка по параметру устройства
+#[derive(Debug, Default, Serialize)]
+struct ParamSummary {
+    frames: u64,                     // Записей параметра
+    value_min: Option<f32>,          // Минимальное значение
+    value_max: Option<f32>,          // Максимальное значение
+    median_gap: Option<usize>,       // Медианный интервал между записями, кадров
+    max_gap: Option<usize>,          // Наибольший интервал между записями, кадров
+    max_gap_at: Option<usize>,       // Кадр, которым закончился наибольший интервал
+    #[serde(skip)]
+    positions: Vec<usize>,           // Номера кадров с записями параметра
+}
+
+/// Кластер кадров с ошибкой CRC
+#[derive(Debug, Serialize)]
+struct CrcCluster {
+    first_frame: usize,              // Первый кадр кластера
+    last_frame: usize,               // Последний кадр кластера
+    failures: u64,                   // Кадров с ошибкой CRC в кластере
+}
+
+/// Сводка по дампу
+#[derive(Debug, Default, Serialize)]
+struct Summary {
+    frames: u64,                                     // Кадров, подошедших под фильтры
+    crc_ok: u64,                                     // С корректным CRC
+    crc_fail: u64,                                   // С ошибкой CRC
+    parse_errors: u64,                               // С корректным CRC, не прошедших разбор
+    routes: BTreeMap<&'static str, u64>,             // Кадров по маршрутам
+    devices: BTreeMap<String, u64>,                  // Кадров по устройствам
+    params: BTreeMap<String, ParamSummary>,          // Сводка по параметрам устройств
+    crc_clusters: Vec<CrcCluster>,                   // Кластеры ошибок CRC
+    #[serde(skip)]
+    device_keys: BTreeMap<DeviceKey, u64>,
+    #[serde(skip)]
+    param_keys: BTreeMap<(DeviceKey, u16), ParamSummary>,
+}
+
+/// Разбирает дамп без запуска конвейера и ZMQ и печатает кадры или сводку
+/// Дамп не содержит меток времени, поэтому пропуски измеряются в кадрах
+pub fn run(path: &str, sorter: PSorter, filter: &InspectFilter, format: ReportFormat, summary: bool) -> Result<()> {
+    let buffer = fs::read(path).context(format!("Failed to read dump file: {}", path))?;
+    let mut out = BufWriter::new(io::stdout().lock());
+
+    match report(&buffer, sorter, filter, format, summary, &mut out) {
+        // Вывод оборван (например, передан в head) - это не ошибка
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        other => other.context("Failed to write inspect output"),
+    }
+}
+
+/// Печатает кадры дампа или сводку по ним в `out`
+fn report(
+    buffer: &[u8],
+    mut sorter: PSorter,
+    filter: &InspectFilter,
+    format: ReportFormat,
+    summary: bool,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let param_dict = sorter.param_dict();
+    let mut totals = Summary::default();
+
+    if !summary && format == ReportFormat::Table {
+        writeln!(out, "{:>7} {:4} {:9} {:>3} {:>3} {:>3} {:>3} {:>5} {:>4} {:>3} {:>10} {:>10} {:>10}  PARAM / ERROR",
+                 "FRAME", "CRC", "ROUTE", "BM", "MCU", "DEV", "SRC", "PRM", "TYPE", "ALM",
+                 "VALUE", "MIN", "MAX")?;
+    }
+
+    for (index, package) in split_frames(buffer).enumerate() {
+        let frame_no = index + 1;
+        let frame = sorter.inspect_package(package);
+        if !filter.matches_frame(&frame) {
+            continue;
+        }
+        let records: Vec<&PackageStruct> = frame.records.iter()
+            .filter(|record| filter.matches_record(record))
+            .collect();
+        if records.is_empty() && (filter.has_record_filters() || !frame.records.is_empty()) {
+            continue;
+        }
+
+        if summary {
+            totals.add(frame_no, &frame, &records);
+            continue;
+        }
+
+        for row in FrameRecord::from_frame(frame_no, None, &frame, &records, &param_dict) {
+            match format {
+                ReportFormat::Json => writeln!(out, "{}", serde_json::to_string(&row).map_err(io::Error::other)?)?,
+                ReportFormat::Table => row.write_table(out)?,
+            }
+        }
+    }
+
+    if summary {
+        totals.finish();
+        match format {
+            ReportFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(&totals).map_err(io::Error::other)?)?,
+            ReportFormat::Table => totals.write_table(out)?,
+        }
+    }
+    out.flush()
+}
+
+/// Делит поток дампа на кадры по разделителю 0xC0 (как DumpReader)
+pub fn split_frames(buffer: &[u8]) -> impl Iterator<Item = &[u8]> {
+    buffer.split(|&byte| byte == 0xC0).filter(|frame| !frame.is_empty())
+}
+
+impl FrameRecord {
+    /// Печатает строку таблицы
+    fn write_table(&self, out: &mut impl Write) -> io::Result<()> {
+        let number = |value: Option<u16>| value.map_or(String::from("-"), |value| value.to_string());
+        let float = |value: Option<f32>| value.map_or(String::from("-"), |value| format!("{:.3}", value));
+        let note = self.error.clone()
+            .or_else(|| match (&self.param, &self.text) {
+                (Some(param), Some(text)) => Some(format!("{} = {}", param, text)),
+                (Some(param), None) => Some(param.to_string()),
+                (None, Some(text)) => Some(text.to_string()),
+                (None, None) => None,
+            })
+            .unwrap_or_default();
+        writeln!(out, "{:>7} {:4} {:9} {:>3} {:>3} {:>3} {:>3} {:>5} {:>4} {:>3} {:>10} {:>10} {:>10}  {}",
+                 self.frame,
+                 self.crc,
+                 self.route.as_deref().unwrap_or("-"),
+                 number(self.bm.map(u16::from)),
+                 number(self.mcu.map(u16::from)),
+                 number(self.dev_id.map(u16::from)),
+                 number(self.src_id.map(u16::from)),
+                 number(self.prm_id),
+                 number(self.prm_type.map(u16::from)),
+                 number(self.alarms.map(u16::from)),
+                 float(self.value),
+                 float(self.value_min),
+                 float(self.value_max),
+                 note)
+    }
+}
+
+impl Summary {
+    /// Учитывает кадр и его записи, подошедшие под фильтры
+    fn add(&mut self, frame_no: usize, frame: &InspectedFrame, records: &[&PackageStruct]) {
+        self.frames += 1;
+        if !frame.crc_ok {
+            self.crc_fail += 1;
+            match self.crc_clusters.last_mut() {
+                Some(cluster) if frame_no - cluster.last_frame <= CLUSTER_MAX_DISTANCE => {
+                    cluster.last_frame = frame_no;
+                    cluster.failures += 1;
+                }
+                _ => self.crc_clusters.push(CrcCluster { first_frame: frame_no, last_frame: frame_no, failures: 1 }),
+            }
+            return;
+        }
+
+        self.crc_ok += 1;
+        if frame.error.is_some() {
+            self.parse_errors += 1;
+        }
+        if let Some(pack_type) = frame.route {
+            *self.routes.entry(route_name(pack_type)).or_insert(0) += 1;
+        }
+
+        let mut devices: Vec<DeviceKey> = Vec::new();
+        for record in records {
+            let device = (record.module_addr_bm, record.module_addr_mcu, record.dev_id);
+            if !devices.contains(&device) {
+                devices.push(device);
+            }
+            let param = self.param_keys.entry((device, record.prm_id)).or_default();
+            param.frames += 1;
+            param.positions.push(frame_no);
+            if record.value_text.is_none() && record.value.is_finite() {
+                param.value_min = Some(param.value_min.map_or(record.value, |min| min.min(record.value)));
+                param.value_max = Some(param.value_max.map_or(record.value, |max| max.max(record.value)));
+            }
+        }
+        for device in devices {
+            *self.device_keys.entry(device).or_insert(0) += 1;
+        }
+    }
+
+    /// Вычисляет интервалы и формирует имена устройств и параметров
+    fn finish(&mut self) {
+        for ((device, prm_id), mut param) in std::mem::take(&mut self.param_keys) {
+            let mut gaps: Vec<(usize, usize)> = param.positions.windows(2)
+                .map(|pair| (pair[1] - pair[0], pair[1]))
+                .collect();
+            if let Some(&(max_gap, at)) = gaps.iter().max_by_key(|(gap, at)| (*gap, std::cmp::Reverse(*at))) {
+                param.max_gap = Some(max_gap);
+                param.max_gap_at = Some(at);
+            }
+            gaps.sort_unstable();
+            param.median_gap = gaps.get(gaps.len() / 2).map(|(gap, _)| *gap);
+            self.params.insert(param_name(device, prm_id), param);
+        }
+        for (device, frames) in std::mem::take(&mut self.device_keys) {
+            self.devices.insert(device_name(device), frames);
+        }
+    }
+
+    /// Печатает сводку таблицами
+    fn write_table(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "Frames: {}", self.frames)?;
+        writeln!(out, "CRC correct: {}", self.crc_ok)?;
+        writeln!(out, "CRC incorrect: {}", self.crc_fail)?;
+        writeln!(out, "Parse errors: {}", self.parse_errors)?;
+        for (route, count) in &self.routes {
+            writeln!(out, "Route {}: {}", route, count)?;
+        }
+
+        writeln!(out, "\nDEVICES:")?;
+        writeln!(out, "  {:16} {:>8}", "Device", "Frames")?;
+        for (device, frames) in &self.devices {
+            writeln!(out, "  {:16} {:>8}", device, frames)?;
+        }
+
+        writeln!(out, "\nPARAMETERS (gaps in frames, dump has no timestamps):")?;
+        writeln!(out, "  {:24} {:>8} {:>12} {:>12} {:>7} {:>7}",
+                 "Parameter", "Frames", "Min", "Max", "Median", "MaxGap")?;
+        for (name, param) in &self.params {
+            let float = |value: Option<f32>| value.map_or(String::from("-"), |value| format!("{:.3}", value));
+            let gap = |value: Option<usize>| value.map_or(String::from("-"), |value| value.to_string());
+            let anomaly = match (param.median_gap, param.max_gap) {
+                (Some(median), Some(max)) if max > median.max(1) * GAP_FACTOR => {
+                    format!("  ! gap ends at frame {}", param.max_gap_at.unwrap_or_default())
+                }
+                _ => String::new(),
+            };
+            writeln!(out, "  {:24} {:>8} {:>12} {:>12} {:>7} {:>7}{}",
+                     name, param.frames, float(param.value_min), float(param.value_max),
+                     gap(param.median_gap), gap(param.max_gap), anomaly)?;
+        }
+
+        writeln!(out, "\nCRC ERROR CLUSTERS:")?;
+        if self.crc_clusters.is_empty() {
+            writeln!(out, "  none")?;
+        }
+        for cluster in &self.crc_clusters {
+            writeln!(out, "  frames {}-{}: {} failures", cluster.first_frame, cluster.last_frame, cluster.failures)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::CrcCheck;
+    use crate::test_frames::{self, addr, src};
+
+    /// Кадр стандартной раскладки (module_id 2, MCU 1, src_id 2) с разделителем
+    fn frame(bm: u16, package_type: u16, dev_id: u16, prm_id: u16, prm: u16, corrupt: bool) -> Vec<u8> {
+        let payload = test_frames::words(&[prm, prm + 0x10, prm - 0x10]);
+        let mut package = test_frames::package(CrcCheck::default(), [addr(2, bm, 1), package_type, src(2, dev_id), prm_id], &payload);
+        if corrupt {
+            let last = package.len() - 1;
+            package[last] ^= 0x01;
+        }
+        test_frames::framed(package)
+    }
+
+    /// Температура BM3 (TMonitor): 0xE820 = 4.0, 0xE830 = 6.0 в Linear11
+    fn temperature(dev_id: u16, prm: u16) -> Vec<u8> {
+        frame(3, 0x8000, dev_id, 10, prm, false)
+    }
+
+    /// Системный пакет BM4 (SMonitor)
+    fn system() -> Vec<u8> {
+        frame(4, 0x8000, 1, 20, 0xE820, false)
+    }
+
+    /// Дамп: ошибки CRC в кадрах 4-5 и 9, ошибка разбора в кадре 7,
+    /// параметр BM3.MCU1.DEV1.prm10 в кадрах 1, 6, 8, 10 и 20
+    fn dump() -> Vec<u8> {
+        let corrupted = frame(5, 0x8000, 1, 10, 0xE820, true);
+        let mut frames = vec![
+            temperature(1, 0xE820),
+            temperature(2, 0xE820),
+            system(),
+            corrupted.clone(),
+            corrupted.clone(),
+            temperature(1, 0xE830),
+            frame(3, 0x1234, 1, 10, 0xE820, false),
+            temperature(1, 0xE820),
+            corrupted,
+            temperature(1, 0xE820),
+        ];
+        frames.extend(std::iter::repeat_n(system(), 9));
+        frames.push(temperature(1, 0xE820));
+        frames.concat()
+    }
+
+    fn filter() -> InspectFilter {
+        InspectFilter { bm: None, dev_id: None, prm_id: None, route: None, crc: CrcFilter::All }
+    }
+
+    /// Номера кадров в JSON-строках отчета
+    fn frames(filter: &InspectFilter) -> Vec<u64> {
+        let mut out = Vec::new();
+        report(&dump(), PSorter::new(), filter, ReportFormat::Json, false, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["frame"].as_u64().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn filters() {
+        assert_eq!(frames(&filter()).len(), 20);
+        assert_eq!(frames(&InspectFilter { bm: Some(5), ..filter() }), [4, 5, 9]);
+        assert_eq!(frames(&InspectFilter { dev_id: Some(2), ..filter() }), [2]);
+        assert_eq!(frames(&InspectFilter { prm_id: Some(20), ..filter() }), [3, 11, 12, 13, 14, 15, 16, 17, 18, 19]);
+        assert_eq!(frames(&InspectFilter { route: Some(String::from("tmonitor")), ..filter() }), [1, 2, 6, 8, 10, 20]);
+        // Кадр 7 с корректным CRC, не прошедший разбор, идет в DMonitor, как в конвейере
+        assert_eq!(frames(&InspectFilter { route: Some(String::from("DMonitor")), ..filter() }), [7]);
+        assert_eq!(frames(&InspectFilter { crc: CrcFilter::Fail, ..filter() }), [4, 5, 9]);
+        // Кадр 7, не прошедший разбор, выводится только без фильтров заголовка
+        assert_eq!(frames(&InspectFilter { crc: CrcFilter::Ok, ..filter() }).len(), 17);
+        assert_eq!(frames(&InspectFilter { crc: CrcFilter::Ok, bm: Some(3), ..filter() }), [1, 2, 6, 8, 10, 20]);
+        assert_eq!(frames(&InspectFilter { dev_id: Some(1), prm_id: Some(10), crc: CrcFilter::Ok, ..filter() }),
+                   [1, 6, 8, 10, 20]);
+    }
+
+    #[test]
+    fn summary() {
+        let mut out = Vec::new();
+        report(&dump(), PSorter::new(), &filter(), ReportFormat::Json, true, &mut out).unwrap();
+        let summary: serde_json::Value = serde_json::from_slice(&out).unwrap();
+
+        assert_eq!(summary["frames"], 20);
+        assert_eq!(summary["crc_ok"], 17);
+        assert_eq!(summary["crc_fail"], 3);
+        assert_eq!(summary["parse_errors"], 1);
+        // Кадр, не прошедший разбор, учитывается в маршруте DMonitor
+        assert_eq!(summary["routes"], serde_json::json!({ "DMonitor": 1, "SMonitor": 10, "TMonitor": 6 }));
+        assert_eq!(summary["devices"]["BM3.MCU1.DEV1"], 5);
+        assert_eq!(summary["devices"]["BM4.MCU1.DEV1"], 10);
+
+        // Интервалы 5, 2, 2, 10 кадров: медиана 5, наибольший заканчивается кадром 20
+        let param = &summary["params"]["BM3.MCU1.DEV1.prm10"];
+        assert_eq!(param["frames"], 5);
+        assert_eq!(param["median_gap"], 5);
+        assert_eq!(param["max_gap"], 10);
+        assert_eq!(param["max_gap_at"], 20);
+        assert_eq!(param["value_min"], 4.0);
+        assert_eq!(param["value_max"], 6.0);
+
+        // Кадры 4-5 в одном кластере, кадр 9 дальше CLUSTER_MAX_DISTANCE - отдельный кластер
+        assert_eq!(summary["crc_clusters"], serde_json::json!([
+            { "first_frame": 4, "last_frame": 5, "failures": 2 },
+            { "first_frame": 9, "last_frame": 9, "failures": 1 },
+        ]));
+    }
+
+    #[test]
+    fn summary_table_marks_gap() {
+        // Интервалы 1, 1, 1, 10: наибольший больше медианы в GAP_FACTOR раз
+        let mut dump: Vec<u8> = (0..4).flat_map(|_| temperature(1, 0xE820)).collect();
+        dump.extend((0..9).flat_map(|_| system()));
+        dump.extend(temperature(1, 0xE820));
+        let mut out = Vec::new();
+        report(&dump, PSorter::new(), &filter(), ReportFormat::Table, true, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        assert!(text.contains("CRC incorrect: 0"));
+        let line = text.lines().find(|line| line.contains("BM3.MCU1.DEV1.prm10")).unwrap();
+        assert!(line.ends_with("! gap ends at frame 14"), "{}", line);
+        assert!(text.contains("CRC ERROR CLUSTERS:\n  none"));
+    }
+}
@@ -0,0 +1,140 @@
+use anyhow::{Context, Result};
+use std::fs::OpenOptions;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::EnvFilter;
+
+/// Переменная окружения с фильтром логов (синтаксис tracing EnvFilter),
+/// например `HWMON_LOG=info,hwmon::psorter=debug`
+pub const LOG_ENV_VAR: &str = "HWMON_LOG";
+
+/// Уровень логирования по умолчанию, если фильтр не задан
+const DEFAULT_FILTER: &str = "info";
+
+/// Формат вывода логов
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    /// Человекочитаемый текст
+    Text,
+    /// JSON-строки для сборщика логов
+    Json,
+}
+
+impl LogFormat {
+    /// Разбирает формат из аргумента командной строки
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(anyhow::anyhow!("Unknown log format: {} (expected text/json)", other)),
+        }
+    }
+}
+
+/// Инициализирует глобальный подписчик tracing с выводом в stdout
+/// Фильтр из командной строки имеет приоритет над переменной окружения HWMON_LOG
+pub fn init(filter: Option<&str>, format: LogFormat) -> Result<()> {
+    init_with_writer(filter, format, BoxMakeWriter::new(std::io::stdout), true)
+}
+
+/// Инициализирует логирование с дозаписью в файл; без файла логи отбрасываются
+/// (терминальный интерфейс занимает экран, вывод в stdout его бы портил)
+pub fn init_to_file(filter: Option<&str>, format: LogFormat, path: Option<&str>) -> Result<()> {
+    let writer = match path {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .context(format!("Failed to open log file: {}", path))?;
+            BoxMakeWriter::new(Arc::new(file))
+        }
+        None => BoxMakeWriter::new(std::io::sink),
+    };
+    init_with_writer(filter, format, writer, false)
+}
+
+/// Инициализирует глобальный подписчик tracing с заданным выводом
+fn init_with_writer(filter: Option<&str>, format: LogFormat, writer: BoxMakeWriter, ansi: bool) -> Result<()> {
+    let env_filter = match filter {
+        Some(directives) => EnvFilter::try_new(directives)
+            .context(format!("Invalid log filter: {}", directives))?,
+        None => EnvFilter::try_from_env(LOG_ENV_VAR)
+            .unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER)),
+    };
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(env_filter)
+        .with_writer(writer)
+        .with_ansi(ansi);
+    let result = match format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder.json().flatten_event(true).try_init(),
+    };
+
+    result.map_err(|e| anyhow::anyhow!("Failed to initialize logging: {}", e))
+}
+
+/// Ограничитель частоты для повторяющихся предупреждений (например, ошибок CRC)
+/// Пропускает не более одного сообщения за интервал и считает подавленные
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,        // Минимальный интервал между сообщениями
+    last: Option<Instant>,     // Время последнего пропущенного сообщения
+    suppressed: u64,           // Количество подавленных с тех пор сообщений
+}
+
+impl RateLimiter {
+    /// Создает ограничитель с указанным интервалом
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: None,
+            suppressed: 0,
+        }
+    }
+
+    /// Проверяет, можно ли выводить сообщение сейчас
+    /// Возвращает количество подавленных сообщений с прошлого вывода,
+    /// или `None`, если сообщение нужно подавить
+    pub fn check(&mut self) -> Option<u64> {
+        let now = Instant::now();
+        match self.last {
+            Some(last) if now.duration_since(last) < self.interval => {
+                self.suppressed += 1;
+                None
+            }
+            _ => {
+                self.last = Some(now);
+                Some(std::mem::take(&mut self.suppressed))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limiter_counts_suppressed() {
+        let mut limiter = RateLimiter::new(Duration::from_millis(50));
+        assert_eq!(limiter.check(), Some(0));
+        for _ in 0..3 {
+            assert_eq!(limiter.check(), None);
+        }
+
+        // После интервала сообщение проходит с числом подавленных, счетчик сбрасывается
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(limiter.check(), Some(3));
+        assert_eq!(limiter.check(), None);
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(limiter.check(), Some(1));
+
+        // Нулевой интервал ничего не подавляет
+        let mut limiter = RateLimiter::new(Duration::ZERO);
+        assert_eq!(limiter.check(), Some(0));
+        assert_eq!(limiter.check(), Some(0));
+    }
+}
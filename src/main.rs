@@ -1,26 +1,70 @@
 use anyhow::{Context, Result};
 use clap::{Arg, Command};
 use std::process;
-use tokio;
+use std::time::Duration;
+use tracing::{error, info};
 
 // Модули приложения
+mod alarm_engine;
 mod channels;
+mod config;
 mod controller;
+mod convert;
+mod device_stats;
+mod diff;
 mod dump_reader;
+mod events;
+mod http_api;
+mod http_server;
+mod hw_alarms;
+mod influx_export;
+mod inspect;
+mod logging;
+mod metrics;
+mod mqtt_sink;
+mod package_parser;
+mod package_types;
+mod param_dict;
 mod preader;
+mod protocol;
 mod psorter;
 mod pwriter;
+mod quarantine;
+mod record;
+mod recorder;
+mod state_store;
+mod stats_reporter;
+mod tui;
 mod zmq_sender;
 mod uart;
+mod watchdog;
+mod ws_stream;
 
 // Вспомогательные модули для обработки данных
 mod include {
     pub mod byte_stuffing;
     pub mod crc;
+    #[cfg_attr(not(test), allow(dead_code))] // Кодировщик Linear11 нужен симулятору и тестам
     pub mod linear11;
+    pub mod pmbus;
 }
 
+// Кодировщик кадров симулятора (проверка совместимости с разборщиком)
+#[cfg(test)]
+#[path = "bin/hwmon-sim/frame.rs"]
+mod sim_frame;
+
+// Сборка пакетов и кадров для модульных тестов
+#[cfg(test)]
+mod test_frames;
+
+use config::Config;
 use controller::Controller;
+use convert::{CaptureFormat, ConvertOptions};
+use inspect::{CrcFilter, InspectFilter, ReportFormat};
+use logging::LogFormat;
+use psorter::PSorter;
+use stats_reporter::{StatsOutput, StatsReporter};
 
 /// Главная функция приложения HWMon
 /// Управляет работой монитора оборудования через различные интерфейсы
@@ -33,21 +77,301 @@ async fn main() -> Result<()> {
             Arg::new("operation")
                 .required(true)
                 .index(1)
-                .help("Operation mode: DUMP <filename> / UART / CAN")
+                .help("Operation mode: DUMP <filename> / UART / CAN / TUI [filename] / INSPECT <filename> / CONVERT <filename> / DIFF <a> <b>")
         )
         .arg(
             Arg::new("filename")
                 .required(false)
                 .index(2)
-                .help("Dump filename for DUMP mode")
+                .help("Dump filename for DUMP and INSPECT modes (TUI replays it instead of reading UART), input capture for CONVERT, first capture for DIFF")
+        )
+        .arg(
+            Arg::new("second")
+                .required(false)
+                .index(3)
+                .help("Second capture for DIFF mode")
+        )
+        .arg(
+            Arg::new("port")
+                .long("port")
+                .value_name("PATH")
+                .default_value(uart::DEFAULT_PORT)
+                .help("UART and TUI: serial port to read, e.g. the pty printed by hwmon-sim PTY")
+        )
+        .arg(
+            Arg::new("log-level")
+                .long("log-level")
+                .value_name("FILTER")
+                .help("Log filter, e.g. info or warn,hwmon::psorter=debug (overrides HWMON_LOG)")
+        )
+        .arg(
+            Arg::new("log-format")
+                .long("log-format")
+                .value_name("FORMAT")
+                .default_value("text")
+                .help("Log output format: text / json")
+        )
+        .arg(
+            Arg::new("log-file")
+                .long("log-file")
+                .value_name("FILE")
+                .help("Append logs to FILE instead of stdout (TUI discards logs without it)")
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .value_name("FILE")
+                .help("TOML configuration file (watchdog intervals, alarm thresholds, ...)")
+        )
+        .arg(
+            Arg::new("http-addr")
+                .long("http-addr")
+                .value_name("ADDR")
+                .help("Local HTTP/JSON API address (status, stats, devices, state, alarms, config, commands, recording), e.g. 127.0.0.1:9898")
+        )
+        .arg(
+            Arg::new("ws-addr")
+                .long("ws-addr")
+                .value_name("ADDR")
+                .help("WebSocket address streaming decoded packages and alarm events as JSON, e.g. 127.0.0.1:9899")
+        )
+        .arg(
+            Arg::new("ws-max-rate")
+                .long("ws-max-rate")
+                .value_name("MSGS")
+                .default_value("200")
+                .value_parser(clap::value_parser!(u32).range(1..))
+                .help("Maximum WebSocket messages per second per client (excess is dropped and reported)")
+        )
+        .arg(
+            Arg::new("stats-interval")
+                .long("stats-interval")
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .help("Print periodic statistics (deltas and rates) every N seconds")
+        )
+        .arg(
+            Arg::new("stats-output")
+                .long("stats-output")
+                .value_name("OUTPUT")
+                .default_value("log")
+                .help("Periodic statistics output: log / stdout")
+        )
+        .arg(
+            Arg::new("stats-json")
+                .long("stats-json")
+                .value_name("FILE")
+                .help("Append each periodic statistics snapshot as a JSON line to FILE")
+        )
+        .arg(
+            Arg::new("bm")
+                .long("bm")
+                .value_name("BM")
+                .value_parser(clap::value_parser!(u8))
+                .help("INSPECT: only frames of this BM module")
+        )
+        .arg(
+            Arg::new("dev-id")
+                .long("dev-id")
+                .value_name("ID")
+                .value_parser(clap::value_parser!(u8))
+                .help("INSPECT: only frames of this device")
+        )
+        .arg(
+            Arg::new("prm-id")
+                .long("prm-id")
+                .value_name("ID")
+                .value_parser(clap::value_parser!(u16))
+                .help("INSPECT: only this parameter")
+        )
+        .arg(
+            Arg::new("route")
+                .long("route")
+                .value_name("ROUTE")
+                .help("INSPECT: only frames routed to TMonitor / SMonitor / PUMonitor / ...")
+        )
+        .arg(
+            Arg::new("crc")
+                .long("crc")
+                .value_name("STATUS")
+                .default_value("all")
+                .help("INSPECT: CRC status filter: all / ok / fail")
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .default_value("table")
+                .help("INSPECT and DIFF output format: table / json")
+        )
+        .arg(
+            Arg::new("summary")
+                .long("summary")
+                .action(clap::ArgAction::SetTrue)
+                .help("INSPECT: print per-device summary, value ranges, CRC error clusters and gaps")
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .value_name("FILE")
+                .help("CONVERT: output capture file")
+        )
+        .arg(
+            Arg::new("from")
+                .long("from")
+                .value_name("FORMAT")
+                .help("CONVERT and DIFF: input format raw / capture / pcapng / hex / jsonl / csv (default: by extension)")
+        )
+        .arg(
+            Arg::new("to")
+                .long("to")
+                .value_name("FORMAT")
+                .help("CONVERT: output format raw / capture / pcapng / hex / jsonl / csv (default: by extension)")
+        )
+        .arg(
+            Arg::new("start")
+                .long("start")
+                .value_name("RFC3339")
+                .help("CONVERT: timestamp of the first frame without one (default: now)")
+        )
+        .arg(
+            Arg::new("interval-ms")
+                .long("interval-ms")
+                .value_name("MS")
+                .default_value("1")
+                .value_parser(clap::value_parser!(u64))
+                .help("CONVERT: interval between frames without timestamps")
         )
         .get_matches();
 
     let operation = matches.get_one::<String>("operation")
         .context("Operation argument is required")?;
 
+    // Инициализация логирования
+    let log_format = LogFormat::parse(
+        matches.get_one::<String>("log-format").map(String::as_str).unwrap_or("text"),
+    )?;
+    // INSPECT и DIFF печатают результат в stdout, поэтому по умолчанию логируют только предупреждения
+    let report_mode = matches!(operation.as_str(), "INSPECT" | "DIFF");
+    let log_filter = matches.get_one::<String>("log-level").map(String::as_str)
+        .or((report_mode && std::env::var(logging::LOG_ENV_VAR).is_err()).then_some("warn"));
+    // Терминальный интерфейс занимает экран, поэтому логи пишутся только в файл
+    let log_file = matches.get_one::<String>("log-file").map(String::as_str);
+    if log_file.is_some() || operation == "TUI" {
+        logging::init_to_file(log_filter, log_format, log_file)?;
+    } else {
+        logging::init(log_filter, log_format)?;
+    }
+
+    let config = match matches.get_one::<String>("config") {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+
+    if operation == "INSPECT" {
+        // Разбор дампа без контроллера: ZMQ-сокеты и HTTP-сервер не создаются
+        let filename = matches.get_one::<String>("filename")
+            .context("Filename required for INSPECT mode")?;
+        let filter = InspectFilter {
+            bm: matches.get_one::<u8>("bm").copied(),
+            dev_id: matches.get_one::<u8>("dev-id").copied(),
+            prm_id: matches.get_one::<u16>("prm-id").copied(),
+            route: matches.get_one::<String>("route").cloned(),
+            crc: CrcFilter::parse(matches.get_one::<String>("crc").map(String::as_str).unwrap_or("all"))?,
+        };
+        let format = ReportFormat::parse(
+            matches.get_one::<String>("format").map(String::as_str).unwrap_or("table"),
+        )?;
+
+        let mut sorter = PSorter::new();
+        sorter.set_source("dump");
+        Controller::configure_decoding(&mut sorter, &config, "dump")?;
+        return inspect::run(filename, sorter, &filter, format, matches.get_flag("summary"));
+    }
+
+    if operation == "DIFF" {
+        // Сравнение двух захватов без контроллера
+        let first = matches.get_one::<String>("filename")
+            .context("Two captures required for DIFF mode")?;
+        let second = matches.get_one::<String>("second")
+            .context("Two captures required for DIFF mode")?;
+        let capture_format = |path: &str| match matches.get_one::<String>("from") {
+            Some(name) => CaptureFormat::parse(name),
+            None => Ok(CaptureFormat::from_path(path)),
+        };
+        let format = ReportFormat::parse(
+            matches.get_one::<String>("format").map(String::as_str).unwrap_or("table"),
+        )?;
+
+        // У каждого захвата свой сортировщик: автоопределение профиля и кэш VOUT_MODE не смешиваются
+        let sorter = || -> Result<PSorter> {
+            let mut sorter = PSorter::new();
+            sorter.set_source("dump");
+            Controller::configure_decoding(&mut sorter, &config, "dump")?;
+            Ok(sorter)
+        };
+        return diff::run(
+            (first, capture_format(first)?),
+            (second, capture_format(second)?),
+            (sorter()?, sorter()?),
+            format,
+        );
+    }
+
+    if operation == "CONVERT" {
+        // Преобразование захвата без контроллера
+        let input = matches.get_one::<String>("filename")
+            .context("Input filename required for CONVERT mode")?;
+        let output = matches.get_one::<String>("output")
+            .context("--output required for CONVERT mode")?;
+        let format = |arg: &str, path: &str| match matches.get_one::<String>(arg) {
+            Some(name) => CaptureFormat::parse(name),
+            None => Ok(CaptureFormat::from_path(path)),
+        };
+        let start = match matches.get_one::<String>("start") {
+            Some(start) => chrono::DateTime::parse_from_rfc3339(start)
+                .context("Invalid --start timestamp")?
+                .with_timezone(&chrono::Utc),
+            None => chrono::Utc::now(),
+        };
+        let options = ConvertOptions {
+            from: format("from", input)?,
+            to: format("to", output)?,
+            start,
+            interval: chrono::Duration::milliseconds(
+                matches.get_one::<u64>("interval-ms").copied().unwrap_or(1) as i64,
+            ),
+        };
+
+        let mut sorter = PSorter::new();
+        sorter.set_source("dump");
+        Controller::configure_decoding(&mut sorter, &config, "dump")?;
+        return convert::run(input, output, &options, sorter);
+    }
+
     // Создаем контроллер приложения
+
     let mut controller = Controller::new().await?;
+    controller.set_config(config);
+    if let Some(port) = matches.get_one::<String>("port") {
+        controller.set_uart_port(port.clone());
+    }
+    if let Some(addr) = matches.get_one::<String>("http-addr") {
+        controller.set_http_addr(addr.clone());
+    }
+    if let Some(addr) = matches.get_one::<String>("ws-addr") {
+        controller.set_ws_addr(addr.clone(), matches.get_one::<u32>("ws-max-rate").copied().unwrap_or(200));
+    }
+    if let Some(&seconds) = matches.get_one::<u64>("stats-interval") {
+        let output = StatsOutput::parse(
+            matches.get_one::<String>("stats-output").map(String::as_str).unwrap_or("log"),
+        )?;
+        let reporter = StatsReporter::new(
+            output,
+            matches.get_one::<String>("stats-json").map(String::as_str),
+        )?;
+        controller.set_stats_reporter(Duration::from_secs(seconds), reporter);
+    }
 
     // Обработка различных режимов работы
     match operation.as_str() {
@@ -60,11 +384,12 @@ async fn main() -> Result<()> {
             
             // Запускаем обработку
             controller.start().await?;
-            println!("Processing dump file...");
+            info!("Processing dump file...");
             
             // Ждем завершения обработки дампа
             controller.wait_for_completion().await;
-            println!("Dump processing finished");
+            info!("Dump processing finished");
+            controller.shutdown().await;
             
             // Выводим статистику сразу после завершения
             controller.print_statistics().await;
@@ -74,25 +399,53 @@ async fn main() -> Result<()> {
             controller.set_read_operation(controller::ReadOperation::Uart);
             controller.start().await?;
             
-            println!("UART mode started - reading continuously. Press Ctrl+C to stop");
+            info!("UART mode started - reading continuously. Press Ctrl+C to stop");
             tokio::signal::ctrl_c().await?;
-            println!("Shutting down UART mode...");
+            info!("Shutting down UART mode...");
+            controller.shutdown().await;
             
             // Выводим статистику для UART режима
             controller.print_statistics().await;
         }
+        "TUI" => {
+            // Терминальный интерфейс: повтор дампа, если задан файл, иначе чтение UART
+            let source = match matches.get_one::<String>("filename") {
+                Some(filename) => {
+                    controller.set_read_operation(controller::ReadOperation::Dump);
+                    controller.set_dump_filename(filename.clone());
+                    filename.clone()
+                }
+                None => {
+                    controller.set_read_operation(controller::ReadOperation::Uart);
+                    String::from("UART")
+                }
+            };
+            let (record_sender, record_receiver) = channels::record_channel(channels::RECORD_TAP_CAPACITY);
+            let sorter = controller.sorter();
+            sorter.lock().await.add_record_tap(record_sender);
+            controller.start().await?;
+
+            tokio::task::spawn_blocking(move || {
+                tui::Dashboard::new(&source, sorter, record_receiver).run()
+            })
+            .await
+            .context("Dashboard task failed")??;
+            controller.shutdown().await;
+
+            controller.print_statistics().await;
+        }
         "CAN" => {
             // Режим CAN (пока не реализован)
             controller.set_read_operation(controller::ReadOperation::Can);
-            println!("CAN mode selected - not implemented yet");
+            info!("CAN mode selected - not implemented yet");
             
             // Выводим пустую статистику для CAN режима
             controller.print_statistics().await;
         }
         _ => {
             // Неизвестный режим работы
-            eprintln!("Unknown operation: {}", operation);
-            eprintln!("Use: DUMP <filename> / UART / CAN");
+            error!("Unknown operation: {}", operation);
+            eprintln!("Use: DUMP <filename> / UART / CAN / TUI [filename] / INSPECT <filename> / CONVERT <filename> / DIFF <a> <b>");
             process::exit(1);
         }
     }
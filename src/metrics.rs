@@ -0,0 +1,282 @@
+use lazy_static::lazy_static;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+lazy_static! {
+    /// Глобальный реестр счетчиков конвейера обработки пакетов
+    pub static ref METRICS: Metrics = Metrics::new();
+}
+
+/// Тип метрики в терминах Prometheus
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetricKind {
+    /// Монотонно растущий счетчик
+    Counter,
+    /// Текущее значение (может уменьшаться)
+    Gauge,
+}
+
+impl MetricKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+        }
+    }
+}
+
+/// Семейство метрик с общим именем и набором меток
+pub struct MetricFamily {
+    name: &'static str,                           // Имя метрики
+    help: &'static str,                           // Описание для # HELP
+    kind: MetricKind,                             // Тип метрики
+    label_names: &'static [&'static str],         // Имена меток
+    values: Mutex<BTreeMap<Vec<String>, u64>>,    // Значения по наборам меток
+}
+
+impl MetricFamily {
+    /// Создает пустое семейство метрик
+    pub fn new(
+        name: &'static str,
+        help: &'static str,
+        kind: MetricKind,
+        label_names: &'static [&'static str],
+    ) -> Self {
+        Self {
+            name,
+            help,
+            kind,
+            label_names,
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Увеличивает значение на 1
+    pub fn inc(&self, labels: &[&str]) {
+        self.add(labels, 1);
+    }
+
+    /// Увеличивает значение на `value`
+    pub fn add(&self, labels: &[&str], value: u64) {
+        let mut values = self.lock();
+        *values.entry(Self::key(labels)).or_insert(0) += value;
+    }
+
+    /// Устанавливает значение (для метрик типа gauge)
+    pub fn set(&self, labels: &[&str], value: u64) {
+        self.lock().insert(Self::key(labels), value);
+    }
+
+    /// Записывает семейство в текстовом формате Prometheus
+    fn render(&self, out: &mut String) {
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} {}", self.name, self.kind.as_str());
+
+        for (labels, value) in self.lock().iter() {
+            let pairs: Vec<String> = self.label_names.iter()
+                .zip(labels.iter())
+                .map(|(name, value)| format!("{}=\"{}\"", name, escape_label(value)))
+                .collect();
+
+            if pairs.is_empty() {
+                let _ = writeln!(out, "{} {}", self.name, value);
+            } else {
+                let _ = writeln!(out, "{}{{{}}} {}", self.name, pairs.join(","), value);
+            }
+        }
+    }
+
+    fn key(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|label| label.to_string()).collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<Vec<String>, u64>> {
+        self.values.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Экранирует значение метки по правилам текстового формата Prometheus
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Набор счетчиков конвейера: источники, CRC, маршруты, ZMQ и каналы
+pub struct Metrics {
+    pub source_bytes: MetricFamily,       // Байты, прочитанные из источника
+    pub source_frames: MetricFamily,      // Кадры, выделенные из потока источника
+    pub crc_checks: MetricFamily,         // Результаты проверки CRC
+    pub framing_errors: MetricFamily,     // Ошибки кадрирования и байт-стаффинга
+    pub parse_errors: MetricFamily,       // Ошибки разбора пакетов с корректным CRC
+    pub crc_diagnostics: MetricFamily,    // Подобранные варианты CRC для пакетов с ошибкой CRC
+    pub route_sent: MetricFamily,         // Пакеты, отправленные по маршрутам
+    pub route_failed: MetricFamily,       // Пакеты, которые не удалось отправить
+    pub zmq_send_errors: MetricFamily,    // Ошибки отправки ZeroMQ
+    pub channel_depth: MetricFamily,      // Количество пакетов в очереди канала
+    pub dropped_packages: MetricFamily,   // Пакеты, потерянные до сортировщика
+    pub reconnects: MetricFamily,         // Переподключения интерфейсов
+}
+
+impl Metrics {
+    /// Создает реестр с пустыми счетчиками
+    pub fn new() -> Self {
+        Self {
+            source_bytes: MetricFamily::new(
+                "hwmon_source_bytes_total",
+                "Bytes read from the input source",
+                MetricKind::Counter,
+                &["source"],
+            ),
+            source_frames: MetricFamily::new(
+                "hwmon_source_frames_total",
+                "Frames split from the input source stream",
+                MetricKind::Counter,
+                &["source"],
+            ),
+            crc_checks: MetricFamily::new(
+                "hwmon_crc_checks_total",
+                "CRC check results",
+                MetricKind::Counter,
+                &["source", "result"],
+            ),
+            framing_errors: MetricFamily::new(
+                "hwmon_framing_errors_total",
+                "Framing and byte stuffing errors",
+                MetricKind::Counter,
+                &["source", "kind"],
+            ),
+            parse_errors: MetricFamily::new(
+                "hwmon_parse_errors_total",
+                "Packages with a valid CRC rejected by the parser",
+                MetricKind::Counter,
+                &["source", "kind"],
+            ),
+            crc_diagnostics: MetricFamily::new(
+                "hwmon_crc_diagnostics_total",
+                "CRC variants matching frames that failed the profile CRC check",
+                MetricKind::Counter,
+                &["source", "match"],
+            ),
+            route_sent: MetricFamily::new(
+                "hwmon_route_packages_sent_total",
+                "Packages sent per route",
+                MetricKind::Counter,
+                &["route"],
+            ),
+            route_failed: MetricFamily::new(
+                "hwmon_route_packages_failed_total",
+                "Packages that failed to be sent per route",
+                MetricKind::Counter,
+                &["route"],
+            ),
+            zmq_send_errors: MetricFamily::new(
+                "hwmon_zmq_send_errors_total",
+                "ZeroMQ send errors per endpoint",
+                MetricKind::Counter,
+                &["endpoint"],
+            ),
+            channel_depth: MetricFamily::new(
+                "hwmon_channel_depth",
+                "Packages waiting in the channel",
+                MetricKind::Gauge,
+                &["channel"],
+            ),
+            dropped_packages: MetricFamily::new(
+                "hwmon_dropped_packages_total",
+                "Packages dropped before reaching the sorter",
+                MetricKind::Counter,
+                &["source"],
+            ),
+            reconnects: MetricFamily::new(
+                "hwmon_reconnects_total",
+                "Interface reconnect attempts",
+                MetricKind::Counter,
+                &["component"],
+            ),
+        }
+    }
+
+    /// Формирует все метрики в текстовом формате Prometheus
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for family in [
+            &self.source_bytes,
+            &self.source_frames,
+            &self.crc_checks,
+            &self.framing_errors,
+            &self.parse_errors,
+            &self.crc_diagnostics,
+            &self.route_sent,
+            &self.route_failed,
+            &self.zmq_send_errors,
+            &self.channel_depth,
+            &self.dropped_packages,
+            &self.reconnects,
+        ] {
+            family.render(&mut out);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rendered(family: &MetricFamily) -> String {
+        let mut out = String::new();
+        family.render(&mut out);
+        out
+    }
+
+    #[test]
+    fn family_lines() {
+        // Без меток: имя и значение, set заменяет значение
+        let uptime = MetricFamily::new("hwmon_uptime", "Uptime", MetricKind::Gauge, &[]);
+        uptime.set(&[], 5);
+        uptime.set(&[], 7);
+        assert_eq!(rendered(&uptime), "# HELP hwmon_uptime Uptime\n# TYPE hwmon_uptime gauge\nhwmon_uptime 7\n");
+
+        // С метками: серии в порядке значений меток, add накапливает
+        let frames = MetricFamily::new("hwmon_frames_total", "Frames", MetricKind::Counter, &["source", "result"]);
+        frames.inc(&["uart", "ok"]);
+        frames.add(&["dump", "ok"], 3);
+        frames.inc(&["uart", "ok"]);
+        assert_eq!(rendered(&frames), concat!(
+            "# HELP hwmon_frames_total Frames\n",
+            "# TYPE hwmon_frames_total counter\n",
+            "hwmon_frames_total{source=\"dump\",result=\"ok\"} 3\n",
+            "hwmon_frames_total{source=\"uart\",result=\"ok\"} 2\n",
+        ));
+    }
+
+    #[test]
+    fn label_escaping() {
+        assert_eq!(escape_label(r#"a"b\c"#), r#"a\"b\\c"#);
+        assert_eq!(escape_label("line\nnext"), r"line\nnext");
+        assert_eq!(escape_label(r"\n"), r"\\n");
+
+        let errors = MetricFamily::new("hwmon_errors_total", "Errors", MetricKind::Counter, &["endpoint"]);
+        errors.inc(&["tcp://\"host\"\n"]);
+        assert!(rendered(&errors).ends_with("hwmon_errors_total{endpoint=\"tcp://\\\"host\\\"\\n\"} 1\n"));
+    }
+
+    #[test]
+    fn registry_render() {
+        // Пустые семейства выводят только HELP и TYPE
+        let metrics = Metrics::new();
+        metrics.crc_checks.inc(&["uart", "ok"]);
+        metrics.channel_depth.set(&["sorter"], 4);
+        let text = metrics.render();
+
+        assert_eq!(text.lines().filter(|line| line.starts_with("# HELP ")).count(), 12);
+        assert_eq!(text.lines().filter(|line| line.starts_with("# TYPE ")).count(), 12);
+        assert_eq!(text.lines().filter(|line| !line.starts_with('#')).collect::<Vec<_>>(), [
+            "hwmon_crc_checks_total{source=\"uart\",result=\"ok\"} 1",
+            "hwmon_channel_depth{channel=\"sorter\"} 4",
+        ]);
+        assert!(text.contains("# TYPE hwmon_channel_depth gauge\n"));
+        assert!(text.starts_with("# HELP hwmon_source_bytes_total Bytes read from the input source\n# TYPE hwmon_source_bytes_total counter\n"));
+        assert!(text.ends_with("# TYPE hwmon_reconnects_total counter\n"));
+    }
+}
@@ -0,0 +1,377 @@
+use anyhow::{Context, Result};
+use rumqttc::{AsyncClient, Event, EventLoop, LastWill, MqttOptions, Packet, QoS};
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::config::MqttConfig;
+use crate::logging::RateLimiter;
+use crate::metrics::METRICS;
+use crate::record::FrameRecord;
+use crate::ws_stream::{StreamHub, StreamItem};
+
+/// Имя маршрута MQTT для метрик и логов
+const MQTT_ROUTE: &str = "MQTT";
+
+/// Порт брокера по умолчанию
+const DEFAULT_PORT: u16 = 1883;
+
+/// Интервал предупреждений о потерянных сообщениях и ошибках связи
+const WARN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Сообщения о состоянии hwmon в топике status_topic
+const STATUS_ONLINE: &str = "online";
+const STATUS_OFFLINE: &str = "offline";
+
+/// Поле шаблона топика
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TopicField {
+    Source,
+    Bm,
+    Mcu,
+    Dev,
+    Src,
+    Prm,
+    Param,
+    Route,
+    Event,
+}
+
+/// Часть шаблона топика
+#[derive(Debug, Clone, PartialEq)]
+enum TopicPart {
+    Literal(String),
+    Field(TopicField),
+}
+
+/// Значения полей для подстановки в шаблон
+#[derive(Debug, Default)]
+struct TopicValues {
+    bm: Option<u8>,
+    mcu: Option<u8>,
+    dev: Option<u8>,
+    src: Option<u8>,
+    prm: Option<u16>,
+    param: Option<String>,
+    route: Option<String>,
+    event: Option<String>,
+}
+
+/// Шаблон топика, например "hwmon/{source}/bm{bm}/fpga{dev}/{param}"
+#[derive(Debug, Clone, PartialEq)]
+struct TopicTemplate {
+    parts: Vec<TopicPart>,
+}
+
+impl TopicTemplate {
+    /// Разбирает шаблон; неизвестные поля и незакрытые скобки - ошибка конфигурации
+    fn parse(template: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(TopicPart::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..].find('}')
+                .context(format!("Unclosed '{{' in MQTT topic template: {}", template))?;
+            let field = match &rest[start + 1..start + end] {
+                "source" => TopicField::Source,
+                "bm" => TopicField::Bm,
+                "mcu" => TopicField::Mcu,
+                "dev" => TopicField::Dev,
+                "src" => TopicField::Src,
+                "prm" => TopicField::Prm,
+                "param" => TopicField::Param,
+                "route" => TopicField::Route,
+                "event" => TopicField::Event,
+                other => return Err(anyhow::anyhow!(
+                    "Unknown field {{{}}} in MQTT topic template (expected source/bm/mcu/dev/src/prm/param/route/event)",
+                    other
+                )),
+            };
+            parts.push(TopicPart::Field(field));
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(TopicPart::Literal(rest.to_string()));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Подставляет значения; отсутствующие поля заменяются на "unknown"
+    /// Символы '/', '+' и '#' в значениях заменяются на '_', чтобы не менять уровни топика
+    fn render(&self, source: &str, values: &TopicValues) -> String {
+        let number = |value: Option<u64>| value.map_or(String::from("unknown"), |value| value.to_string());
+        self.parts.iter()
+            .map(|part| match part {
+                TopicPart::Literal(text) => text.clone(),
+                TopicPart::Field(field) => {
+                    let value = match field {
+                        TopicField::Source => source.to_string(),
+                        TopicField::Bm => number(values.bm.map(u64::from)),
+                        TopicField::Mcu => number(values.mcu.map(u64::from)),
+                        TopicField::Dev => number(values.dev.map(u64::from)),
+                        TopicField::Src => number(values.src.map(u64::from)),
+                        TopicField::Prm => number(values.prm.map(u64::from)),
+                        // Без словаря параметр называется по идентификатору
+                        TopicField::Param => values.param.clone()
+                            .unwrap_or_else(|| values.prm.map_or(String::from("unknown"), |prm| format!("prm{}", prm))),
+                        TopicField::Route => values.route.clone().unwrap_or_else(|| String::from("unknown")),
+                        TopicField::Event => values.event.clone().unwrap_or_else(|| String::from("unknown")),
+                    };
+                    value.replace(['/', '+', '#'], "_")
+                }
+            })
+            .collect()
+    }
+}
+
+/// Публикация значений параметров и событий в MQTT
+///
+/// Значения (по записи на параметр пакета с корректным CRC) публикуются в топик по шаблону topic,
+/// события - в events_topic. Топик status_topic получает "online" при каждом подключении
+/// и "offline" как last will при потере связи. На время переподключения сообщения копятся
+/// в очереди клиента (buffer_size); при переполнении новые сообщения отбрасываются
+pub struct MqttSink {
+    source: &'static str,                // Источник пакетов (uart, dump, ...)
+    topic: TopicTemplate,                // Шаблон топика значений
+    events_topic: Option<TopicTemplate>, // Шаблон топика событий
+    status_topic: String,                // Топик состояния hwmon
+    qos: QoS,                            // Качество обслуживания
+    retain: bool,                        // Сохранять значения на брокере
+    reconnect_delay: Duration,           // Пауза перед повторным подключением
+    options: MqttOptions,                // Параметры подключения
+    buffer_size: usize,                  // Емкость очереди клиента
+}
+
+impl MqttSink {
+    /// Создает публикацию по конфигурации; None, если брокер не задан
+    pub fn from_config(config: &MqttConfig, source: &'static str) -> Result<Option<Self>> {
+        let Some(broker) = config.broker.as_deref().filter(|broker| !broker.is_empty()) else {
+            return Ok(None);
+        };
+        let (host, port) = broker_address(broker)?;
+        let qos = match config.qos {
+            0 => QoS::AtMostOnce,
+            1 => QoS::AtLeastOnce,
+            2 => QoS::ExactlyOnce,
+            other => return Err(anyhow::anyhow!("Invalid MQTT QoS: {} (expected 0, 1 or 2)", other)),
+        };
+        if config.buffer_size == 0 {
+            return Err(anyhow::anyhow!("MQTT buffer_size must be positive"));
+        }
+        let status_topic = TopicTemplate::parse(&config.status_topic)?.render(source, &TopicValues::default());
+
+        let mut options = MqttOptions::new(&config.client_id, host, port);
+        options.set_keep_alive(Duration::from_secs(config.keep_alive_secs.max(5)));
+        options.set_last_will(LastWill::new(&status_topic, STATUS_OFFLINE, qos, true));
+        if let Some(username) = &config.username {
+            options.set_credentials(username, config.password.clone().unwrap_or_default());
+        }
+
+        Ok(Some(Self {
+            source,
+            topic: TopicTemplate::parse(&config.topic)?,
+            events_topic: config.events_topic.as_deref()
+                .filter(|topic| !topic.is_empty())
+                .map(TopicTemplate::parse)
+                .transpose()?,
+            status_topic,
+            qos,
+            retain: config.retain,
+            reconnect_delay: Duration::from_secs_f64(config.reconnect_delay_secs.max(0.1)),
+            options,
+            buffer_size: config.buffer_size,
+        }))
+    }
+
+    /// Запускает подключение к брокеру и публикацию сообщений из общей очереди
+    pub fn start(self, hub: StreamHub) -> Vec<JoinHandle<()>> {
+        let (client, event_loop) = AsyncClient::new(self.options.clone(), self.buffer_size);
+        let (host, port) = self.options.broker_address();
+        info!(host, port, status_topic = %self.status_topic, "MQTT publishing enabled");
+
+        let connection = tokio::spawn(Self::run_connection(
+            event_loop,
+            client.clone(),
+            self.status_topic.clone(),
+            self.qos,
+            self.reconnect_delay,
+        ));
+        let publisher = tokio::spawn(self.run_publisher(client, hub));
+        vec![connection, publisher]
+    }
+
+    /// Обслуживает соединение: переподключение и сообщение "online" после каждого подключения
+    async fn run_connection(
+        mut event_loop: EventLoop,
+        client: AsyncClient,
+        status_topic: String,
+        qos: QoS,
+        reconnect_delay: Duration,
+    ) {
+        let mut error_limiter = RateLimiter::new(WARN_INTERVAL);
+        loop {
+            match event_loop.poll().await {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    info!("MQTT broker connected");
+                    if client.try_publish(&status_topic, qos, true, STATUS_ONLINE).is_err() {
+                        warn!("MQTT queue full, status not published");
+                    }
+                }
+                Ok(event) => debug!(?event, "MQTT event"),
+                Err(e) => {
+                    if let Some(suppressed) = error_limiter.check() {
+                        warn!(suppressed, "MQTT connection error: {}", e);
+                    }
+                    tokio::time::sleep(reconnect_delay).await;
+                }
+            }
+        }
+    }
+
+    /// Переносит сообщения из общей очереди в очередь клиента MQTT
+    async fn run_publisher(self, client: AsyncClient, hub: StreamHub) {
+        let mut items = hub.subscribe();
+        let mut drop_limiter = RateLimiter::new(WARN_INTERVAL);
+        loop {
+            let published = match items.recv().await {
+                Ok(item) => match item.as_ref() {
+                    StreamItem::Package(record) => self.publish_value(&client, record),
+                    StreamItem::Event(event) => self.publish_event(&client, event),
+                },
+                Err(RecvError::Lagged(count)) => {
+                    METRICS.route_failed.add(&[MQTT_ROUTE], count);
+                    false
+                }
+                Err(RecvError::Closed) => break,
+            };
+            if !published {
+                if let Some(suppressed) = drop_limiter.check() {
+                    warn!(suppressed, "MQTT messages dropped (broker unavailable or queue full)");
+                }
+            }
+        }
+    }
+
+    /// Публикует значение параметра; записи без значения и с ошибкой CRC пропускаются
+    /// Возвращает false, если сообщение пришлось отбросить
+    fn publish_value(&self, client: &AsyncClient, record: &FrameRecord) -> bool {
+        if record.crc != "ok" || (record.value.is_none() && record.text.is_none()) {
+            return true;
+        }
+        let topic = self.topic.render(self.source, &TopicValues {
+            bm: record.bm,
+            mcu: record.mcu,
+            dev: record.dev_id,
+            src: record.src_id,
+            prm: record.prm_id,
+            param: record.param.clone(),
+            route: record.route.clone(),
+            event: None,
+        });
+        let payload = serde_json::json!({
+            "value": record.value,
+            "min": record.value_min,
+            "max": record.value_max,
+            "text": record.text,
+            "alarms": record.alarms,
+            "prm_id": record.prm_id,
+            "route": record.route,
+            "timestamp": record.timestamp,
+        });
+        self.publish(client, topic, self.retain, payload)
+    }
+
+    /// Публикует событие (без сохранения на брокере)
+    fn publish_event(&self, client: &AsyncClient, event: &serde_json::Value) -> bool {
+        let Some(template) = &self.events_topic else {
+            return true;
+        };
+        let field = |name: &str| event.get(name).and_then(serde_json::Value::as_u64);
+        let topic = template.render(self.source, &TopicValues {
+            bm: field("module_addr_bm").map(|bm| bm as u8),
+            mcu: field("module_addr_mcu").map(|mcu| mcu as u8),
+            dev: field("dev_id").map(|id| id as u8),
+            src: field("src_id").map(|id| id as u8),
+            prm: field("prm_id").map(|id| id as u16),
+            param: None,
+            route: None,
+            event: event.get("event").and_then(serde_json::Value::as_str).map(String::from),
+        });
+        self.publish(client, topic, false, event.clone())
+    }
+
+    fn publish(&self, client: &AsyncClient, topic: String, retain: bool, payload: serde_json::Value) -> bool {
+        match client.try_publish(topic, self.qos, retain, payload.to_string()) {
+            Ok(()) => {
+                METRICS.route_sent.inc(&[MQTT_ROUTE]);
+                true
+            }
+            Err(_) => {
+                METRICS.route_failed.inc(&[MQTT_ROUTE]);
+                false
+            }
+        }
+    }
+}
+
+/// Разбирает адрес брокера host[:port]; адрес IPv6 с портом задается в скобках ([::1]:1883),
+/// без порта - в скобках или без них (::1)
+fn broker_address(broker: &str) -> Result<(&str, u16)> {
+    let port = |port: &str| port.parse().context(format!("Invalid MQTT broker port: {}", broker));
+    if let Some(rest) = broker.strip_prefix('[') {
+        let (host, rest) = rest.split_once(']').context(format!("Invalid MQTT broker address: {}", broker))?;
+        return match rest {
+            "" => Ok((host, DEFAULT_PORT)),
+            _ => match rest.strip_prefix(':') {
+                Some(value) => Ok((host, port(value)?)),
+                None => Err(anyhow::anyhow!("Invalid MQTT broker address: {}", broker)),
+            },
+        };
+    }
+    match broker.split_once(':') {
+        // Несколько двоеточий без скобок - адрес IPv6 без порта
+        Some((_, rest)) if rest.contains(':') => Ok((broker, DEFAULT_PORT)),
+        Some((host, value)) => Ok((host, port(value)?)),
+        None => Ok((broker, DEFAULT_PORT)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topic_template_render() {
+        let template = TopicTemplate::parse("hwmon/{source}/bm{bm}/mcu{mcu}/dev{dev}/{param}").unwrap();
+        let values = TopicValues { bm: Some(3), mcu: Some(1), dev: Some(2), prm: Some(17), ..Default::default() };
+        assert_eq!(template.render("uart", &values), "hwmon/uart/bm3/mcu1/dev2/prm17");
+
+        let values = TopicValues { param: Some(String::from("temp/fpga#1")), ..Default::default() };
+        assert_eq!(template.render("uart", &values), "hwmon/uart/bmunknown/mcuunknown/devunknown/temp_fpga_1");
+
+        assert!(TopicTemplate::parse("hwmon/{unknown}").is_err());
+        assert!(TopicTemplate::parse("hwmon/{bm").is_err());
+    }
+
+    #[test]
+    fn broker_host_and_port() {
+        let address = |broker: &str| {
+            let config = MqttConfig { broker: Some(String::from(broker)), ..Default::default() };
+            MqttSink::from_config(&config, "uart").map(|sink| sink.unwrap().options.broker_address())
+        };
+        assert_eq!(address("broker.local:1884").unwrap(), (String::from("broker.local"), 1884));
+        assert_eq!(address("broker.local").unwrap(), (String::from("broker.local"), 1883));
+        assert_eq!(address("[::1]:1884").unwrap(), (String::from("::1"), 1884));
+        assert_eq!(address("[::1]").unwrap(), (String::from("::1"), 1883));
+        assert_eq!(address("::1").unwrap(), (String::from("::1"), 1883));
+        assert_eq!(address("fe80::1").unwrap(), (String::from("fe80::1"), 1883));
+
+        assert!(address("broker.local:port").is_err());
+        assert!(address("[::1").is_err());
+        assert!(address("[::1]1883").is_err());
+        assert!(address("[::1]:").is_err());
+    }
+}
@@ -0,0 +1,276 @@
+use std::fmt;
+
+use crate::package_types::{PackageTypeRegistry, Payload};
+use crate::protocol::HeaderLayout;
+use crate::psorter::PackageStruct;
+
+/// Длина заголовка пакета (addr, package_type, src, data_type)
+pub const HEADER_LEN: usize = 8;
+
+/// Ошибка разбора пакета с корректным CRC
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// Пакет короче минимальной длины для своего типа
+    TooShort { len: usize, min: usize },
+    /// Пакет длиннее максимальной длины для своего типа
+    TooLong { len: usize, max: usize },
+    /// Неизвестный тип пакета (нет декодера в реестре)
+    UnknownPackageType { package_type: u16 },
+    /// Содержимое не соответствует формату типа пакета
+    InvalidPayload { reason: String },
+    /// Установлены зарезервированные биты
+    ReservedBits { field: &'static str, bits: u16 },
+}
+
+impl ParseError {
+    /// Вид ошибки для метрик и счетчиков
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ParseError::TooShort { .. } => "too_short",
+            ParseError::TooLong { .. } => "too_long",
+            ParseError::UnknownPackageType { .. } => "unknown_package_type",
+            ParseError::InvalidPayload { .. } => "invalid_payload",
+            ParseError::ReservedBits { .. } => "reserved_bits",
+        }
+    }
+
+    /// Пересчитывает длины содержимого в длины пакета (заголовок и CRC)
+    fn with_overhead(self, overhead: usize) -> Self {
+        match self {
+            ParseError::TooShort { len, min } => ParseError::TooShort { len: len + overhead, min: min + overhead },
+            ParseError::TooLong { len, max } => ParseError::TooLong { len: len + overhead, max: max + overhead },
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::TooShort { len, min } => write!(f, "package too short: {} bytes, expected at least {}", len, min),
+            ParseError::TooLong { len, max } => write!(f, "package too long: {} bytes, expected at most {}", len, max),
+            ParseError::UnknownPackageType { package_type } => {
+                write!(f, "unknown package_type {:#06x}", package_type)
+            }
+            ParseError::ReservedBits { field, bits } => {
+                write!(f, "reserved bits {:#06x} set in {}", bits, field)
+            }
+            ParseError::InvalidPayload { reason } => write!(f, "invalid payload: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Разбирает пакет (после байт-стаффинга, вместе с CRC длиной `crc_len`) в записи параметров
+/// Заголовок разбирается по раскладке профиля протокола и проверяется на длину
+/// и зарезервированные биты, затем содержимое
+/// разбирается декодером, зарегистрированным для package_type. Пакет с одним
+/// значением дает одну запись, блочное чтение и пакет нескольких параметров -
+/// по записи на параметр. Значения параметров не преобразуются - это делает
+/// сортировщик по словарю параметров
+pub fn parse_package(
+    package: &[u8],
+    registry: &PackageTypeRegistry,
+    layout: &HeaderLayout,
+    crc_len: usize,
+) -> Result<Vec<PackageStruct>, ParseError> {
+    let len = package.len();
+    if len < HEADER_LEN + crc_len {
+        return Err(ParseError::TooShort { len, min: HEADER_LEN + crc_len });
+    }
+
+    // Слово little endian по смещению
+    let word = |offset: usize| ((package[offset + 1] as u16) << 8) | (package[offset] as u16);
+
+    // Адрес (байты 0-1)
+    let addr = word(0);
+    let reserved = addr & layout.addr_reserved_mask();
+    if reserved != 0 {
+        return Err(ParseError::ReservedBits { field: "addr", bits: reserved });
+    }
+
+    // Тип пакета (байты 2-3) определяет формат содержимого
+    let package_type = word(2);
+    let decoder = registry.get(package_type)
+        .ok_or(ParseError::UnknownPackageType { package_type })?;
+    let payload = decoder.decode(&package[HEADER_LEN..len - crc_len])
+        .map_err(|e| e.with_overhead(HEADER_LEN + crc_len))?;
+
+    // Источник данных (байты 4-5) и тип данных (байты 6-7)
+    let header = parse_header(layout, addr, package_type, word(4), word(6));
+
+    let records = match payload {
+        Payload::Values { prm, prm_max, prm_min } => vec![PackageStruct { prm, prm_max, prm_min, ..header }],
+        Payload::Block { values } => values.iter()
+            .enumerate()
+            .map(|(index, &prm)| {
+                // Параметры блока идут подряд начиная с prm_id из заголовка
+                let prm_mask = layout.prm_id.mask() >> layout.prm_id.shift;
+                let prm_id = (header.prm_id + index as u16) & prm_mask;
+                PackageStruct {
+                    data_type: (header.data_type & !layout.prm_id.mask()) | (prm_id << layout.prm_id.shift),
+                    prm_id,
+                    prm,
+                    prm_max: prm,
+                    prm_min: prm,
+                    ..header.clone()
+                }
+            })
+            .collect(),
+        Payload::Burst { values } => values.iter()
+            .map(|&(data_type, prm)| {
+                let record = parse_header(layout, addr, package_type, header.src, data_type);
+                PackageStruct { prm, prm_max: prm, prm_min: prm, ..record }
+            })
+            .collect(),
+        Payload::Text { text } => vec![PackageStruct { value_text: Some(text), ..header }],
+    };
+    // Маршрут и статистика определяются по первой записи, пакет без записей некорректен
+    if records.is_empty() {
+        return Err(ParseError::InvalidPayload { reason: String::from("package has no parameter records") });
+    }
+    Ok(records)
+}
+
+/// Разбирает битовые поля заголовка по раскладке
+fn parse_header(layout: &HeaderLayout, addr: u16, package_type: u16, src: u16, data_type: u16) -> PackageStruct {
+    let module_addr = layout.module_addr.extract(addr) as u8;
+    PackageStruct {
+        addr,
+        module_addr,
+        module_addr_mcu: module_addr & ((1u16 << layout.mcu_width) - 1) as u8,
+        module_addr_bm: module_addr >> layout.mcu_width,
+        module_id: layout.module_id.extract(addr) as u8,
+        package_type,
+        src,
+        dev_id: layout.dev_id.extract(src) as u8,
+        pwr_line: layout.pwr_line.extract(src) as u8,
+        src_id: layout.src_id.extract(src) as u8,
+        rtr: layout.rtr.extract(src) != 0,
+        data_type,
+        prm_id: layout.prm_id.extract(data_type),
+        alarms: layout.alarms.extract(data_type) as u8,
+        prm_type: layout.prm_type.extract(data_type) as u8,
+        prm: 0,
+        prm_max: 0,
+        prm_min: 0,
+        value: 0.0,
+        value_max: 0.0,
+        value_min: 0.0,
+        value_text: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::CrcCheck;
+    use crate::test_frames;
+
+    /// Пакет стандартной раскладки: заголовок, содержимое и CRC (без байт-стаффинга)
+    fn package(addr: u16, package_type: u16, src: u16, data_type: u16, payload: &[u8]) -> Vec<u8> {
+        test_frames::package(CrcCheck::default(), [addr, package_type, src, data_type], payload)
+    }
+
+    fn parse(package: &[u8]) -> Result<Vec<PackageStruct>, ParseError> {
+        parse_package(package, &PackageTypeRegistry::default(), &HeaderLayout::default(), CrcCheck::default().size())
+    }
+
+    #[test]
+    fn values_header_fields() {
+        // module_id 2, BM 5, MCU 1; src_id 3, pwr_line 4, dev_id 6; prm_type 1, alarms 0b0101, prm_id 12
+        let addr = (2 << 7) | (5 << 3) | 1;
+        let src = (3 << 11) | (4 << 7) | 6;
+        let data_type = (1 << 14) | (0b0101 << 10) | 12;
+        let records = parse(&package(addr, 0x8000, src, data_type, &[0x01, 0x00, 0x03, 0x00, 0x02, 0x00])).unwrap();
+
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!((record.module_id, record.module_addr_bm, record.module_addr_mcu), (2, 5, 1));
+        assert_eq!(record.module_addr, (5 << 3) | 1);
+        assert_eq!((record.src_id, record.pwr_line, record.dev_id, record.rtr), (3, 4, 6, false));
+        assert_eq!((record.prm_type, record.alarms, record.prm_id), (1, 0b0101, 12));
+        assert_eq!((record.prm, record.prm_max, record.prm_min), (1, 3, 2));
+    }
+
+    #[test]
+    fn two_byte_package_too_short() {
+        // Два байта совпадают с CRC пустого пакета - раньше такой кадр уходил в OMonitor
+        let mut package = Vec::new();
+        CrcCheck::default().append(&mut package);
+        assert_eq!(package.len(), 2);
+        assert!(CrcCheck::default().validate(&package));
+        assert_eq!(parse(&package).unwrap_err(), ParseError::TooShort { len: 2, min: HEADER_LEN + 2 });
+    }
+
+    #[test]
+    fn payload_length_checked_with_overhead() {
+        // Длины в ошибке пересчитываются в длины пакета (заголовок и CRC)
+        let short = package(0x0109, 0x8000, 0x1001, 10, &[0x01, 0x00]);
+        assert_eq!(parse(&short).unwrap_err(), ParseError::TooShort { len: 12, min: 16 });
+
+        let long = package(0x0109, 0x8000, 0x1001, 10, &[0; 8]);
+        assert_eq!(parse(&long).unwrap_err(), ParseError::TooLong { len: 18, max: 16 });
+    }
+
+    #[test]
+    fn unknown_package_type() {
+        let package = package(0x0109, 0x1234, 0x1001, 10, &[0; 6]);
+        assert_eq!(parse(&package).unwrap_err(), ParseError::UnknownPackageType { package_type: 0x1234 });
+    }
+
+    #[test]
+    fn reserved_addr_bits() {
+        // Биты 11-15 addr стандартной раскладки не заняты полями
+        let package = package(0x0909, 0x8000, 0x1001, 10, &[0; 6]);
+        let error = parse(&package).unwrap_err();
+        assert_eq!(error, ParseError::ReservedBits { field: "addr", bits: 0x0800 });
+        assert_eq!(error.kind(), "reserved_bits");
+    }
+
+    #[test]
+    fn simulator_frames_round_trip() {
+        use crate::include::{byte_stuffing, linear11};
+        use crate::sim_frame::{self, FrameAssembler, FrameHeader, FRAME_DELIMITER, MODULE_ID_BM};
+
+        // Младшие байты src (0xC0) и data_type (0xDB) требуют экранирования
+        let header = FrameHeader {
+            bm: 5,
+            mcu: 2,
+            module_id: MODULE_ID_BM,
+            dev_id: 0x40,
+            pwr_line: 1,
+            src_id: 2,
+            rtr: false,
+            prm_id: 0xDB,
+            alarms: 0b0010,
+            prm_type: 1,
+        };
+        let mut stream = sim_frame::encode_values(&header, 42.5, 50.0, -40.25, false).unwrap();
+        stream.extend(sim_frame::encode_values(&header, 42.5, 50.0, -40.25, true).unwrap());
+        assert!(stream.windows(2).any(|pair| pair == [0xDB, 0xDC]));
+        assert!(stream.windows(2).any(|pair| pair == [0xDB, 0xDD]));
+
+        let frames = FrameAssembler::default().push(&stream);
+        assert_eq!(frames.len(), 2);
+        assert!(frames.iter().all(|frame| !frame.contains(&FRAME_DELIMITER)));
+        assert_eq!(sim_frame::decode_request(&frames[0]), Some(header));
+        assert_eq!(sim_frame::decode_request(&frames[1]), None);
+
+        let crc = CrcCheck::default();
+        let destuffed = byte_stuffing::byte_stuffing(&frames[0]);
+        assert_eq!(destuffed.len(), HEADER_LEN + 6 + crc.size());
+        assert!(crc.validate(&destuffed));
+        assert!(!crc.validate(&byte_stuffing::byte_stuffing(&frames[1])));
+
+        let records = parse(&destuffed).unwrap();
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!((record.module_id, record.module_addr_bm, record.module_addr_mcu), (MODULE_ID_BM, 5, 2));
+        assert_eq!((record.dev_id, record.pwr_line, record.src_id), (0x40, 1, 2));
+        assert_eq!((record.prm_id, record.alarms, record.prm_type), (0xDB, 0b0010, 1));
+        let values = [record.prm, record.prm_max, record.prm_min].map(linear11::from_linear11_f);
+        assert_eq!(values, [42.5, 50.0, -40.25]);
+    }
+}
@@ -0,0 +1,267 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::config::{PackageTypeConfig, PayloadLayout};
+use crate::package_parser::ParseError;
+
+/// Тип пакета с одним значением параметра (prm, prm_max, prm_min)
+pub const PACKAGE_TYPE_VALUES: u16 = 0x8000;
+
+/// Содержимое пакета после заголовка
+#[derive(Debug, Clone, PartialEq)]
+pub enum Payload {
+    /// Значение параметра из заголовка с минимумом и максимумом
+    Values { prm: u16, prm_max: u16, prm_min: u16 },
+    /// Блочное чтение: значения параметров prm_id, prm_id + 1, ...
+    Block { values: Vec<u16> },
+    /// Пакет нескольких параметров: пары (data_type, значение)
+    Burst { values: Vec<(u16, u16)> },
+    /// Строковый дескриптор (версия прошивки, серийный номер, ...)
+    Text { text: String },
+}
+
+/// Декодер содержимого пакета определенного типа
+pub trait PayloadDecoder: Send + Sync {
+    /// Имя декодера для логов
+    fn name(&self) -> &str;
+
+    /// Разбирает содержимое пакета (байты между заголовком и CRC)
+    fn decode(&self, payload: &[u8]) -> Result<Payload, ParseError>;
+}
+
+/// Проверяет длину содержимого; ошибки содержат длину содержимого,
+/// в длину пакета ее пересчитывает разборщик (длина CRC зависит от профиля)
+fn check_len(payload: &[u8], min: usize, max: usize) -> Result<(), ParseError> {
+    let len = payload.len();
+    if len < min {
+        return Err(ParseError::TooShort { len, min });
+    }
+    if len > max {
+        return Err(ParseError::TooLong { len, max });
+    }
+    Ok(())
+}
+
+/// Слова little endian содержимого
+fn words(payload: &[u8]) -> impl Iterator<Item = u16> + '_ {
+    payload.chunks_exact(2).map(|word| ((word[1] as u16) << 8) | (word[0] as u16))
+}
+
+/// Значение параметра с минимумом и максимумом (байты 8-13)
+pub struct ValuesDecoder;
+
+impl PayloadDecoder for ValuesDecoder {
+    fn name(&self) -> &str {
+        "values"
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<Payload, ParseError> {
+        check_len(payload, 6, 6)?;
+        let values: Vec<u16> = words(payload).collect();
+        Ok(Payload::Values { prm: values[0], prm_max: values[1], prm_min: values[2] })
+    }
+}
+
+/// Блочное чтение: подряд идущие 16-битные значения
+pub struct BlockDecoder {
+    pub max_items: usize,    // Максимальное число значений в блоке
+}
+
+impl PayloadDecoder for BlockDecoder {
+    fn name(&self) -> &str {
+        "block"
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<Payload, ParseError> {
+        check_len(payload, 2, self.max_items * 2)?;
+        if !payload.len().is_multiple_of(2) {
+            return Err(ParseError::InvalidPayload {
+                reason: format!("block length {} is not a multiple of 2", payload.len()),
+            });
+        }
+        Ok(Payload::Block { values: words(payload).collect() })
+    }
+}
+
+/// Несколько параметров: пары слов (data_type, значение)
+pub struct BurstDecoder {
+    pub max_items: usize,    // Максимальное число параметров в пакете
+}
+
+impl PayloadDecoder for BurstDecoder {
+    fn name(&self) -> &str {
+        "burst"
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<Payload, ParseError> {
+        check_len(payload, 4, self.max_items * 4)?;
+        if !payload.len().is_multiple_of(4) {
+            return Err(ParseError::InvalidPayload {
+                reason: format!("burst length {} is not a multiple of 4", payload.len()),
+            });
+        }
+        let values: Vec<u16> = words(payload).collect();
+        Ok(Payload::Burst {
+            values: values.chunks_exact(2).map(|pair| (pair[0], pair[1])).collect(),
+        })
+    }
+}
+
+/// Строковый дескриптор в UTF-8, завершающие нулевые байты отбрасываются
+pub struct StringDecoder {
+    pub max_len: usize,      // Максимальная длина строки в байтах
+}
+
+impl PayloadDecoder for StringDecoder {
+    fn name(&self) -> &str {
+        "string"
+    }
+
+    fn decode(&self, payload: &[u8]) -> Result<Payload, ParseError> {
+        check_len(payload, 1, self.max_len)?;
+        let end = payload.iter().rposition(|&byte| byte != 0).map_or(0, |last| last + 1);
+        let text = std::str::from_utf8(&payload[..end]).map_err(|e| ParseError::InvalidPayload {
+            reason: format!("string is not valid UTF-8: {}", e),
+        })?;
+        Ok(Payload::Text { text: text.to_string() })
+    }
+}
+
+/// Реестр типов пакетов: package_type -> декодер содержимого
+#[derive(Clone)]
+pub struct PackageTypeRegistry {
+    decoders: HashMap<u16, Arc<dyn PayloadDecoder>>,
+}
+
+impl Default for PackageTypeRegistry {
+    /// Реестр со встроенным типом 0x8000 (одно значение параметра)
+    fn default() -> Self {
+        let mut registry = Self { decoders: HashMap::new() };
+        registry.register(PACKAGE_TYPE_VALUES, Arc::new(ValuesDecoder));
+        registry
+    }
+}
+
+impl PackageTypeRegistry {
+    /// Создает реестр со встроенными типами и типами, описанными в конфигурации
+    pub fn from_config(types: &[PackageTypeConfig]) -> Result<Self> {
+        let mut registry = Self::default();
+        for entry in types {
+            if entry.max_items == 0 {
+                return Err(anyhow::anyhow!(
+                    "Package type {:#06x}: max_items must be positive", entry.package_type
+                ));
+            }
+            let decoder: Arc<dyn PayloadDecoder> = match entry.layout {
+                PayloadLayout::Values => Arc::new(ValuesDecoder),
+                PayloadLayout::Block => Arc::new(BlockDecoder { max_items: entry.max_items }),
+                PayloadLayout::Burst => Arc::new(BurstDecoder { max_items: entry.max_items }),
+                PayloadLayout::String => Arc::new(StringDecoder { max_len: entry.max_items }),
+            };
+            registry.register(entry.package_type, decoder);
+        }
+        Ok(registry)
+    }
+
+    /// Регистрирует (или заменяет) декодер для типа пакета
+    pub fn register(&mut self, package_type: u16, decoder: Arc<dyn PayloadDecoder>) {
+        self.decoders.insert(package_type, decoder);
+    }
+
+    /// Возвращает декодер для типа пакета
+    pub fn get(&self, package_type: u16) -> Option<&dyn PayloadDecoder> {
+        self.decoders.get(&package_type).map(|decoder| decoder.as_ref())
+    }
+
+    /// Возвращает зарегистрированные типы и имена их декодеров
+    pub fn entries(&self) -> Vec<(u16, &str)> {
+        let mut entries: Vec<(u16, &str)> = self.decoders.iter()
+            .map(|(package_type, decoder)| (*package_type, decoder.name()))
+            .collect();
+        entries.sort();
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package_parser::{parse_package, HEADER_LEN};
+    use crate::protocol::{CrcCheck, HeaderLayout};
+    use crate::test_frames::{self, addr, src};
+
+    fn registry(package_type: u16, layout: PayloadLayout, max_items: usize) -> PackageTypeRegistry {
+        PackageTypeRegistry::from_config(&[PackageTypeConfig { package_type, layout, max_items }]).unwrap()
+    }
+
+    /// Пакет стандартной раскладки с CRC: addr BM 1 MCU 1, src dev_id 2
+    fn package(package_type: u16, data_type: u16, payload: &[u8]) -> Vec<u8> {
+        test_frames::package(CrcCheck::default(), [addr(2, 1, 1), package_type, src(2, 2), data_type], payload)
+    }
+
+    #[test]
+    fn odd_length_invalid() {
+        let block = BlockDecoder { max_items: 4 };
+        assert!(matches!(block.decode(&[1, 0, 2]), Err(ParseError::InvalidPayload { .. })));
+        assert_eq!(block.decode(&[1, 0, 2, 0]).unwrap(), Payload::Block { values: vec![1, 2] });
+
+        let burst = BurstDecoder { max_items: 4 };
+        assert!(matches!(burst.decode(&[10, 0, 1, 0, 11, 0]), Err(ParseError::InvalidPayload { .. })));
+        assert_eq!(
+            burst.decode(&[10, 0, 1, 0, 11, 0, 2, 0]).unwrap(),
+            Payload::Burst { values: vec![(10, 1), (11, 2)] }
+        );
+    }
+
+    #[test]
+    fn max_items_overflow() {
+        // Ошибка содержит длины пакета: заголовок, содержимое и CRC
+        let overhead = HEADER_LEN + CrcCheck::default().size();
+        let layout = HeaderLayout::default();
+        for (layout_kind, item) in [(PayloadLayout::Block, 2), (PayloadLayout::Burst, 4)] {
+            let registry = registry(0x9000, layout_kind, 3);
+            let full = package(0x9000, 10, &vec![0; 3 * item]);
+            assert_eq!(parse_package(&full, &registry, &layout, 2).unwrap().len(), 3);
+
+            let overflow = package(0x9000, 10, &vec![0; 4 * item]);
+            assert_eq!(
+                parse_package(&overflow, &registry, &layout, 2).unwrap_err(),
+                ParseError::TooLong { len: 4 * item + overhead, max: 3 * item + overhead }
+            );
+        }
+        assert!(PackageTypeRegistry::from_config(&[PackageTypeConfig {
+            package_type: 0x9000,
+            layout: PayloadLayout::Block,
+            max_items: 0,
+        }]).is_err());
+    }
+
+    #[test]
+    fn string_trailing_nul_and_utf8() {
+        let decoder = StringDecoder { max_len: 16 };
+        assert_eq!(decoder.decode(b"v1.2\0\0\0").unwrap(), Payload::Text { text: String::from("v1.2") });
+        // Нулевые байты внутри строки сохраняются, строка из одних нулей - пустая
+        assert_eq!(decoder.decode(b"a\0b\0").unwrap(), Payload::Text { text: String::from("a\0b") });
+        assert_eq!(decoder.decode(&[0, 0]).unwrap(), Payload::Text { text: String::new() });
+        assert!(matches!(decoder.decode(&[0x76, 0xFF, 0xFE]), Err(ParseError::InvalidPayload { .. })));
+        assert!(matches!(decoder.decode(&[0x41; 17]), Err(ParseError::TooLong { len: 17, max: 16 })));
+    }
+
+    #[test]
+    fn block_prm_id_increment_wraps() {
+        // Блок начинается с prm_id 1022 при 10-битном поле: 1022, 1023, 0
+        let registry = registry(0x9000, PayloadLayout::Block, 8);
+        let data_type = (1 << 14) | (0b0011 << 10) | 1022;
+        let package = package(0x9000, data_type, &[1, 0, 2, 0, 3, 0]);
+        let records = parse_package(&package, &registry, &HeaderLayout::default(), 2).unwrap();
+
+        let ids: Vec<u16> = records.iter().map(|record| record.prm_id).collect();
+        assert_eq!(ids, [1022, 1023, 0]);
+        let values: Vec<u16> = records.iter().map(|record| record.prm).collect();
+        assert_eq!(values, [1, 2, 3]);
+        // Остальные поля data_type сохраняются, prm_id подставляется в его биты
+        assert_eq!(records[2].data_type, (1 << 14) | (0b0011 << 10));
+        assert!(records.iter().all(|record| record.prm_type == 1 && record.alarms == 0b0011));
+    }
+}
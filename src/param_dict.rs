@@ -0,0 +1,406 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::include::pmbus;
+
+/// Способ преобразования 16-битного значения параметра
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Encoding {
+    /// PMBus LINEAR11 (5 бит экспоненты, 11 бит мантиссы)
+    Linear11,
+    /// PMBus LINEAR16: беззнаковая мантисса с фиксированной экспонентой
+    /// или (если экспонента не задана) с экспонентой из VOUT_MODE устройства
+    Linear16 { exponent: Option<i8> },
+    /// PMBus DIRECT: X = (Y * 10^-R - b) / m
+    Direct { m: i16, b: i16, r: i8 },
+    /// Ответ на команду VOUT_MODE (младший байт), задает экспоненту LINEAR16 устройства
+    VoutMode,
+    /// Беззнаковое целое
+    RawU16,
+    /// Знаковое целое (дополнительный код)
+    Signed,
+    /// Фиксированная точка: value = raw * scale + offset
+    Scaled { scale: f64, offset: f64, signed: bool },
+    /// Перечисление: значение -> метка
+    Enum { labels: BTreeMap<u16, String> },
+    /// Битовое поле: номер бита -> имя флага
+    Bitfield { flags: BTreeMap<u8, String> },
+}
+
+impl Encoding {
+    /// Разбирает описание кодировки:
+    /// `linear11`, `linear16[:<exp>]`, `direct:<m>:<b>:<R>`, `vout_mode`, `u16`, `i16`, `scaled:<scale>[:<offset>]`,
+    /// `scaled_signed:<scale>[:<offset>]`, `enum:0=off;1=on`, `bitfield:0=fault;3=warn`
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        let (kind, args) = spec.split_once(':').unwrap_or((spec, ""));
+        let number = |text: &str| -> Result<f64> {
+            text.trim().parse::<f64>().context(format!("Invalid number in encoding: {}", spec))
+        };
+
+        match kind.to_ascii_lowercase().as_str() {
+            "linear11" => Ok(Encoding::Linear11),
+            "linear16" if args.trim().is_empty() => Ok(Encoding::Linear16 { exponent: None }),
+            "linear16" => Ok(Encoding::Linear16 {
+                exponent: Some(args.trim().parse()
+                    .context(format!("Invalid linear16 exponent: {}", spec))?),
+            }),
+            "direct" => {
+                let parts: Vec<&str> = args.split(':').map(str::trim).collect();
+                let [m, b, r] = parts.as_slice() else {
+                    return Err(anyhow::anyhow!("direct requires m:b:R coefficients: {}", spec));
+                };
+                let m: i16 = m.parse().context(format!("Invalid direct coefficient m: {}", spec))?;
+                if m == 0 {
+                    return Err(anyhow::anyhow!("direct coefficient m must not be zero: {}", spec));
+                }
+                Ok(Encoding::Direct {
+                    m,
+                    b: b.parse().context(format!("Invalid direct coefficient b: {}", spec))?,
+                    r: r.parse().context(format!("Invalid direct coefficient R: {}", spec))?,
+                })
+            }
+            "vout_mode" => Ok(Encoding::VoutMode),
+            "u16" | "raw" => Ok(Encoding::RawU16),
+            "i16" | "signed" => Ok(Encoding::Signed),
+            "scaled" | "scaled_signed" => {
+                let mut parts = args.split(':');
+                let scale = number(parts.next().unwrap_or_default())?;
+                let offset = match parts.next() {
+                    Some(offset) => number(offset)?,
+                    None => 0.0,
+                };
+                Ok(Encoding::Scaled { scale, offset, signed: kind.eq_ignore_ascii_case("scaled_signed") })
+            }
+            "enum" => Ok(Encoding::Enum { labels: parse_pairs(args, spec)? }),
+            "bitfield" => {
+                let flags = parse_pairs::<u8>(args, spec)?;
+                if flags.keys().any(|&bit| bit > 15) {
+                    return Err(anyhow::anyhow!("Bit number out of range in encoding: {}", spec));
+                }
+                Ok(Encoding::Bitfield { flags })
+            }
+            other => Err(anyhow::anyhow!("Unknown encoding: {}", other)),
+        }
+    }
+
+    /// Преобразует сырое значение параметра
+    /// vout_exponent - экспонента LINEAR16 из VOUT_MODE устройства (если известна)
+    pub fn decode(&self, raw: u16, vout_exponent: Option<i8>) -> DecodedValue {
+        match self {
+            Encoding::Linear11 => DecodedValue::number(pmbus::from_linear11(raw)),
+            Encoding::Linear16 { exponent } => match exponent.or(vout_exponent) {
+                Some(exponent) => DecodedValue::number(pmbus::from_linear16(raw, exponent)),
+                None => DecodedValue {
+                    value: f64::NAN,
+                    text: Some(String::from("vout_mode unknown")),
+                },
+            },
+            Encoding::Direct { m, b, r } => {
+                let coefficients = pmbus::DirectCoefficients { m: *m, b: *b, r: *r };
+                // m проверен при разборе, поэтому ошибки быть не может
+                DecodedValue::number(pmbus::from_direct(raw, coefficients).unwrap_or(f64::NAN))
+            }
+            Encoding::VoutMode => DecodedValue {
+                value: raw as f64,
+                text: pmbus::VoutMode::parse(raw as u8).ok().map(|mode| format!("{:?}", mode)),
+            },
+            Encoding::RawU16 => DecodedValue::number(raw as f64),
+            Encoding::Signed => DecodedValue::number(raw as i16 as f64),
+            Encoding::Scaled { scale, offset, signed } => {
+                let raw = if *signed { raw as i16 as f64 } else { raw as f64 };
+                DecodedValue::number(raw * scale + offset)
+            }
+            Encoding::Enum { labels } => DecodedValue {
+                value: raw as f64,
+                text: Some(labels.get(&raw).cloned().unwrap_or_else(|| format!("unknown({})", raw))),
+            },
+            Encoding::Bitfield { flags } => {
+                let set: Vec<String> = (0..16u8)
+                    .filter(|bit| raw & (1 << bit) != 0)
+                    .map(|bit| flags.get(&bit).cloned().unwrap_or_else(|| format!("bit{}", bit)))
+                    .collect();
+                DecodedValue {
+                    value: raw as f64,
+                    text: Some(set.join("|")),
+                }
+            }
+        }
+    }
+}
+
+/// Разбирает пары `ключ=значение;...`
+fn parse_pairs<K: std::str::FromStr + Ord>(args: &str, spec: &str) -> Result<BTreeMap<K, String>> {
+    args.split(';')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| {
+            let (key, label) = pair.split_once('=')
+                .context(format!("Expected key=label in encoding: {}", spec))?;
+            let key = key.trim().parse::<K>()
+                .map_err(|_| anyhow::anyhow!("Invalid key '{}' in encoding: {}", key, spec))?;
+            Ok((key, label.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Преобразованное значение параметра
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedValue {
+    pub value: f64,              // Числовое значение
+    pub text: Option<String>,    // Метка перечисления или список флагов
+}
+
+impl DecodedValue {
+    fn number(value: f64) -> Self {
+        Self { value, text: None }
+    }
+}
+
+/// Описание параметра
+#[derive(Debug, Clone, Serialize)]
+pub struct ParamDef {
+    pub prm_id: u16,             // Идентификатор параметра (10 бит)
+    pub name: String,            // Имя параметра
+    pub unit: String,            // Единица измерения
+    pub encoding: Encoding,      // Кодировка значения
+    pub description: String,     // Описание
+}
+
+/// Запись параметра в TOML-файле словаря
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ParamRecord {
+    prm_id: u16,
+    name: String,
+    #[serde(default)]
+    unit: String,
+    #[serde(default = "default_encoding")]
+    encoding: String,
+    #[serde(default)]
+    description: String,
+}
+
+fn default_encoding() -> String {
+    String::from("linear11")
+}
+
+/// Корень TOML-файла словаря
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DictionaryFile {
+    params: Vec<ParamRecord>,
+}
+
+/// Словарь параметров: prm_id -> имя, единица, кодировка
+/// Параметры, отсутствующие в словаре, декодируются как LINEAR11
+#[derive(Debug, Clone, Default)]
+pub struct ParamDictionary {
+    params: HashMap<u16, ParamDef>,
+}
+
+impl ParamDictionary {
+    /// Загружает словарь из TOML (`[[params]]`) или CSV
+    /// (`prm_id,name,unit,encoding,description`) по расширению файла
+    pub fn load(path: &str) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .context(format!("Failed to read parameter dictionary: {}", path))?;
+
+        let is_csv = Path::new(path).extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+        let records = if is_csv {
+            parse_csv(&text).context(format!("Failed to parse parameter dictionary: {}", path))?
+        } else {
+            toml::from_str::<DictionaryFile>(&text)
+                .context(format!("Failed to parse parameter dictionary: {}", path))?
+                .params
+        };
+
+        let mut params = HashMap::new();
+        for record in records {
+            if record.prm_id > 0x03FF {
+                return Err(anyhow::anyhow!("prm_id {} does not fit in 10 bits", record.prm_id));
+            }
+            let encoding = Encoding::parse(&record.encoding)
+                .context(format!("Invalid encoding for prm_id {}", record.prm_id))?;
+            let def = ParamDef {
+                prm_id: record.prm_id,
+                name: record.name,
+                unit: record.unit,
+                encoding,
+                description: record.description,
+            };
+            if params.insert(def.prm_id, def).is_some() {
+                return Err(anyhow::anyhow!("Duplicate prm_id {} in {}", record.prm_id, path));
+            }
+        }
+
+        Ok(Self { params })
+    }
+
+    /// Возвращает количество описанных параметров
+    pub fn len(&self) -> usize {
+        self.params.len()
+    }
+
+    /// Возвращает описание параметра
+    pub fn get(&self, prm_id: u16) -> Option<&ParamDef> {
+        self.params.get(&prm_id)
+    }
+
+    /// Возвращает все описания параметров, упорядоченные по prm_id
+    pub fn entries(&self) -> Vec<ParamDef> {
+        let mut entries: Vec<ParamDef> = self.params.values().cloned().collect();
+        entries.sort_by_key(|def| def.prm_id);
+        entries
+    }
+
+    /// Преобразует значение параметра по словарю (по умолчанию LINEAR11)
+    pub fn decode(&self, prm_id: u16, raw: u16, vout_exponent: Option<i8>) -> DecodedValue {
+        match self.get(prm_id) {
+            Some(def) => def.encoding.decode(raw, vout_exponent),
+            None => Encoding::Linear11.decode(raw, vout_exponent),
+        }
+    }
+}
+
+/// Разбирает CSV-словарь: заголовок и строки `prm_id,name,unit,encoding,description`
+/// Описание может содержать запятые - оно занимает остаток строки
+fn parse_csv(text: &str) -> Result<Vec<ParamRecord>> {
+    let mut records = Vec::new();
+    let mut first = true;
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.splitn(5, ',').map(str::trim).collect();
+
+        // Пропускаем строку заголовка (первую строку данных, комментарии перед ней допустимы)
+        if std::mem::take(&mut first) && fields[0].eq_ignore_ascii_case("prm_id") {
+            continue;
+        }
+        if fields.len() < 2 {
+            return Err(anyhow::anyhow!("Line {}: expected at least prm_id,name", number + 1));
+        }
+
+        records.push(ParamRecord {
+            prm_id: fields[0].parse()
+                .context(format!("Line {}: invalid prm_id", number + 1))?,
+            name: fields[1].to_string(),
+            unit: fields.get(2).unwrap_or(&"").to_string(),
+            encoding: fields.get(3).filter(|spec| !spec.is_empty())
+                .map_or_else(default_encoding, |spec| spec.to_string()),
+            description: fields.get(4).unwrap_or(&"").trim_matches('"').to_string(),
+        });
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(spec: &str, raw: u16) -> DecodedValue {
+        Encoding::parse(spec).unwrap().decode(raw, None)
+    }
+
+    fn text(spec: &str, raw: u16) -> String {
+        decode(spec, raw).text.unwrap()
+    }
+
+    #[test]
+    fn encodings() {
+        // 0xE820: экспонента -3, мантисса 32
+        assert_eq!(decode("linear11", 0xE820).value, 4.0);
+        assert_eq!(decode("linear16:-12", 0x1800).value, 1.5);
+        assert_eq!(decode("direct:2:0:0", 100).value, 50.0);
+        assert_eq!(decode("direct:1:0:-1", 100).value, 1000.0);
+        assert_eq!(decode("u16", 0xFFFF).value, 65535.0);
+        assert_eq!(decode("i16", 0xFFFF).value, -1.0);
+        assert_eq!(decode("scaled:0.5:-10", 100).value, 40.0);
+        assert_eq!(decode("scaled_signed:0.5", 0xFFFE).value, -1.0);
+        assert_eq!(text("enum:0=off; 1=on", 1), "on");
+        assert_eq!(text("enum:0=off;1=on", 5), "unknown(5)");
+        assert_eq!(text("bitfield:0=fault;3=warn", 0b1011), "fault|bit1|warn");
+        assert_eq!(text("bitfield:0=fault", 0), "");
+
+        // VOUT_MODE: режим linear, экспонента -12
+        let vout_mode = decode("vout_mode", 0x14);
+        assert_eq!(vout_mode.value, 20.0);
+        assert_eq!(vout_mode.text.as_deref(), Some("Linear { exponent: -12 }"));
+
+        // LINEAR16 без экспоненты берет ее из VOUT_MODE устройства
+        let linear16 = Encoding::parse("linear16").unwrap();
+        assert_eq!(linear16.decode(0x1800, Some(-12)).value, 1.5);
+        let unknown = linear16.decode(0x1800, None);
+        assert!(unknown.value.is_nan());
+        assert_eq!(unknown.text.as_deref(), Some("vout_mode unknown"));
+
+        for spec in ["float", "linear16:x", "direct:0:0:0", "direct:1:2", "scaled:abc",
+                     "enum:1", "bitfield:16=overflow", "bitfield:x=flag"] {
+            assert!(Encoding::parse(spec).is_err(), "{} accepted", spec);
+        }
+    }
+
+    #[test]
+    fn csv_header_and_quoting() {
+        let records = parse_csv(concat!(
+            "# словарь платы\n",
+            "prm_id,name,unit,encoding,description\n",
+            "10,fpga_temp,C,,\"FPGA die temperature, sensor 1\"\n",
+            "\n",
+            "11, vout ,V,linear16:-12\n",
+            "12,status\n",
+        )).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].prm_id, 10);
+        assert_eq!(records[0].encoding, "linear11");
+        assert_eq!(records[0].description, "FPGA die temperature, sensor 1");
+        assert_eq!(records[1].name, "vout");
+        assert_eq!(records[1].encoding, "linear16:-12");
+        assert_eq!(records[2].unit, "");
+
+        assert!(parse_csv("prm_id,name\nx,bad\n").is_err());
+        assert!(parse_csv("10\n").is_err());
+    }
+
+    #[test]
+    fn load_checks() {
+        let dir = std::env::temp_dir();
+        let write = |name: &str, text: &str| {
+            let path = dir.join(format!("hwmon-dict-test-{}-{}", std::process::id(), name));
+            fs::write(&path, text).unwrap();
+            path.to_string_lossy().into_owned()
+        };
+
+        let toml = write("ok.toml", concat!(
+            "[[params]]\nprm_id = 10\nname = \"fpga_temp\"\nunit = \"C\"\n",
+            "[[params]]\nprm_id = 1023\nname = \"status\"\nencoding = \"bitfield:0=fault\"\n",
+        ));
+        let dictionary = ParamDictionary::load(&toml).unwrap();
+        assert_eq!(dictionary.len(), 2);
+        assert_eq!(dictionary.get(10).unwrap().encoding, Encoding::Linear11);
+        assert_eq!(dictionary.entries().iter().map(|def| def.prm_id).collect::<Vec<_>>(), [10, 1023]);
+        // Параметры вне словаря декодируются как LINEAR11
+        assert_eq!(dictionary.decode(11, 0xE820, None).value, 4.0);
+
+        let csv = write("ok.csv", "prm_id,name\n10,fpga_temp\n");
+        assert_eq!(ParamDictionary::load(&csv).unwrap().get(10).unwrap().name, "fpga_temp");
+
+        let wide = write("wide.csv", "1024,too_wide\n");
+        let duplicate = write("duplicate.csv", "10,first\n10,second\n");
+        let bad_encoding = write("bad.csv", "10,temp,C,float\n");
+        for path in [&wide, &duplicate, &bad_encoding] {
+            assert!(ParamDictionary::load(path).is_err(), "{} accepted", path);
+        }
+        assert!(format!("{:#}", ParamDictionary::load(&duplicate).unwrap_err()).contains("Duplicate prm_id 10"));
+
+        for path in [toml, csv, wide, duplicate, bad_encoding] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+}
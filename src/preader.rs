@@ -1,12 +1,19 @@
 // src/preader.rs
-use anyhow::{Context, Result};
+use anyhow::Result;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::time::{sleep, Duration};
+use tracing::{debug, error, info, trace, warn};
 
 use crate::channels::PackageSender;
+use crate::metrics::METRICS;
 use crate::uart::Uart;
 
+/// Имя источника для метрик
+const SOURCE: &str = "uart";
+/// Пауза перед повторным открытием порта после ошибки чтения
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
 /// Пакетный ридер для чтения данных с UART и формирования пакетов
 /// Пакеты разделяются байтом-разделителем 0xC0
 pub struct PReader {
@@ -28,10 +35,9 @@ impl PReader {
     }
 
     /// Запускает процесс чтения данных с UART
-    pub fn start_reading(&mut self) -> Result<()> {
-        let uart_guard = self.uart.try_lock()
-            .context("Failed to lock UART for reading")?;
-        
+    pub async fn start_reading(&mut self) -> Result<()> {
+        let uart_guard = self.uart.lock().await;
+
         // Проверяем, что порт открыт перед началом чтения
         if !uart_guard.is_open() {
             return Err(anyhow::anyhow!("Serial port is not open"));
@@ -39,17 +45,19 @@ impl PReader {
 
         self.reading_active = true;
         self.current_packet.clear();
-        println!("UART reading started");
+        info!("UART reading started");
         Ok(())
     }
 
     /// Останавливает процесс чтения данных
+    #[allow(dead_code)]
     pub fn stop_reading(&mut self) {
         self.reading_active = false;
-        println!("UART reading stopped");
+        info!("UART reading stopped");
     }
 
     /// Возвращает статус активности чтения
+    #[allow(dead_code)]
     pub fn is_reading(&self) -> bool {
         self.reading_active
     }
@@ -67,10 +75,9 @@ impl PReader {
                 break;
             }
 
-            // Читаем новые данные с UART
+            // Читаем новые данные с UART; порт может быть занят записью команды (PWriter)
             let new_data = {
-                let mut uart_guard = self.uart.try_lock()
-                    .context("Failed to lock UART for data reading")?;
+                let mut uart_guard = self.uart.lock().await;
 
                 // Если данных нет, ждем немного и продолжаем
                 if !uart_guard.is_readable() {
@@ -78,12 +85,28 @@ impl PReader {
                     continue;
                 }
 
-                uart_guard.read_all()?
+                match uart_guard.read_all() {
+                    Ok(data) => data,
+                    Err(e) => {
+                        // Ошибка чтения - пробуем переоткрыть порт
+                        warn!("UART read error: {}, reopening port", e);
+                        METRICS.reconnects.inc(&[SOURCE]);
+                        // Байты до ошибки не должны попасть в первый кадр после переоткрытия
+                        self.current_packet.clear();
+                        if let Err(e) = uart_guard.reopen() {
+                            error!("Failed to reopen UART: {}", e);
+                        }
+                        drop(uart_guard);
+                        sleep(RECONNECT_DELAY).await;
+                        continue;
+                    }
+                }
             };
 
             // Обрабатываем полученные данные
             if !new_data.is_empty() {
-                println!("UART read {} bytes", new_data.len());
+                trace!(size = new_data.len(), "UART read");
+                METRICS.source_bytes.add(&[SOURCE], new_data.len() as u64);
                 for byte in new_data {
                     self.process_byte(byte).await;
                 }
@@ -109,16 +132,17 @@ impl PReader {
         }
 
         // Если встретили байт-разделитель, отправляем собранный пакет
-        if byte == 0xC0 {
-            if !self.current_packet.is_empty() {
-                // Отправляем пакет через канал (аналог signalPRPackage)
-                if let Err(e) = self.package_sender.send(self.current_packet.clone()) {
-                    eprintln!("Failed to send UART package: {}", e);
-                } else {
-                    println!("UART packet sent: {}", hex::encode(&self.current_packet));
-                }
-                self.current_packet.clear();
+        if byte == 0xC0 && !self.current_packet.is_empty() {
+            METRICS.source_frames.inc(&[SOURCE]);
+
+            // Отправляем пакет через канал (аналог signalPRPackage)
+            if let Err(e) = self.package_sender.send(self.current_packet.clone()) {
+                METRICS.dropped_packages.inc(&[SOURCE]);
+                error!("Failed to send UART package: {}", e);
+            } else {
+                debug!(package = %hex::encode(&self.current_packet), "UART packet sent");
             }
+            self.current_packet.clear();
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,564 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use tracing::{info, warn};
+
+use crate::config::{ProtocolConfig, ProtocolProfileConfig};
+use crate::include::crc;
+use crate::package_parser;
+use crate::package_types::PackageTypeRegistry;
+
+/// Имя встроенного профиля текущего поколения плат
+pub const STANDARD_PROFILE: &str = "standard";
+
+/// Значение настройки профиля, включающее автоопределение
+pub const AUTO_PROFILE: &str = "auto";
+
+/// Битовое поле внутри 16-битного слова заголовка
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct BitField {
+    pub shift: u8,    // Номер младшего бита
+    pub width: u8,    // Ширина в битах
+}
+
+impl BitField {
+    const fn new(shift: u8, width: u8) -> Self {
+        Self { shift, width }
+    }
+
+    /// Маска поля на своем месте в слове
+    pub fn mask(self) -> u16 {
+        (((1u32 << self.width) - 1) << self.shift) as u16
+    }
+
+    /// Извлекает значение поля из слова
+    pub fn extract(self, word: u16) -> u16 {
+        (word & self.mask()) >> self.shift
+    }
+
+    /// Проверяет, что поле помещается в 16 бит
+    fn validate(self, name: &str) -> Result<()> {
+        if self.width == 0 || self.shift as u32 + self.width as u32 > 16 {
+            return Err(anyhow::anyhow!(
+                "Field {} (shift {}, width {}) does not fit in 16 bits", name, self.shift, self.width
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Раскладка битовых полей заголовка (addr, src, data_type)
+/// Незаданные в конфигурации поля берутся из стандартной раскладки
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HeaderLayout {
+    pub module_addr: BitField,    // Адрес модуля в addr
+    pub mcu_width: u8,            // Ширина адреса MCU (младшие биты module_addr), остальное - BM
+    pub module_id: BitField,      // Идентификатор модуля в addr
+    pub dev_id: BitField,         // Идентификатор устройства в src
+    pub pwr_line: BitField,       // Линия питания в src
+    pub src_id: BitField,         // Идентификатор источника в src
+    pub rtr: BitField,            // Флаг RTR в src
+    pub prm_id: BitField,         // Идентификатор параметра в data_type
+    pub alarms: BitField,         // Аварийные биты в data_type
+    pub prm_type: BitField,       // Тип параметра в data_type
+}
+
+impl Default for HeaderLayout {
+    /// Раскладка текущего поколения плат
+    fn default() -> Self {
+        Self {
+            module_addr: BitField::new(0, 7),
+            mcu_width: 3,
+            module_id: BitField::new(7, 4),
+            dev_id: BitField::new(0, 7),
+            pwr_line: BitField::new(7, 4),
+            src_id: BitField::new(11, 5),
+            rtr: BitField::new(15, 1),
+            prm_id: BitField::new(0, 10),
+            alarms: BitField::new(10, 4),
+            prm_type: BitField::new(14, 2),
+        }
+    }
+}
+
+impl HeaderLayout {
+    /// Биты addr, не занятые полями (должны быть нулевыми)
+    pub fn addr_reserved_mask(&self) -> u16 {
+        !(self.module_addr.mask() | self.module_id.mask())
+    }
+
+    /// Проверяет корректность раскладки
+    fn validate(&self) -> Result<()> {
+        for (name, field) in [
+            ("module_addr", self.module_addr),
+            ("module_id", self.module_id),
+            ("dev_id", self.dev_id),
+            ("pwr_line", self.pwr_line),
+            ("src_id", self.src_id),
+            ("rtr", self.rtr),
+            ("prm_id", self.prm_id),
+            ("alarms", self.alarms),
+            ("prm_type", self.prm_type),
+        ] {
+            field.validate(name)?;
+        }
+        // Эти поля хранятся в PackageStruct в u8
+        for (name, field) in [
+            ("module_addr", self.module_addr),
+            ("dev_id", self.dev_id),
+            ("pwr_line", self.pwr_line),
+            ("src_id", self.src_id),
+            ("alarms", self.alarms),
+            ("prm_type", self.prm_type),
+        ] {
+            if field.width > 8 {
+                return Err(anyhow::anyhow!("Field {} (width {}) must not be wider than 8 bits", name, field.width));
+            }
+        }
+        if self.mcu_width >= self.module_addr.width {
+            return Err(anyhow::anyhow!("mcu_width must be narrower than module_addr"));
+        }
+        Ok(())
+    }
+}
+
+/// Вариант CRC, которым защищен пакет
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrcVariant {
+    /// CRC-16/CCITT-FALSE (полином 0x1021, начальное значение 0xFFFF)
+    #[default]
+    CcittFalse,
+    /// CRC-16/XMODEM (полином 0x1021, начальное значение 0x0000)
+    Xmodem,
+    /// CRC-16/KERMIT (полином 0x1021, отраженный, начальное значение 0x0000)
+    Kermit,
+    /// CRC-16/MODBUS (полином 0x8005, отраженный, начальное значение 0xFFFF)
+    Modbus,
+    /// CRC-32 (ISO-HDLC, 4 байта)
+    Crc32,
+}
+
+impl CrcVariant {
+    /// Все поддерживаемые варианты (для диагностики)
+    pub const ALL: [CrcVariant; 5] = [
+        CrcVariant::CcittFalse,
+        CrcVariant::Xmodem,
+        CrcVariant::Kermit,
+        CrcVariant::Modbus,
+        CrcVariant::Crc32,
+    ];
+
+    /// Имя варианта (как в конфигурации)
+    pub fn name(self) -> &'static str {
+        match self {
+            CrcVariant::CcittFalse => "ccitt_false",
+            CrcVariant::Xmodem => "xmodem",
+            CrcVariant::Kermit => "kermit",
+            CrcVariant::Modbus => "modbus",
+            CrcVariant::Crc32 => "crc32",
+        }
+    }
+
+    /// Параметры алгоритма
+    fn params(self) -> &'static crc::CrcParams {
+        match self {
+            CrcVariant::CcittFalse => &crc::CRC16_CCITT_FALSE,
+            CrcVariant::Xmodem => &crc::CRC16_XMODEM,
+            CrcVariant::Kermit => &crc::CRC16_KERMIT,
+            CrcVariant::Modbus => &crc::CRC16_MODBUS,
+            CrcVariant::Crc32 => &crc::CRC32,
+        }
+    }
+
+    /// Длина CRC в байтах
+    pub fn size(self) -> usize {
+        self.params().width as usize / 8
+    }
+
+    /// Вычисляет CRC данных
+    pub fn calculate(self, data: &[u8]) -> u32 {
+        match self {
+            // Табличная реализация для основного варианта
+            CrcVariant::CcittFalse => crc::calculate_crc16(data) as u32,
+            _ => crc::calculate_crc(self.params(), data),
+        }
+    }
+}
+
+/// Порядок байтов CRC в конце пакета
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ByteOrder {
+    /// Старший байт первым
+    #[default]
+    Big,
+    /// Младший байт первым
+    Little,
+}
+
+impl ByteOrder {
+    /// Оба порядка байтов (для диагностики)
+    pub const ALL: [ByteOrder; 2] = [ByteOrder::Big, ByteOrder::Little];
+
+    /// Имя порядка байтов (как в конфигурации)
+    pub fn name(self) -> &'static str {
+        match self {
+            ByteOrder::Big => "big",
+            ByteOrder::Little => "little",
+        }
+    }
+
+    /// Читает беззнаковое число из байтов
+    fn read(self, bytes: &[u8]) -> u32 {
+        let fold = |acc: u32, byte: &u8| (acc << 8) | *byte as u32;
+        match self {
+            ByteOrder::Big => bytes.iter().fold(0, fold),
+            ByteOrder::Little => bytes.iter().rev().fold(0, fold),
+        }
+    }
+
+    /// Записывает беззнаковое число в size байтов
+    fn write(self, value: u32, size: usize) -> Vec<u8> {
+        let bytes = &value.to_be_bytes()[4 - size..];
+        match self {
+            ByteOrder::Big => bytes.to_vec(),
+            ByteOrder::Little => bytes.iter().rev().copied().collect(),
+        }
+    }
+}
+
+/// Способ проверки CRC: вариант алгоритма и порядок байтов в пакете
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct CrcCheck {
+    pub variant: CrcVariant,       // Алгоритм CRC
+    pub byte_order: ByteOrder,     // Порядок байтов CRC в пакете
+}
+
+impl fmt::Display for CrcCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.variant.name(), self.byte_order.name())
+    }
+}
+
+impl CrcCheck {
+    /// Длина CRC в конце пакета
+    pub fn size(self) -> usize {
+        self.variant.size()
+    }
+
+    /// Разделяет пакет на данные и CRC из пакета; None, если пакет короче CRC
+    fn split(self, package: &[u8]) -> Option<(&[u8], u32)> {
+        let crc_start = package.len().checked_sub(self.size())?;
+        let (data, crc) = package.split_at(crc_start);
+        Some((data, self.byte_order.read(crc)))
+    }
+
+    /// CRC из пакета и CRC, вычисленный по данным; None, если пакет короче CRC
+    pub fn values(self, package: &[u8]) -> Option<(u32, u32)> {
+        self.split(package).map(|(data, expected)| (expected, self.variant.calculate(data)))
+    }
+
+    /// Дописывает CRC данных в конец пакета
+    pub fn append(self, package: &mut Vec<u8>) {
+        let crc = self.variant.calculate(package);
+        package.extend(self.byte_order.write(crc, self.size()));
+    }
+
+    /// Форматирует значение CRC в hex с шириной варианта
+    pub fn format(self, value: u32) -> String {
+        format!("{:#0width$x}", value, width = 2 + self.size() * 2)
+    }
+
+    /// Проверяет CRC в конце пакета
+    pub fn validate(self, package: &[u8]) -> bool {
+        match self.split(package) {
+            Some((data, expected)) => match self.variant {
+                CrcVariant::CcittFalse if self.byte_order == ByteOrder::Big => {
+                    crc::crc16_validate(data, expected as u16)
+                }
+                _ => self.variant.calculate(data) == expected,
+            },
+            None => false,
+        }
+    }
+
+    /// Диагностика: все сочетания варианта и порядка байтов, с которыми CRC пакета сходится
+    pub fn diagnose(package: &[u8]) -> Vec<CrcCheck> {
+        CrcVariant::ALL.iter()
+            .flat_map(|&variant| ByteOrder::ALL.iter().map(move |&byte_order| CrcCheck { variant, byte_order }))
+            .filter(|check| check.validate(package))
+            .collect()
+    }
+}
+
+/// Профиль протокола: раскладка заголовка и вариант CRC
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProtocolProfile {
+    pub name: String,            // Имя профиля
+    pub layout: HeaderLayout,    // Раскладка заголовка
+    pub crc: CrcCheck,           // Вариант CRC и порядок его байтов
+}
+
+impl ProtocolProfile {
+    /// Встроенный профиль текущего поколения плат
+    pub fn standard() -> Self {
+        Self {
+            name: STANDARD_PROFILE.to_string(),
+            layout: HeaderLayout::default(),
+            crc: CrcCheck::default(),
+        }
+    }
+
+    /// Создает профиль из конфигурации
+    fn from_config(config: &ProtocolProfileConfig) -> Result<Self> {
+        if config.name == AUTO_PROFILE {
+            return Err(anyhow::anyhow!("Protocol profile name '{}' is reserved", AUTO_PROFILE));
+        }
+        config.layout.validate()
+            .map_err(|e| anyhow::anyhow!("Protocol profile {}: {}", config.name, e))?;
+        Ok(Self {
+            name: config.name.clone(),
+            layout: config.layout,
+            crc: CrcCheck { variant: config.crc, byte_order: config.crc_byte_order },
+        })
+    }
+
+    /// Проверяет, подходит ли пакет под профиль (CRC и разбор заголовка)
+    pub fn accepts(&self, package: &[u8], registry: &PackageTypeRegistry) -> bool {
+        self.crc.validate(package)
+            && package_parser::parse_package(package, registry, &self.layout, self.crc.size()).is_ok()
+    }
+}
+
+/// Выбор профиля протокола для источника: фиксированный или автоопределение
+/// При автоопределении первые кадры проверяются всеми профилями, и после
+/// `window` кадров выбирается профиль, принявший больше всего из них.
+/// До завершения определения используется текущий лидер
+pub struct ProtocolSelector {
+    profiles: Vec<ProtocolProfile>,    // Профили-кандидаты
+    scores: Vec<u32>,                  // Количество принятых кадров по профилям
+    frames: u32,                       // Кадров просмотрено при определении
+    window: u32,                       // Кадров для принятия решения
+    selected: Option<usize>,           // Выбранный профиль
+}
+
+impl Default for ProtocolSelector {
+    /// Фиксированный стандартный профиль
+    fn default() -> Self {
+        Self::fixed(ProtocolProfile::standard())
+    }
+}
+
+impl ProtocolSelector {
+    /// Селектор с заранее выбранным профилем
+    pub fn fixed(profile: ProtocolProfile) -> Self {
+        Self {
+            profiles: vec![profile],
+            scores: vec![0],
+            frames: 0,
+            window: 0,
+            selected: Some(0),
+        }
+    }
+
+    /// Создает селектор для источника по конфигурации
+    pub fn from_config(config: &ProtocolConfig, source: &str) -> Result<Self> {
+        let mut profiles = vec![ProtocolProfile::standard()];
+        for entry in &config.profiles {
+            let profile = ProtocolProfile::from_config(entry)?;
+            match profiles.iter_mut().find(|existing| existing.name == profile.name) {
+                Some(existing) => *existing = profile,
+                None => profiles.push(profile),
+            }
+        }
+
+        let name = config.sources.get(source).unwrap_or(&config.profile);
+        if name == AUTO_PROFILE {
+            if config.detect_frames == 0 {
+                return Err(anyhow::anyhow!("protocol.detect_frames must be positive"));
+            }
+            return Ok(Self {
+                scores: vec![0; profiles.len()],
+                profiles,
+                frames: 0,
+                window: config.detect_frames,
+                selected: None,
+            });
+        }
+
+        let profile = profiles.into_iter()
+            .find(|profile| &profile.name == name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown protocol profile: {}", name))?;
+        Ok(Self::fixed(profile))
+    }
+
+    /// Учитывает кадр при автоопределении
+    pub fn observe(&mut self, package: &[u8], registry: &PackageTypeRegistry) {
+        if self.selected.is_some() {
+            return;
+        }
+
+        for (profile, score) in self.profiles.iter().zip(self.scores.iter_mut()) {
+            if profile.accepts(package, registry) {
+                *score += 1;
+            }
+        }
+        self.frames += 1;
+        if self.frames < self.window {
+            return;
+        }
+
+        let best = self.leader();
+        if self.scores[best] == 0 {
+            if self.frames == self.window {
+                warn!(frames = self.frames, "No protocol profile matched the first frames, still detecting");
+            }
+            return;
+        }
+        self.selected = Some(best);
+        info!(
+            profile = %self.profiles[best].name,
+            accepted = self.scores[best],
+            frames = self.frames,
+            "Protocol profile detected"
+        );
+    }
+
+    /// Текущий профиль (выбранный или лидирующий при определении)
+    pub fn current(&self) -> &ProtocolProfile {
+        &self.profiles[self.selected.unwrap_or_else(|| self.leader())]
+    }
+
+    /// Профиль с наибольшим числом принятых кадров (при равенстве - первый)
+    fn leader(&self) -> usize {
+        self.scores.iter()
+            .enumerate()
+            .max_by_key(|(index, score)| (**score, std::cmp::Reverse(*index)))
+            .map_or(0, |(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::psorter::PSorter;
+    use crate::test_frames;
+
+    /// Раскладка старого поколения плат: 8-битный адрес модуля (BM 4 бита, MCU 4 бита),
+    /// module_id в битах 8-11
+    fn legacy_layout() -> HeaderLayout {
+        HeaderLayout {
+            module_addr: BitField::new(0, 8),
+            mcu_width: 4,
+            module_id: BitField::new(8, 4),
+            ..HeaderLayout::default()
+        }
+    }
+
+    fn legacy_config(profile: &str) -> ProtocolConfig {
+        ProtocolConfig {
+            profile: profile.to_string(),
+            detect_frames: 4,
+            profiles: vec![ProtocolProfileConfig {
+                name: String::from("legacy"),
+                crc: CrcVariant::Xmodem,
+                crc_byte_order: ByteOrder::Little,
+                layout: legacy_layout(),
+            }],
+            ..ProtocolConfig::default()
+        }
+    }
+
+    /// Пакет 0x8000 с заданным CRC (без байт-стаффинга)
+    fn package(crc: CrcCheck, addr: u16, src: u16, data_type: u16) -> Vec<u8> {
+        test_frames::package(crc, [addr, 0x8000, src, data_type], &test_frames::words(&test_frames::VALUES))
+    }
+
+    fn legacy_crc() -> CrcCheck {
+        CrcCheck { variant: CrcVariant::Xmodem, byte_order: ByteOrder::Little }
+    }
+
+    #[test]
+    fn layout_validation() {
+        assert!(HeaderLayout::default().validate().is_ok());
+        assert!(legacy_layout().validate().is_ok());
+
+        let too_wide = |layout: HeaderLayout| layout.validate().unwrap_err().to_string();
+        let dev_id = HeaderLayout { dev_id: BitField::new(0, 9), pwr_line: BitField::new(9, 2), ..HeaderLayout::default() };
+        assert!(too_wide(dev_id).contains("dev_id"));
+        let module_addr = HeaderLayout { module_addr: BitField::new(0, 9), module_id: BitField::new(9, 4), ..HeaderLayout::default() };
+        assert!(too_wide(module_addr).contains("module_addr"));
+        let prm_type = HeaderLayout { prm_id: BitField::new(0, 6), alarms: BitField::new(6, 1), prm_type: BitField::new(7, 9), ..HeaderLayout::default() };
+        assert!(too_wide(prm_type).contains("prm_type"));
+
+        let overflow = HeaderLayout { src_id: BitField::new(12, 5), ..HeaderLayout::default() };
+        assert!(overflow.validate().is_err());
+        let mcu = HeaderLayout { mcu_width: 7, ..HeaderLayout::default() };
+        assert!(mcu.validate().is_err());
+
+        let mut config = legacy_config("legacy");
+        config.profiles[0].layout.alarms = BitField::new(10, 9);
+        assert!(ProtocolSelector::from_config(&config, "dump").is_err());
+    }
+
+    #[test]
+    fn auto_detection() {
+        let registry = PackageTypeRegistry::default();
+        let mut selector = ProtocolSelector::from_config(&legacy_config(AUTO_PROFILE), "dump").unwrap();
+        // До первых кадров лидирует стандартный профиль
+        assert_eq!(selector.current().name, STANDARD_PROFILE);
+
+        // Кадр стандартного профиля, затем кадры старого поколения
+        selector.observe(&package(CrcCheck::default(), (2 << 7) | (3 << 3) | 1, 2 << 11, 10), &registry);
+        for bm in 0..3 {
+            selector.observe(&package(legacy_crc(), (2 << 8) | (bm << 4) | 5, 2 << 11, 10), &registry);
+            assert_eq!(selector.current().name, if bm == 0 { STANDARD_PROFILE } else { "legacy" });
+        }
+        assert_eq!(selector.current().layout, legacy_layout());
+
+        // После выбора профиль больше не меняется
+        for _ in 0..8 {
+            selector.observe(&package(CrcCheck::default(), (2 << 7) | (3 << 3) | 1, 2 << 11, 10), &registry);
+        }
+        assert_eq!(selector.current().name, "legacy");
+    }
+
+    #[test]
+    fn detection_waits_for_matching_frames() {
+        let registry = PackageTypeRegistry::default();
+        let mut selector = ProtocolSelector::from_config(&legacy_config(AUTO_PROFILE), "dump").unwrap();
+        // Ни один профиль не принимает мусор - определение продолжается после окна
+        for _ in 0..6 {
+            selector.observe(&[0x01, 0x02, 0x03, 0x04], &registry);
+        }
+        selector.observe(&package(legacy_crc(), (2 << 8) | (7 << 4) | 5, 2 << 11, 10), &registry);
+        assert_eq!(selector.current().name, "legacy");
+        selector.observe(&package(CrcCheck::default(), 2 << 7, 2 << 11, 10), &registry);
+        assert_eq!(selector.current().name, "legacy");
+    }
+
+    #[test]
+    fn non_standard_layout_addresses() {
+        // BM и MCU разделяются по раскладке профиля во всех потребителях: хранилище, модули, события
+        let mut sorter = PSorter::new();
+        sorter.set_protocol(ProtocolSelector::from_config(&legacy_config("legacy"), "dump").unwrap());
+        let frame = test_frames::stuffed(package(legacy_crc(), (2 << 8) | (10 << 4) | 5, (2 << 11) | 3, (0b0001 << 10) | 10));
+        sorter.slot_input_package(&frame, |_, _| {});
+
+        assert_eq!(sorter.crc_correct_counter(), 1);
+        let snapshot = sorter.state_store().snapshot();
+        assert_eq!(snapshot.len(), 1);
+        let key = snapshot[0].key;
+        assert_eq!((key.module_addr, key.module_addr_bm, key.module_addr_mcu, key.dev_id), (0xA5, 10, 5, 3));
+        assert_eq!(sorter.stats().modules.keys().collect::<Vec<_>>(), ["BM10.MCU5"]);
+
+        let events = sorter.take_events();
+        assert_eq!(events.len(), 1);
+        let event = serde_json::to_value(&events[0]).unwrap();
+        assert_eq!(event["event"], "hw_alarm_set");
+        assert_eq!((event["module_addr_bm"].as_u64(), event["module_addr_mcu"].as_u64()), (Some(10), Some(5)));
+    }
+}
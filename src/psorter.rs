@@ -1,10 +1,39 @@
-use crate::include::{byte_stuffing, crc, linear11};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::time::Duration;
+use tracing::{debug, info, trace, warn};
 
-/// Тип колбэк-функции для обработки отсортированных пакетов
-pub type PackageCallback = dyn Fn(i32, &[u8]) + Send + Sync;
+use crate::alarm_engine::{AlarmEngine, Measurement, SharedAlarmEngine};
+use crate::channels::RecordSender;
+use crate::config::AlarmClassConfig;
+use crate::device_stats::{DeviceKey, DeviceStatsTable, SharedDeviceStats};
+use crate::events::Event;
+use crate::hw_alarms::{HwAlarmTracker, SharedHwAlarms};
+use crate::include::byte_stuffing;
+use crate::include::pmbus::VoutModeCache;
+use crate::influx_export::ExportTap;
+use crate::logging::RateLimiter;
+use crate::metrics::METRICS;
+use crate::package_parser::{self, ParseError};
+use crate::package_types::PackageTypeRegistry;
+use crate::param_dict::{Encoding, ParamDictionary};
+use crate::protocol::{CrcCheck, ProtocolSelector};
+use crate::quarantine::{Quarantine, RejectedFrame};
+use crate::record::FrameRecord;
+use crate::state_store::{ParamKey, SharedStateStore, StateStore};
+
+/// Минимальный интервал между предупреждениями об ошибках CRC и разбора
+const CRC_WARN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Тип пакета для диагностического маршрута (пакеты, не прошедшие разбор)
+pub const DIAGNOSTICS_PACK_TYPE: i32 = 6;
 
 /// Структура для хранения разобранных данных пакета
-#[derive(Debug)]
+/// Пакет с несколькими параметрами разбирается в несколько таких записей
+#[derive(Debug, Clone, Default)]
+#[allow(dead_code)] // Заголовок разбирается полностью, не все поля пока используются
 pub struct PackageStruct {
     pub addr: u16,                    // Адрес модуля
     pub module_addr: u8,              // Адрес модуля (7 бит)
@@ -24,30 +53,100 @@ pub struct PackageStruct {
     pub prm: u16,                     // Значение параметра
     pub prm_max: u16,                 // Максимальное значение параметра
     pub prm_min: u16,                 // Минимальное значение параметра
-    pub temperature: f32,             // Температура (преобразованная из Linear11)
-    pub temp_max: f32,                // Максимальная температура
-    pub temp_min: f32,                // Минимальная температура
+    pub value: f32,                   // Значение (преобразованное по словарю параметров)
+    pub value_max: f32,               // Максимальное значение
+    pub value_min: f32,               // Минимальное значение
+    pub value_text: Option<String>,   // Строка дескриптора, метка перечисления или список флагов
+}
+
+/// Результат разбора кадра без обновления счетчиков, хранилища и тревог (режим INSPECT)
+#[derive(Debug, Clone)]
+pub struct InspectedFrame {
+    pub destuffed: Vec<u8>,              // Кадр после байт-стаффинга
+    pub crc_ok: bool,                    // Корректен ли CRC
+    pub records: Vec<PackageStruct>,     // Записи параметров (при ошибке CRC - по возможно поврежденному заголовку)
+    pub route: Option<i32>,              // Маршрут (только для кадров с корректным CRC; не прошедшие разбор - DMonitor)
+    pub error: Option<String>,           // Ошибка разбора
+}
+
+/// Снимок счетчиков сортировщика для периодических отчетов
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SorterStats {
+    pub input_packages: u64,               // Всего принятых пакетов
+    pub crc_correct: u64,                  // Пакетов с корректным CRC
+    pub crc_incorrect: u64,                // Пакетов с некорректным CRC
+    pub routes: BTreeMap<String, u64>,     // Пакетов по маршрутам (мониторам)
+    pub modules: BTreeMap<String, u64>,    // Пакетов по модулям (BM/MCU)
+    pub parse_errors: BTreeMap<String, u64>, // Ошибок разбора по видам
+    pub crc_matches: BTreeMap<String, u64>,  // Подобранные варианты CRC для пакетов с ошибкой CRC
 }
 
 /// Сортировщик пакетов - анализирует входящие пакеты и распределяет их по типам
 pub struct PSorter {
+    source: String,                    // Имя источника пакетов (uart, dump, ...)
     input_package_counter: u32,        // Счетчик принятых пакетов
     crc_correct_counter: u32,          // Счетчик пакетов с корректным CRC
     crc_incorrect_counter: u32,        // Счетчик пакетов с некорректным CRC
-    send_pack_forTmon_counter: u32,    // Счетчик пакетов для TMonitor
+    send_pack_for_tmon_counter: u32,   // Счетчик пакетов для TMonitor
+    route_counters: BTreeMap<&'static str, u64>,  // Счетчики пакетов по маршрутам
+    module_counters: BTreeMap<(u8, u8), u64>,     // Счетчики пакетов по модулям (BM, MCU)
+    device_stats: SharedDeviceStats,              // Статистика по отдельным устройствам
+    state_store: SharedStateStore,                // Последние значения параметров
+    alarm_engine: SharedAlarmEngine,              // Пороговые тревоги
+    hw_alarms: SharedHwAlarms,                    // Аппаратные аварийные биты
+    param_dict: Arc<ParamDictionary>,             // Словарь параметров
+    vout_modes: VoutModeCache,                    // Режимы VOUT_MODE по устройствам
+    package_types: PackageTypeRegistry,           // Декодеры содержимого по типам пакетов
+    protocol: ProtocolSelector,                   // Профиль протокола источника
+    pending_events: Vec<Event>,                   // События, ожидающие публикации
+    parse_error_counters: BTreeMap<&'static str, u64>,  // Счетчики ошибок разбора по видам
+    crc_diagnostics: bool,                        // Подбор варианта CRC для пакетов с ошибкой CRC
+    crc_match_counters: BTreeMap<String, u64>,    // Счетчики подобранных вариантов CRC
+    quarantine: Quarantine,                       // Карантин отклоненных кадров
+    record_taps: Vec<RecordSender>,               // Копии декодированных записей (терминальный интерфейс, WebSocket)
+    export_tap: Option<ExportTap>,                // Записи для экспорта (InfluxDB)
+    pending_exports: Vec<FrameRecord>,            // Записи, ожидающие отправки в канал экспорта
+    crc_warn_limiter: RateLimiter,     // Ограничитель предупреждений об ошибках CRC
+    parse_warn_limiter: RateLimiter,   // Ограничитель предупреждений об ошибках разбора
 }
 
 impl PSorter {
     /// Создает новый сортировщик пакетов
     pub fn new() -> Self {
         Self {
+            source: String::from("unknown"),
             input_package_counter: 0,
             crc_correct_counter: 0,
             crc_incorrect_counter: 0,
-            send_pack_forTmon_counter: 0,
+            send_pack_for_tmon_counter: 0,
+            route_counters: BTreeMap::new(),
+            module_counters: BTreeMap::new(),
+            device_stats: DeviceStatsTable::shared(),
+            state_store: StateStore::shared(),
+            alarm_engine: AlarmEngine::shared(Vec::new()),
+            hw_alarms: HwAlarmTracker::shared_empty(),
+            param_dict: Arc::new(ParamDictionary::default()),
+            vout_modes: VoutModeCache::default(),
+            package_types: PackageTypeRegistry::default(),
+            protocol: ProtocolSelector::default(),
+            pending_events: Vec::new(),
+            parse_error_counters: BTreeMap::new(),
+            crc_diagnostics: false,
+            crc_match_counters: BTreeMap::new(),
+            quarantine: Quarantine::default(),
+            record_taps: Vec::new(),
+            export_tap: None,
+            pending_exports: Vec::new(),
+            crc_warn_limiter: RateLimiter::new(CRC_WARN_INTERVAL),
+            parse_warn_limiter: RateLimiter::new(CRC_WARN_INTERVAL),
         }
     }
     
+    /// Устанавливает имя источника пакетов
+    pub fn set_source(&mut self, source: &str) {
+        self.source = source.to_string();
+    }
+
     /// Возвращает количество принятых пакетов
     pub fn input_package_counter(&self) -> u32 {self.input_package_counter}
     
@@ -58,137 +157,459 @@ impl PSorter {
     pub fn crc_incorrect_counter(&self) -> u32 {self.crc_incorrect_counter}
     
     /// Возвращает количество пакетов отправленных в TMonitor
-    pub fn send_pack_forTmon_counter(&self) -> u32 {self.send_pack_forTmon_counter}
+    pub fn send_pack_for_tmon_counter(&self) -> u32 {self.send_pack_for_tmon_counter}
     
+    /// Возвращает разделяемую таблицу статистики устройств
+    pub fn device_stats(&self) -> SharedDeviceStats {
+        self.device_stats.clone()
+    }
+
+    /// Возвращает разделяемое хранилище последних значений параметров
+    pub fn state_store(&self) -> SharedStateStore {
+        self.state_store.clone()
+    }
+
+    /// Устанавливает классы пороговых тревог (сбрасывает текущие состояния)
+    pub fn set_alarm_classes(&mut self, classes: Vec<AlarmClassConfig>) {
+        *self.lock_alarm_engine() = AlarmEngine::new(classes);
+    }
+
+    /// Возвращает разделяемый движок пороговых тревог
+    pub fn alarm_engine(&self) -> SharedAlarmEngine {
+        self.alarm_engine.clone()
+    }
+
+    /// Устанавливает трекер аппаратных аварийных битов
+    pub fn set_hw_alarms(&mut self, tracker: HwAlarmTracker) {
+        *self.lock_hw_alarms() = tracker;
+    }
+
+    /// Возвращает разделяемый трекер аппаратных аварийных битов
+    pub fn hw_alarms(&self) -> SharedHwAlarms {
+        self.hw_alarms.clone()
+    }
+
+    /// Устанавливает словарь параметров
+    pub fn set_param_dict(&mut self, dictionary: ParamDictionary) {
+        self.param_dict = Arc::new(dictionary);
+    }
+
+    /// Устанавливает реестр типов пакетов
+    pub fn set_package_types(&mut self, registry: PackageTypeRegistry) {
+        self.package_types = registry;
+    }
+
+    /// Устанавливает выбор профиля протокола
+    pub fn set_protocol(&mut self, protocol: ProtocolSelector) {
+        self.protocol = protocol;
+    }
+
+    /// Включает подбор варианта CRC для пакетов с ошибкой CRC
+    pub fn set_crc_diagnostics(&mut self, enabled: bool) {
+        self.crc_diagnostics = enabled;
+    }
+
+    /// Устанавливает карантин отклоненных кадров
+    pub fn set_quarantine(&mut self, quarantine: Quarantine) {
+        self.quarantine = quarantine;
+    }
+
+    /// Добавляет канал для копий декодированных записей
+    pub fn add_record_tap(&mut self, tap: RecordSender) {
+        self.record_taps.push(tap);
+    }
+
+    /// Устанавливает канал экспорта: записи не отбрасываются, а копятся до take_exports
+    pub fn set_export_tap(&mut self, tap: ExportTap) {
+        self.export_tap = Some(tap);
+    }
+
+    /// Забирает записи для экспорта вместе с каналом; передаются они уже без блокировки сортировщика
+    pub fn take_exports(&mut self) -> Option<(ExportTap, Vec<FrameRecord>)> {
+        let tap = self.export_tap.clone()?;
+        Some((tap, std::mem::take(&mut self.pending_exports)))
+    }
+
+    /// Возвращает словарь параметров
+    pub fn param_dict(&self) -> Arc<ParamDictionary> {
+        self.param_dict.clone()
+    }
+
+    /// Возвращает способ проверки CRC текущего профиля протокола
+    pub fn crc_check(&self) -> CrcCheck {
+        self.protocol.current().crc
+    }
+
+    /// Забирает события, сформированные при обработке пакетов
+    pub fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.pending_events)
+    }
+
+    /// Возвращает снимок всех счетчиков сортировщика
+    pub fn stats(&self) -> SorterStats {
+        SorterStats {
+            input_packages: self.input_package_counter as u64,
+            crc_correct: self.crc_correct_counter as u64,
+            crc_incorrect: self.crc_incorrect_counter as u64,
+            routes: self.route_counters.iter()
+                .map(|(route, count)| (route.to_string(), *count))
+                .collect(),
+            modules: self.module_counters.iter()
+                .map(|((bm, mcu), count)| (format!("BM{}.MCU{}", bm, mcu), *count))
+                .collect(),
+            parse_errors: self.parse_error_counters.iter()
+                .map(|(kind, count)| (kind.to_string(), *count))
+                .collect(),
+            crc_matches: self.crc_match_counters.clone(),
+        }
+    }
+
     /// Основной метод обработки входящего пакета
     /// Принимает пакет и колбэк для отправки отсортированного пакета
-    pub fn slot_input_package<F>(&mut self, package: &[u8], callback: F) 
-where
-    F: Fn(i32, &[u8]) + Send + Sync,
-{
-    if package.is_empty() {
-        println!("PSorter: Empty package received");
-        return;
-    }
-
-    self.input_package_counter += 1;
-    println!("pSorter принял пакет: {}", hex::encode(package));
-
-    // Применяем байт-стаффинг для восстановления исходных данных
-    let pack_stuffed = byte_stuffing::byte_stuffing(package);
-
-    // Проверяем корректность CRC
-    let is_crc = self.crc_correct(&pack_stuffed);
-    if is_crc {
-        self.crc_correct_counter += 1;
-        
-        // Разбираем пакет в структуру
-        let pack_struct = self.make_package_struct(&pack_stuffed);
-        
-        // Отладочная информация о структуре пакета
-        println!("Parsed package - BM:{}, FPGA:{}, Type:0x{:04x}, PRM_ID:{}, PRM_TYPE:{}", 
-                 pack_struct.module_addr_bm, pack_struct.dev_id, 
-                 pack_struct.package_type, pack_struct.prm_id, pack_struct.prm_type);
-        
-        // Определяем тип пакета
-        let pack_type = self.package_identificator(&pack_struct);
-        
-        // Вызываем колбэк с типом пакета и данными
-        callback(pack_type, &pack_stuffed);
-        
-        // Логируем назначение пакета
-        match pack_type {
-            1 => {println!(">>> to TMonitor"); self.send_pack_forTmon_counter += 1},
-            2 => println!(">>> to SMonitor"),
-            3 => println!(">>> to PUMonitor"),
-            4 => println!(">>> to OMonitor"),
-            5 => println!(">>> to CMonitor"),
-            _ => println!(">>> to Overview"),
+    pub fn slot_input_package<F>(&mut self, package: &[u8], callback: F)
+    where
+        F: Fn(i32, &[u8]) + Send + Sync,
+    {
+        if package.is_empty() {
+            debug!("Empty package received");
+            return;
+        }
+
+        self.input_package_counter += 1;
+        trace!(package = %hex::encode(package), "Package received");
+
+        if byte_stuffing::has_invalid_escape(package) {
+            METRICS.framing_errors.inc(&[&self.source, "invalid_escape"]);
+        }
+
+        // Применяем байт-стаффинг для восстановления исходных данных
+        let pack_stuffed = byte_stuffing::byte_stuffing(package);
+
+        // Профиль протокола источника (при автоопределении - текущий лидер)
+        self.protocol.observe(&pack_stuffed, &self.package_types);
+        let profile = self.protocol.current();
+        let (layout, crc_check) = (profile.layout, profile.crc);
+
+        // Проверяем корректность CRC
+        let is_crc = crc_check.validate(&pack_stuffed);
+        if is_crc {
+            self.crc_correct_counter += 1;
+            METRICS.crc_checks.inc(&[&self.source, "ok"]);
+
+            // Разбираем пакет по типу; некорректные пакеты уходят в диагностику
+            let mut records = match package_parser::parse_package(&pack_stuffed, &self.package_types, &layout, crc_check.size()) {
+                Ok(records) => records,
+                Err(e) => {
+                    self.reject(e.kind(), e.to_string(), package, &pack_stuffed, crc_check, None);
+                    self.tap(&pack_stuffed, true, &[], Some(DIAGNOSTICS_PACK_TYPE), Some(e.to_string()));
+                    self.handle_parse_error(e, &pack_stuffed, callback);
+                    return;
+                }
+            };
+
+            // Каждый параметр пакета обновляет хранилище и тревоги
+            let now = Utc::now();
+            for record in records.iter_mut() {
+                self.process_record(record, now);
+            }
+
+            // Маршрут определяется по первой записи (разборщик не возвращает пустой список)
+            let Some(pack_struct) = records.first() else {
+                return;
+            };
+
+            // Определяем тип пакета
+            let pack_type = self.package_identificator(pack_struct);
+
+            // Вызываем колбэк с типом пакета и данными
+            callback(pack_type, &pack_stuffed);
+
+            if pack_type == 1 {
+                self.send_pack_for_tmon_counter += 1;
+            }
+            *self.route_counters.entry(route_name(pack_type)).or_insert(0) += 1;
+            *self.module_counters
+                .entry((pack_struct.module_addr_bm, pack_struct.module_addr_mcu))
+                .or_insert(0) += 1;
+            self.lock_device_stats().record_package(
+                self.device_key(pack_struct),
+                records.iter().map(|record| record.prm_id),
+            );
+            self.tap(&pack_stuffed, true, &records, Some(pack_type), None);
+            trace!(route = route_name(pack_type), "Package routed");
+        } else {
+            self.crc_incorrect_counter += 1;
+            METRICS.crc_checks.inc(&[&self.source, "fail"]);
+
+            // Если заголовок разбирается, относим ошибку к устройству из заголовка
+            // (сам заголовок тоже может быть поврежден)
+            let records = package_parser::parse_package(&pack_stuffed, &self.package_types, &layout, crc_check.size())
+                .unwrap_or_default();
+            if let Some(pack_struct) = records.first() {
+                self.lock_device_stats().record_crc_failure(self.device_key(pack_struct));
+            }
+            self.tap(&pack_stuffed, false, &records, None, None);
+            let crc_match = self.crc_diagnostics.then(|| self.diagnose_crc(&pack_stuffed));
+            self.reject("crc", String::from("CRC incorrect"), package, &pack_stuffed, crc_check, crc_match.clone());
+            if let Some(suppressed) = self.crc_warn_limiter.check() {
+                warn!(
+                    package = %hex::encode(package),
+                    expected = %crc_check,
+                    crc_match = crc_match.as_deref(),
+                    suppressed,
+                    "CRC incorrect for package"
+                );
+            }
+        }
+    }
+
+    /// Отправляет копии записей кадра в подключенные каналы (терминальный интерфейс, WebSocket)
+    /// и откладывает их для экспорта
+    fn tap(&mut self, pack_stuffed: &[u8], crc_ok: bool, records: &[PackageStruct], route: Option<i32>, error: Option<String>) {
+        if self.record_taps.is_empty() && self.export_tap.is_none() {
+            return;
+        }
+        let frame = InspectedFrame {
+            destuffed: pack_stuffed.to_vec(),
+            crc_ok,
+            records: records.to_vec(),
+            route,
+            error,
+        };
+        let records: Vec<&PackageStruct> = frame.records.iter().collect();
+        let frame_no = self.input_package_counter as usize;
+        for record in FrameRecord::from_frame(frame_no, Some(Utc::now()), &frame, &records, &self.param_dict) {
+            // Получатель не успевает или закрыт - запись пропускается, конвейер не ждет
+            for tap in &self.record_taps {
+                let _ = tap.try_send(record.clone());
+            }
+            if self.export_tap.is_some() {
+                self.pending_exports.push(record);
+            }
+        }
+    }
+
+    /// Отправляет отклоненный кадр в карантин (если он настроен)
+    fn reject(
+        &mut self,
+        reason: &'static str,
+        detail: String,
+        package: &[u8],
+        pack_stuffed: &[u8],
+        crc_check: CrcCheck,
+        crc_match: Option<String>,
+    ) {
+        if !self.quarantine.is_enabled() {
+            return;
         }
-    } else {
-        self.crc_incorrect_counter += 1;
-        println!("CRC incorrect for package");
-    }
-    }
-
-    /// Разбирает байтовый буфер в структурированные данные пакета
-    fn make_package_struct(&self, input_package: &[u8]) -> PackageStruct {
-        let mut ps = PackageStruct {
-            addr: 0,
-            module_addr: 0,
-            module_addr_mcu: 0,
-            module_addr_bm: 0,
-            module_id: 0,
-            package_type: 0,
-            src: 0,
-            dev_id: 0,
-            pwr_line: 0,
-            src_id: 0,
-            rtr: false,
-            data_type: 0,
-            prm_id: 0,
-            alarms: 0,
-            prm_type: 0,
-            prm: 0,
-            prm_max: 0,
-            prm_min: 0,
-            temperature: 0.0,
-            temp_max: 0.0,
-            temp_min: 0.0,
+
+        let crc_values = crc_check.values(pack_stuffed);
+        let frame = RejectedFrame {
+            timestamp: Utc::now(),
+            source: self.source.clone(),
+            reason,
+            detail,
+            raw: hex::encode(package),
+            destuffed: hex::encode(pack_stuffed),
+            crc: crc_check.to_string(),
+            crc_expected: crc_values.map(|(expected, _)| crc_check.format(expected)),
+            crc_computed: crc_values.map(|(_, computed)| crc_check.format(computed)),
+            crc_match,
         };
+        self.quarantine.record(&frame);
+    }
 
-        // Проверяем минимальную длину пакета
-        if input_package.len() < 14 {
-            return ps;
+    /// Разбирает кадр так же, как slot_input_package, но только возвращает результат
+    /// Профиль протокола и кэш VOUT_MODE обновляются, чтобы декодирование совпадало с конвейером
+    pub fn inspect_package(&mut self, package: &[u8]) -> InspectedFrame {
+        let destuffed = byte_stuffing::byte_stuffing(package);
+        self.protocol.observe(&destuffed, &self.package_types);
+        let profile = self.protocol.current();
+        let (layout, crc_check) = (profile.layout, profile.crc);
+
+        let crc_ok = crc_check.validate(&destuffed);
+        let (mut records, error) = match package_parser::parse_package(&destuffed, &self.package_types, &layout, crc_check.size()) {
+            Ok(records) => (records, None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+        // Значения кадра с ошибкой CRC не декодируются (в том числе не обновляют VOUT_MODE)
+        if crc_ok {
+            for record in records.iter_mut() {
+                self.decode_values(record);
+            }
         }
+        // Кадр с корректным CRC, не прошедший разбор, конвейер отправляет в DMonitor
+        let route = match records.first() {
+            _ if !crc_ok => None,
+            Some(record) => Some(self.package_identificator(record)),
+            None => error.is_some().then_some(DIAGNOSTICS_PACK_TYPE),
+        };
+
+        InspectedFrame { destuffed, crc_ok, records, route, error }
+    }
 
-        // Маски для извлечения битовых полей
-        let mask_1b = 0x0001;
-        let mask_2b = 0x0003;
-        let mask_3b = 0x0007;
-        let mask_4b = 0x000F;
-        let mask_5b = 0x001F;
-        let mask_7b = 0x007F;
-        let mask_10b = 0x03FF;
+    /// Подбирает варианты CRC и порядок байтов, с которыми сходится CRC пакета
+    /// Возвращает совпадения через запятую ("none", если совпадений нет)
+    fn diagnose_crc(&mut self, package: &[u8]) -> String {
+        let matches = CrcCheck::diagnose(package);
+        let crc_match = if matches.is_empty() {
+            String::from("none")
+        } else {
+            matches.iter().map(|check| check.to_string()).collect::<Vec<_>>().join(",")
+        };
 
-        // Извлекаем адрес (байты 0-1) - little endian
-        ps.addr = ((input_package[1] as u16) << 8) | (input_package[0] as u16);
-        ps.module_addr = (ps.addr & mask_7b) as u8;
-        ps.module_addr_mcu = (ps.module_addr as u16 & mask_3b) as u8;
-        ps.module_addr_bm = (ps.module_addr >> 3) as u8;
-        ps.module_id = ((ps.addr >> 7) & mask_4b) as u8;
+        METRICS.crc_diagnostics.inc(&[&self.source, &crc_match]);
+        let counter = self.crc_match_counters.entry(crc_match.clone()).or_insert(0);
+        if *counter == 0 && !matches.is_empty() {
+            info!(
+                source = %self.source,
+                profile = %self.protocol.current().name,
+                crc_match,
+                "CRC of a rejected package matches another variant"
+            );
+        }
+        *counter += 1;
+        crc_match
+    }
 
-        // Извлекаем тип пакета (байты 2-3)
-        ps.package_type = ((input_package[3] as u16) << 8) | (input_package[2] as u16);
+    /// Обрабатывает одну запись параметра: хранилище, пороги, аварийные биты
+    fn process_record(&mut self, ps: &mut PackageStruct, now: DateTime<Utc>) {
+        let descriptor = ps.value_text.is_some();
+        self.decode_values(ps);
 
-        // Извлекаем источник данных (байты 4-5)
-        ps.src = ((input_package[5] as u16) << 8) | (input_package[4] as u16);
-        ps.dev_id = (ps.src & mask_7b) as u8;
-        ps.pwr_line = ((ps.src >> 7) & mask_4b) as u8;
-        ps.src_id = ((ps.src >> 11) & mask_5b) as u8;
-        ps.rtr = ((ps.src >> 15) & mask_1b) != 0;
+        // Отладочная информация о структуре пакета
+        let param_dict = self.param_dict.clone();
+        let param = param_dict.get(ps.prm_id);
+        trace!(
+            bm = ps.module_addr_bm,
+            fpga = ps.dev_id,
+            package_type = ps.package_type,
+            prm_id = ps.prm_id,
+            prm_type = ps.prm_type,
+            param = param.map_or("", |def| def.name.as_str()),
+            value = ps.value,
+            unit = param.map_or("", |def| def.unit.as_str()),
+            "Package parsed"
+        );
 
-        // Извлекаем тип данных (байты 6-7)
-        ps.data_type = ((input_package[7] as u16) << 8) | (input_package[6] as u16);
-        ps.prm_id = ps.data_type & mask_10b;
-        ps.alarms = ((ps.data_type >> 10) & mask_4b) as u8;
-        ps.prm_type = ((ps.data_type >> 14) & mask_2b) as u8;
+        // Обновляем последнее значение параметра и проверяем пороги
+        // Нечисловое значение (например, LINEAR16 до ответа VOUT_MODE) не затирает последнее
+        // известное; строковые дескрипторы хранятся как текст
+        let key = ParamKey::of(ps);
+        let alarm = if ps.value.is_finite() || descriptor {
+            self.state_store.update(ps, param);
+            let measurement = Measurement {
+                key,
+                value: ps.value,
+                value_min: ps.value_min,
+                value_max: ps.value_max,
+            };
+            self.lock_alarm_engine().evaluate(measurement, now)
+        } else {
+            debug!(bm = ps.module_addr_bm, dev_id = ps.dev_id, prm_id = ps.prm_id, "Non-finite value not stored");
+            None
+        };
+        if let Some(alarm) = alarm {
+            warn!(
+                class = %alarm.class,
+                from = ?alarm.from,
+                to = ?alarm.to,
+                bm = key.module_addr_bm,
+                dev_id = key.dev_id,
+                prm_id = key.prm_id,
+                param = param.map_or("", |def| def.name.as_str()),
+                value = alarm.value,
+                threshold = alarm.threshold,
+                "Threshold alarm state changed"
+            );
+            self.pending_events.push(Event::ThresholdAlarm(alarm));
+        }
 
-        // Извлекаем значение параметра (байты 8-9)
-        ps.prm = ((input_package[9] as u16) << 8) | (input_package[8] as u16);
+        // Отслеживаем переходы аппаратных аварийных битов
+        // История сохраняется в файл по таймеру контроллера, а не здесь
+        let hw_events = self.lock_hw_alarms().observe(key, ps.alarms, now);
+        for event in hw_events {
+            info!(
+                event = %event.event,
+                name = %event.name,
+                bm = ps.module_addr_bm,
+                dev_id = key.dev_id,
+                prm_id = key.prm_id,
+                "Hardware alarm bit changed"
+            );
+            self.pending_events.push(Event::HardwareAlarm(event));
+        }
+    }
 
-        // Извлекаем максимальное значение параметра (байты 10-11)
-        ps.prm_max = ((input_package[11] as u16) << 8) | (input_package[10] as u16);
+    /// Формирует ключ устройства для статистики
+    fn device_key(&self, ps: &PackageStruct) -> DeviceKey {
+        DeviceKey {
+            source: self.source.clone(),
+            module_addr_bm: ps.module_addr_bm,
+            module_addr_mcu: ps.module_addr_mcu,
+            dev_id: ps.dev_id,
+        }
+    }
 
-        // Извлекаем минимальное значение параметра (байты 12-13)
-        ps.prm_min = ((input_package[13] as u16) << 8) | (input_package[12] as u16);
+    fn lock_alarm_engine(&self) -> std::sync::MutexGuard<'_, AlarmEngine> {
+        self.alarm_engine.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
 
-        // Преобразуем из формата Linear11 в float
-        ps.temperature = linear11::from_linear11_f(ps.prm);
-        ps.temp_max = linear11::from_linear11_f(ps.prm_max);
-        ps.temp_min = linear11::from_linear11_f(ps.prm_min);
+    fn lock_hw_alarms(&self) -> std::sync::MutexGuard<'_, HwAlarmTracker> {
+        self.hw_alarms.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
 
-        ps
+    fn lock_device_stats(&self) -> std::sync::MutexGuard<'_, DeviceStatsTable> {
+        self.device_stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Учитывает ошибку разбора пакета и отправляет пакет в диагностический маршрут
+    fn handle_parse_error<F>(&mut self, error: ParseError, package: &[u8], callback: F)
+    where
+        F: Fn(i32, &[u8]) + Send + Sync,
+    {
+        let kind = error.kind();
+        *self.parse_error_counters.entry(kind).or_insert(0) += 1;
+        METRICS.parse_errors.inc(&[&self.source, kind]);
+        if let Some(suppressed) = self.parse_warn_limiter.check() {
+            warn!(
+                package = %hex::encode(package),
+                kind,
+                suppressed,
+                "Package rejected: {}", error
+            );
+        }
+
+        callback(DIAGNOSTICS_PACK_TYPE, package);
+        *self.route_counters.entry(route_name(DIAGNOSTICS_PACK_TYPE)).or_insert(0) += 1;
+    }
+
+    /// Преобразует значения параметра по словарю параметров
+    fn decode_values(&mut self, ps: &mut PackageStruct) {
+        // Строковый дескриптор не имеет числового значения
+        if ps.value_text.is_some() {
+            ps.value = f32::NAN;
+            ps.value_max = f32::NAN;
+            ps.value_min = f32::NAN;
+            return;
+        }
+
+        // Ответ VOUT_MODE обновляет экспоненту LINEAR16 устройства
+        let device = (ps.module_addr, ps.dev_id);
+        if self.param_dict.get(ps.prm_id).is_some_and(|def| def.encoding == Encoding::VoutMode) {
+            match self.vout_modes.update(device, ps.prm as u8) {
+                Ok(mode) => debug!(bm = ps.module_addr_bm, dev_id = ps.dev_id, ?mode, "VOUT_MODE updated"),
+                Err(e) => debug!(bm = ps.module_addr_bm, dev_id = ps.dev_id, "Invalid VOUT_MODE: {}", e),
+            }
+        }
+
+        // Преобразуем значения по словарю параметров (по умолчанию Linear11)
+        let vout_exponent = self.vout_modes.exponent(device);
+        let decoded = self.param_dict.decode(ps.prm_id, ps.prm, vout_exponent);
+        ps.value = decoded.value as f32;
+        ps.value_text = decoded.text;
+        ps.value_max = self.param_dict.decode(ps.prm_id, ps.prm_max, vout_exponent).value as f32;
+        ps.value_min = self.param_dict.decode(ps.prm_id, ps.prm_min, vout_exponent).value as f32;
     }
 
     /// Определяет тип пакета на основе его структуры
@@ -197,63 +618,147 @@ where
         let max_bm = 15;
 
         // Пакеты температуры
-        if ps.module_addr_mcu == 1 || ps.module_addr_mcu == 2 {
-            if ps.module_id == 2 { // BM module
-                if ps.module_addr_bm >= min_bm && ps.module_addr_bm <= max_bm {
-                    if ps.package_type == 32768 { // 0x8000
-                        if ps.prm_type == 0 || ps.prm_type == 1 || ps.prm_type == 2 { // Значение параметра (не min/max)
-                            if ps.src_id == 2 || ps.src_id == 3 { // FPGA
-                                if ps.prm_id == 10 || ps.prm_id == 11 || ps.prm_id == 12 {
-                                    if ps.dev_id >= 1 && ps.dev_id <= 6 {
-                                        println!("Identified as TEMPERATURE package: BM{}, FPGA{}, PRM_ID: {}", 
-                                                 ps.module_addr_bm, ps.dev_id, ps.prm_id);
-                                        return 1; // Данные температуры
-                                    } else {
-                                        return 5; // Данные управления
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+        if (ps.module_addr_mcu == 1 || ps.module_addr_mcu == 2)
+            && ps.module_id == 2 // BM module
+            && (min_bm..=max_bm).contains(&ps.module_addr_bm)
+            && ps.package_type == 32768 // 0x8000
+            && (ps.prm_type == 0 || ps.prm_type == 1 || ps.prm_type == 2) // Значение параметра (не min/max)
+            && (ps.src_id == 2 || ps.src_id == 3) // FPGA
+            && (ps.prm_id == 10 || ps.prm_id == 11 || ps.prm_id == 12)
+        {
+            if ps.dev_id >= 1 && ps.dev_id <= 6 {
+                trace!(bm = ps.module_addr_bm, fpga = ps.dev_id, prm_id = ps.prm_id,
+                       "Identified as TEMPERATURE package");
+                return 1; // Данные температуры
+            } else {
+                return 5; // Данные управления
             }
         }
 
         // Системные пакеты
         if ps.package_type == 0x8000 && ps.prm_id == 20 {
-            println!("Identified as SYSTEM package");
+            trace!("Identified as SYSTEM package");
             return 2;
         }
 
         // Пакеты энергопотребления
         if ps.package_type == 0x8000 && ps.prm_id == 30 {
-            println!("Identified as POWER USAGE package");
+            trace!("Identified as POWER USAGE package");
             return 3;
         }
 
         // Пакеты управления
         if ps.dev_id > 6 || ps.module_addr_bm > max_bm {
-            println!("Identified as CONTROL package");
+            trace!("Identified as CONTROL package");
             return 5;
         }
 
         // По умолчанию - обзорные пакеты
-        println!("Identified as OVERVIEW package - BM:{}, FPGA:{}, Type:{}, PRM_ID:{}", 
-                 ps.module_addr_bm, ps.dev_id, ps.package_type, ps.prm_id);
+        trace!(bm = ps.module_addr_bm, fpga = ps.dev_id, package_type = ps.package_type,
+               prm_id = ps.prm_id, "Identified as OVERVIEW package");
         4
     }
+}
 
-    /// Проверяет корректность CRC пакета
-    fn crc_correct(&self, byte_buffer: &[u8]) -> bool {
-        if byte_buffer.len() < 2 {
-            return false;
-        }
-        
-        // Извлекаем CRC из пакета (последние 2 байта)
-        let crc_received = ((byte_buffer[byte_buffer.len() - 2] as u16) << 8) 
-            | (byte_buffer[byte_buffer.len() - 1] as u16);
+/// Возвращает имя монитора-получателя для типа пакета
+pub fn route_name(pack_type: i32) -> &'static str {
+    match pack_type {
+        1 => "TMonitor",
+        2 => "SMonitor",
+        3 => "PUMonitor",
+        4 => "OMonitor",
+        5 => "CMonitor",
+        DIAGNOSTICS_PACK_TYPE => "DMonitor",
+        _ => "Overview",
+    }
+}
 
-        // Проверяем CRC для данных без последних 2 байт (самого CRC)
-        crc::crc16_validate(&byte_buffer[0..byte_buffer.len() - 2], crc_received)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::CrcCheck;
+    use crate::test_frames::{self, addr, src};
+
+    /// Кадр без разделителя: заголовок, содержимое, CRC и байт-стаффинг
+    fn frame(addr: u16, package_type: u16, src: u16, data_type: u16, payload: &[u8]) -> Vec<u8> {
+        test_frames::stuffed(test_frames::package(CrcCheck::default(), [addr, package_type, src, data_type], payload))
+    }
+
+    /// Кадр температуры FPGA (тип 0x8000, значения prm, prm_max, prm_min)
+    fn temperature(bm: u16, dev_id: u16) -> Vec<u8> {
+        frame(addr(2, bm, 1), 0x8000, src(2, dev_id), 10, &test_frames::words(&test_frames::VALUES))
+    }
+
+    #[test]
+    fn crc_failure_attributed_to_header_device() {
+        let mut sorter = PSorter::new();
+        sorter.set_source("dump");
+        sorter.slot_input_package(&temperature(3, 2), |_, _| {});
+
+        let mut corrupted = temperature(3, 2);
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 0x01;
+        sorter.slot_input_package(&corrupted, |_, _| {});
+        // Заголовок не разбирается - кадр не относится ни к одному устройству
+        sorter.slot_input_package(&[0x01, 0x02, 0x03], |_, _| {});
+
+        assert_eq!(sorter.crc_correct_counter(), 1);
+        assert_eq!(sorter.crc_incorrect_counter(), 2);
+        let devices = sorter.device_stats().lock().unwrap().entries();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].key, DeviceKey {
+            source: String::from("dump"),
+            module_addr_bm: 3,
+            module_addr_mcu: 1,
+            dev_id: 2,
+        });
+        assert_eq!(devices[0].packages, 1);
+        assert_eq!(devices[0].crc_failures, 1);
+        assert_eq!(devices[0].parameters, [10]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn parse_errors_routed_to_diagnostics() {
+        let mut sorter = PSorter::new();
+        let routes = std::sync::Mutex::new(Vec::new());
+        let callback = |route: i32, _: &[u8]| routes.lock().unwrap().push(route);
+
+        // Два байта с совпадающим CRC пустого пакета - слишком короткий пакет
+        let mut short = Vec::new();
+        CrcCheck::default().append(&mut short);
+        byte_stuffing::request_byte_stuffing(&mut short);
+        sorter.slot_input_package(&short, callback);
+        // Неизвестный тип пакета и зарезервированные биты адреса
+        sorter.slot_input_package(&frame(addr(2, 3, 1), 0x1234, src(2, 2), 10, &[0; 6]), callback);
+        sorter.slot_input_package(&frame(0x0800 | addr(2, 3, 1), 0x8000, src(2, 2), 10, &[0; 6]), callback);
+        sorter.slot_input_package(&temperature(3, 2), callback);
+
+        assert_eq!(*routes.lock().unwrap(), [DIAGNOSTICS_PACK_TYPE, DIAGNOSTICS_PACK_TYPE, DIAGNOSTICS_PACK_TYPE, 1]);
+        let stats = sorter.stats();
+        assert_eq!(stats.crc_correct, 4);
+        assert_eq!(stats.routes["DMonitor"], 3);
+        assert_eq!(stats.routes["TMonitor"], 1);
+        assert!(!stats.routes.contains_key("OMonitor"));
+        assert_eq!(stats.parse_errors, BTreeMap::from([
+            (String::from("reserved_bits"), 1),
+            (String::from("too_short"), 1),
+            (String::from("unknown_package_type"), 1),
+        ]));
+        // Пакеты, не прошедшие разбор, не относятся к модулям
+        assert_eq!(stats.modules, BTreeMap::from([(String::from("BM3.MCU1"), 1)]));
+    }
+
+    #[test]
+    fn non_finite_value_not_stored() {
+        let path = std::env::temp_dir().join(format!("hwmon-psorter-dict-{}.csv", std::process::id()));
+        std::fs::write(&path, "prm_id,name,unit,encoding\n10,vout,V,linear16\n").unwrap();
+        let mut sorter = PSorter::new();
+        sorter.set_param_dict(ParamDictionary::load(path.to_str().unwrap()).unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        // VOUT_MODE устройства неизвестен - значение NaN, в хранилище не попадает
+        sorter.slot_input_package(&temperature(3, 2), |_, _| {});
+        assert_eq!(sorter.crc_correct_counter(), 1);
+        assert!(sorter.state_store().snapshot().is_empty());
+    }
+}
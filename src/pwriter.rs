@@ -1,34 +1,33 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use tracing::{debug, trace};
 
-use crate::channels::CommandSender;
 use crate::uart::Uart;
 
 /// Структура для записи команд и данных через UART
 pub struct PWriter {
-    uart: Arc<Mutex<Uart>>,           // Защищенный доступ к UART
-    command_sender: CommandSender,    // Канал для отправки команд
+    uart: Arc<Mutex<Uart>>,           // Защищенный доступ к UART (общий с PReader)
 }
 
 impl PWriter {
     /// Создает новый экземпляр писателя пакетов
-    pub fn new(uart: Arc<Mutex<Uart>>, command_sender: CommandSender) -> Self {
-        println!("PWriter initialized with UART");
-        Self { uart, command_sender }
+    pub fn new(uart: Arc<Mutex<Uart>>) -> Self {
+        debug!("PWriter initialized with UART");
+        Self { uart }
     }
 
     /// Записывает команду непосредственно в UART
-    pub fn write_command(&self, command: &[u8]) -> Result<()> {
+    /// Ждет, пока читатель освободит порт (не дольше одного таймаута чтения)
+    pub async fn write_command(&self, command: &[u8]) -> Result<()> {
         if command.is_empty() {
             return Err(anyhow::anyhow!("Empty command received"));
         }
 
-        println!("PWriter: Preparing to write command, size: {}", command.len());
+        trace!(size = command.len(), "PWriter: Preparing to write command");
 
         // Получаем доступ к UART
-        let mut uart_guard = self.uart.try_lock()
-            .context("Failed to lock UART for writing")?;
+        let mut uart_guard = self.uart.lock().await;
 
         // Проверяем, что порт открыт
         if !uart_guard.is_open() {
@@ -37,15 +36,8 @@ impl PWriter {
 
         // Записываем команду в порт
         uart_guard.write_all(command)?;
-        println!("Successfully written {} bytes to UART", command.len());
-        println!("Command data: {}", hex::encode(command));
+        debug!(size = command.len(), command = %hex::encode(command), "Command written to UART");
 
         Ok(())
     }
-
-    /// Отправляет команду через канал для асинхронной обработки
-    pub fn send_command(&self, command: Vec<u8>) -> Result<()> {
-        self.command_sender.send(command)
-            .map_err(|e| anyhow::anyhow!("Failed to send command: {}", e))
-    }
-}
\ No newline at end of file
+}
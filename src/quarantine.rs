@@ -0,0 +1,265 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, trace, warn};
+
+use crate::config::QuarantineConfig;
+use crate::logging::RateLimiter;
+use crate::metrics::METRICS;
+use crate::zmq_sender::ZmqSender;
+
+/// Имя маршрута карантина для метрик и логов
+pub const QUARANTINE_ROUTE: &str = "Quarantine";
+
+/// Минимальный интервал между предупреждениями об ошибках записи карантина
+const WRITE_WARN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Отклоненный кадр: ошибка CRC или ошибка разбора
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectedFrame {
+    pub timestamp: DateTime<Utc>,        // Время приема
+    pub source: String,                  // Источник пакетов (uart, dump, ...)
+    pub reason: &'static str,            // Причина: "crc" или вид ошибки разбора
+    pub detail: String,                  // Описание причины
+    pub raw: String,                     // Кадр как принят (hex, до байт-стаффинга)
+    pub destuffed: String,               // Кадр после байт-стаффинга (hex)
+    pub crc: String,                     // Проверка CRC профиля (вариант/порядок байтов)
+    pub crc_expected: Option<String>,    // CRC из кадра
+    pub crc_computed: Option<String>,    // CRC, вычисленный по данным кадра
+    pub crc_match: Option<String>,       // Подобранные варианты CRC (при диагностике)
+}
+
+/// Файл JSON Lines с ротацией по размеру: path, path.1, ..., path.<max_files>
+struct RotatingFile {
+    path: PathBuf,               // Текущий файл
+    max_bytes: u64,              // Размер, после которого файл ротируется
+    max_files: usize,            // Количество хранимых старых файлов
+    file: Option<File>,          // Открытый текущий файл
+    written: u64,                // Размер текущего файла
+}
+
+impl RotatingFile {
+    fn new(path: PathBuf, max_bytes: u64, max_files: usize) -> Self {
+        Self { path, max_bytes, max_files, file: None, written: 0 }
+    }
+
+    /// Дописывает строку, при превышении размера предварительно ротирует файлы
+    fn write_line(&mut self, line: &[u8]) -> Result<()> {
+        if self.file.is_some() && self.written + line.len() as u64 + 1 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        if self.file.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .context(format!("Failed to open quarantine file: {}", self.path.display()))?;
+            self.written = file.metadata().map_or(0, |metadata| metadata.len());
+            self.file = Some(file);
+        }
+
+        let file = self.file.as_mut().expect("quarantine file is open");
+        file.write_all(line)?;
+        file.write_all(b"\n")?;
+        self.written += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    /// Сдвигает старые файлы (path.N-1 -> path.N) и начинает новый
+    fn rotate(&mut self) -> Result<()> {
+        self.file = None;
+        if self.max_files == 0 {
+            fs::remove_file(&self.path).ok();
+            return Ok(());
+        }
+
+        for index in (1..self.max_files).rev() {
+            let from = Self::numbered(&self.path, index);
+            if from.exists() {
+                fs::rename(&from, Self::numbered(&self.path, index + 1))?;
+            }
+        }
+        fs::rename(&self.path, Self::numbered(&self.path, 1))
+            .context(format!("Failed to rotate quarantine file: {}", self.path.display()))?;
+        Ok(())
+    }
+
+    /// Имя старого файла с номером
+    fn numbered(path: &Path, index: usize) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+}
+
+/// Карантин отклоненных кадров: ZMQ маршрут (JSON) и/или файл с ротацией
+/// Без настроенных приемников кадры не сохраняются
+pub struct Quarantine {
+    sender: Option<ZmqSender>,         // ZMQ отправитель маршрута карантина
+    file: Option<RotatingFile>,        // Файл карантина
+    warn_limiter: RateLimiter,         // Ограничитель предупреждений об ошибках записи
+}
+
+impl Default for Quarantine {
+    /// Карантин без приемников
+    fn default() -> Self {
+        Self {
+            sender: None,
+            file: None,
+            warn_limiter: RateLimiter::new(WRITE_WARN_INTERVAL),
+        }
+    }
+}
+
+impl Quarantine {
+    /// Создает карантин по конфигурации (пустая строка отключает приемник)
+    pub fn from_config(config: &QuarantineConfig) -> Result<Self> {
+        let endpoint = config.endpoint.as_ref().filter(|endpoint| !endpoint.is_empty());
+        let file = config.file.as_ref().filter(|path| !path.is_empty());
+        if file.is_some() && config.max_file_bytes == 0 {
+            return Err(anyhow::anyhow!("quarantine.max_file_bytes must be positive"));
+        }
+
+        if let Some(path) = file {
+            info!(path = %path, max_file_bytes = config.max_file_bytes, max_files = config.max_files,
+                  "Quarantine file enabled");
+        }
+        Ok(Self {
+            sender: endpoint.map(|endpoint| ZmqSender::new(endpoint)),
+            file: file.map(|path| RotatingFile::new(PathBuf::from(path), config.max_file_bytes, config.max_files)),
+            warn_limiter: RateLimiter::new(WRITE_WARN_INTERVAL),
+        })
+    }
+
+    /// Есть ли хотя бы один приемник
+    pub fn is_enabled(&self) -> bool {
+        self.sender.is_some() || self.file.is_some()
+    }
+
+    /// Сохраняет отклоненный кадр во все приемники
+    pub fn record(&mut self, frame: &RejectedFrame) {
+        let line = match serde_json::to_vec(frame) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::error!("Failed to serialize rejected frame: {}", e);
+                return;
+            }
+        };
+
+        if let Some(sender) = &self.sender {
+            // Ошибки отправки логируются в ZmqSender с ограничением частоты
+            if sender.send_package(&line).is_ok() {
+                METRICS.route_sent.inc(&[QUARANTINE_ROUTE]);
+            } else {
+                METRICS.route_failed.inc(&[QUARANTINE_ROUTE]);
+            }
+        }
+
+        if let Some(file) = &mut self.file {
+            if let Err(e) = file.write_line(&line) {
+                // Файл будет открыт заново при следующей записи
+                file.file = None;
+                if let Some(suppressed) = self.warn_limiter.check() {
+                    warn!(path = %file.path.display(), suppressed, "Failed to write quarantine file: {:#}", e);
+                }
+            }
+        }
+        trace!(reason = frame.reason, "Frame quarantined");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::CrcCheck;
+    use crate::psorter::PSorter;
+
+    /// Временный каталог теста (удаляется вызывающим)
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hwmon-quarantine-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read(path: &Path) -> Option<String> {
+        fs::read_to_string(path).ok()
+    }
+
+    #[test]
+    fn rotation_order() {
+        let dir = temp_dir("rotation");
+        let path = dir.join("rejected.jsonl");
+        // Каждая строка (5 байт и перевод строки) занимает отдельный файл
+        let mut file = RotatingFile::new(path.clone(), 10, 2);
+        for line in ["line1", "line2", "line3", "line4"] {
+            file.write_line(line.as_bytes()).unwrap();
+        }
+
+        assert_eq!(read(&path).as_deref(), Some("line4\n"));
+        assert_eq!(read(&dir.join("rejected.jsonl.1")).as_deref(), Some("line3\n"));
+        assert_eq!(read(&dir.join("rejected.jsonl.2")).as_deref(), Some("line2\n"));
+        assert!(!dir.join("rejected.jsonl.3").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn no_old_files_kept() {
+        let dir = temp_dir("no-old");
+        let path = dir.join("rejected.jsonl");
+        // Существующий файл дописывается, его размер учитывается при ротации
+        fs::write(&path, "old\n").unwrap();
+        let mut file = RotatingFile::new(path.clone(), 12, 0);
+        file.write_line(b"line1").unwrap();
+        assert_eq!(read(&path).as_deref(), Some("old\nline1\n"));
+        file.write_line(b"line2").unwrap();
+
+        assert_eq!(read(&path).as_deref(), Some("line2\n"));
+        assert!(!dir.join("rejected.jsonl.1").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejected_frame_crc_fields() {
+        let dir = temp_dir("frames");
+        let path = dir.join("rejected.jsonl");
+        let config = QuarantineConfig {
+            endpoint: Some(String::new()),
+            file: Some(path.to_str().unwrap().to_string()),
+            ..QuarantineConfig::default()
+        };
+        let mut sorter = PSorter::new();
+        sorter.set_source("dump");
+        sorter.set_quarantine(Quarantine::from_config(&config).unwrap());
+
+        // CRC-16/CCITT-FALSE строки "123456789" - 0x29B1, в кадре испорчен младший бит
+        let mut corrupted = b"123456789".to_vec();
+        corrupted.extend_from_slice(&[0x29, 0xB0]);
+        sorter.slot_input_package(&corrupted, |_, _| {});
+        // Два байта с корректным CRC пустого пакета - ошибка разбора
+        sorter.slot_input_package(&[0xFF, 0xFF], |_, _| {});
+
+        let lines: Vec<serde_json::Value> = read(&path).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["source"], "dump");
+        assert_eq!(lines[0]["reason"], "crc");
+        assert_eq!(lines[0]["raw"], hex::encode(&corrupted));
+        assert_eq!(lines[0]["crc"], CrcCheck::default().to_string());
+        assert_eq!(lines[0]["crc_expected"], "0x29b0");
+        assert_eq!(lines[0]["crc_computed"], "0x29b1");
+        assert_eq!(lines[0]["crc_match"], serde_json::Value::Null);
+
+        assert_eq!(lines[1]["reason"], "too_short");
+        assert_eq!(lines[1]["crc_expected"], "0xffff");
+        assert_eq!(lines[1]["crc_computed"], "0xffff");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,103 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::param_dict::ParamDictionary;
+use crate::psorter::{route_name, InspectedFrame, PackageStruct};
+
+/// Запись кадра для JSON-строк и CSV: запись параметра или кадр, не прошедший разбор
+/// Поля заголовка пусты, если кадр не разобран
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FrameRecord {
+    pub frame: usize,                        // Номер кадра (с 1)
+    pub timestamp: Option<DateTime<Utc>>,    // Время приема, если известно
+    pub crc: String,                         // ok / fail
+    pub route: Option<String>,               // Маршрут
+    pub addr: Option<u16>,                   // Слова заголовка как в пакете
+    pub package_type: Option<u16>,
+    pub src: Option<u16>,
+    pub data_type: Option<u16>,
+    pub bm: Option<u8>,
+    pub mcu: Option<u8>,
+    pub module_id: Option<u8>,
+    pub dev_id: Option<u8>,
+    pub src_id: Option<u8>,
+    pub rtr: Option<bool>,
+    pub prm_id: Option<u16>,
+    pub prm_type: Option<u8>,
+    pub alarms: Option<u8>,
+    pub prm: Option<u16>,                    // Сырые значения
+    pub prm_max: Option<u16>,
+    pub prm_min: Option<u16>,
+    pub value: Option<f32>,                  // Значения по словарю параметров
+    pub value_max: Option<f32>,
+    pub value_min: Option<f32>,
+    pub text: Option<String>,                // Строка дескриптора или метка
+    pub param: Option<String>,               // Имя параметра из словаря
+    pub error: Option<String>,               // Ошибка разбора
+    pub destuffed: String,                   // Кадр после байт-стаффинга (hex)
+}
+
+/// Ключ устройства в сводке: (BM, MCU, dev_id)
+pub type DeviceKey = (u8, u8, u8);
+
+impl FrameRecord {
+    /// Записи кадра: по одной на запись параметра или одна запись кадра, не прошедшего разбор
+    pub fn from_frame(
+        frame_no: usize,
+        timestamp: Option<DateTime<Utc>>,
+        frame: &InspectedFrame,
+        records: &[&PackageStruct],
+        param_dict: &ParamDictionary,
+    ) -> Vec<Self> {
+        let base = Self {
+            frame: frame_no,
+            timestamp,
+            crc: String::from(if frame.crc_ok { "ok" } else { "fail" }),
+            route: frame.route.map(|pack_type| route_name(pack_type).to_string()),
+            destuffed: hex::encode(&frame.destuffed),
+            ..Self::default()
+        };
+        if records.is_empty() {
+            return vec![Self { error: frame.error.clone(), ..base }];
+        }
+
+        // Значения кадра с ошибкой CRC не декодируются
+        let decoded = |value: f32| (frame.crc_ok && value.is_finite()).then_some(value);
+        records.iter()
+            .map(|record| Self {
+                addr: Some(record.addr),
+                package_type: Some(record.package_type),
+                src: Some(record.src),
+                data_type: Some(record.data_type),
+                bm: Some(record.module_addr_bm),
+                mcu: Some(record.module_addr_mcu),
+                module_id: Some(record.module_id),
+                dev_id: Some(record.dev_id),
+                src_id: Some(record.src_id),
+                rtr: Some(record.rtr),
+                prm_id: Some(record.prm_id),
+                prm_type: Some(record.prm_type),
+                alarms: Some(record.alarms),
+                prm: Some(record.prm),
+                prm_max: Some(record.prm_max),
+                prm_min: Some(record.prm_min),
+                value: decoded(record.value),
+                value_max: decoded(record.value_max),
+                value_min: decoded(record.value_min),
+                text: record.value_text.clone(),
+                param: param_dict.get(record.prm_id).map(|def| def.name.clone()),
+                ..base.clone()
+            })
+            .collect()
+    }
+}
+
+/// Имя устройства в сводке
+pub fn device_name((bm, mcu, dev_id): DeviceKey) -> String {
+    format!("BM{}.MCU{}.DEV{}", bm, mcu, dev_id)
+}
+
+/// Имя параметра устройства в сводке
+pub fn param_name(device: DeviceKey, prm_id: u16) -> String {
+    format!("{}.prm{}", device_name(device), prm_id)
+}
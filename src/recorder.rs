@@ -0,0 +1,108 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+
+use crate::convert::{self, CaptureFormat, CapturedFrame};
+
+/// Разделяемый записыватель захвата (обработчик пакетов пишет, HTTP управляет)
+pub type SharedRecorder = Arc<Mutex<Recorder>>;
+
+/// Состояние записи
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingStatus {
+    pub path: String,                // Файл захвата
+    pub format: String,              // Формат захвата
+    pub frames: u64,                 // Записано кадров
+    pub started: DateTime<Utc>,      // Время начала записи
+}
+
+/// Активная запись
+struct Recording {
+    status: RecordingStatus,
+    format: CaptureFormat,
+    out: BufWriter<File>,
+}
+
+/// Запись принимаемых кадров в файл захвата (raw, capture, pcapng или hex)
+/// Кадры пишутся как в потоке, до разбора, с временем приема
+#[derive(Default)]
+pub struct Recorder {
+    active: Option<Recording>,
+}
+
+/// Путь файла захвата внутри каталога записей; каталог создается при необходимости
+/// Абсолютные пути и выход из каталога (..) отклоняются
+pub fn capture_path(directory: &str, name: &str) -> Result<PathBuf> {
+    let relative = Path::new(name);
+    let plain = relative.components().all(|component| matches!(component, Component::Normal(_)));
+    if name.is_empty() || !plain {
+        return Err(anyhow::anyhow!("Capture file must be a relative path inside the recording directory: {}", name));
+    }
+    std::fs::create_dir_all(directory)
+        .context(format!("Failed to create recording directory: {}", directory))?;
+    Ok(Path::new(directory).join(relative))
+}
+
+impl Recorder {
+    /// Создает разделяемый записыватель без активной записи
+    pub fn shared() -> SharedRecorder {
+        Arc::new(Mutex::new(Self::default()))
+    }
+
+    /// Начинает запись в файл (перезаписывает существующий)
+    pub fn start(&mut self, path: &str, format: CaptureFormat) -> Result<RecordingStatus> {
+        if let Some(recording) = &self.active {
+            return Err(anyhow::anyhow!("Recording already active: {}", recording.status.path));
+        }
+
+        let file = File::create(path).context(format!("Failed to create capture file: {}", path))?;
+        let mut out = BufWriter::new(file);
+        convert::write_header(&mut out, format)?;
+
+        let status = RecordingStatus {
+            path: path.to_string(),
+            format: format!("{:?}", format).to_lowercase(),
+            frames: 0,
+            started: Utc::now(),
+        };
+        info!(path, ?format, "Recording started");
+        self.active = Some(Recording { status: status.clone(), format, out });
+        Ok(status)
+    }
+
+    /// Завершает запись; возвращает ее итог или None, если запись не велась
+    pub fn stop(&mut self) -> Result<Option<RecordingStatus>> {
+        let Some(mut recording) = self.active.take() else {
+            return Ok(None);
+        };
+        recording.out.flush()
+            .context(format!("Failed to write capture file: {}", recording.status.path))?;
+        info!(path = %recording.status.path, frames = recording.status.frames, "Recording stopped");
+        Ok(Some(recording.status))
+    }
+
+    /// Состояние активной записи
+    pub fn status(&self) -> Option<RecordingStatus> {
+        self.active.as_ref().map(|recording| recording.status.clone())
+    }
+
+    /// Записывает принятый кадр; при ошибке записи запись прекращается
+    pub fn record(&mut self, frame: &[u8]) {
+        let Some(recording) = &mut self.active else {
+            return;
+        };
+        let captured = CapturedFrame { timestamp: Some(Utc::now()), frame: frame.to_vec() };
+        match convert::write_frame(&mut recording.out, recording.format, &captured) {
+            Ok(()) => recording.status.frames += 1,
+            Err(e) => {
+                warn!(path = %recording.status.path, "Recording stopped after write error: {}", e);
+                self.active = None;
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::param_dict::ParamDef;
+use crate::psorter::PackageStruct;
+
+/// Коэффициент сглаживания для оценки интервала обновления
+const INTERVAL_EWMA_ALPHA: f64 = 0.2;
+
+/// Разделяемое хранилище последних значений параметров
+pub type SharedStateStore = Arc<StateStore>;
+
+/// Ключ параметра: адрес модуля, устройство, источник и идентификатор параметра
+/// BM и MCU разделяются по раскладке заголовка источника при разборе пакета
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ParamKey {
+    pub module_addr: u8,         // Адрес модуля (BM и MCU)
+    pub module_addr_bm: u8,      // Адрес BM
+    pub module_addr_mcu: u8,     // Адрес MCU
+    pub dev_id: u8,              // Идентификатор устройства (7 бит)
+    pub src_id: u8,              // Идентификатор источника (5 бит)
+    pub prm_id: u16,             // Идентификатор параметра (10 бит)
+}
+
+impl ParamKey {
+    /// Ключ параметра из разобранного пакета
+    pub fn of(ps: &PackageStruct) -> Self {
+        Self {
+            module_addr: ps.module_addr,
+            module_addr_bm: ps.module_addr_bm,
+            module_addr_mcu: ps.module_addr_mcu,
+            dev_id: ps.dev_id,
+            src_id: ps.src_id,
+            prm_id: ps.prm_id,
+        }
+    }
+}
+
+/// Последнее известное состояние параметра
+#[derive(Debug, Clone, Serialize)]
+pub struct ParamState {
+    pub name: Option<String>,        // Имя параметра из словаря
+    pub unit: Option<String>,        // Единица измерения из словаря
+    pub value: f32,                  // Последнее значение
+    pub text: Option<String>,        // Метка перечисления или список флагов
+    pub min: f32,                    // Минимальное значение из пакета
+    pub max: f32,                    // Максимальное значение из пакета
+    pub alarms: u8,                  // Аварийные биты (4 бита)
+    pub timestamp: DateTime<Utc>,    // Время последнего обновления
+    pub update_count: u64,           // Количество обновлений
+    pub interval_secs: Option<f64>,  // Сглаженный интервал между обновлениями
+}
+
+/// Запись снимка хранилища для выдачи наружу (JSON)
+#[derive(Debug, Clone, Serialize)]
+pub struct ParamSnapshot {
+    #[serde(flatten)]
+    pub key: ParamKey,
+    #[serde(flatten)]
+    pub state: ParamState,
+}
+
+/// Потокобезопасное хранилище последних значений всех параметров
+#[derive(Debug, Default)]
+pub struct StateStore {
+    params: RwLock<HashMap<ParamKey, ParamState>>,
+}
+
+impl StateStore {
+    /// Создает пустое разделяемое хранилище
+    pub fn shared() -> SharedStateStore {
+        Arc::new(Self::default())
+    }
+
+    /// Обновляет состояние параметра по разобранному пакету и возвращает его ключ
+    /// Имя и единица измерения берутся из описания параметра в словаре (если есть)
+    pub fn update(&self, ps: &PackageStruct, param: Option<&ParamDef>) -> ParamKey {
+        self.update_at(ps, param, Utc::now())
+    }
+
+    /// Обновляет состояние параметра на момент времени `now`
+    fn update_at(&self, ps: &PackageStruct, param: Option<&ParamDef>, now: DateTime<Utc>) -> ParamKey {
+        let key = ParamKey::of(ps);
+
+        let mut params = self.params.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let previous = params.get(&key);
+        let update_count = previous.map_or(0, |state| state.update_count) + 1;
+
+        // Экспоненциально сглаженный интервал между обновлениями
+        let interval_secs = previous.map(|state| {
+            let elapsed = (now - state.timestamp).num_microseconds().unwrap_or(i64::MAX) as f64 / 1e6;
+            match state.interval_secs {
+                Some(average) => average + INTERVAL_EWMA_ALPHA * (elapsed - average),
+                None => elapsed,
+            }
+        });
+
+        params.insert(key, ParamState {
+            name: param.map(|def| def.name.clone()),
+            unit: param.map(|def| def.unit.clone()).filter(|unit| !unit.is_empty()),
+            value: ps.value,
+            text: ps.value_text.clone(),
+            min: ps.value_min,
+            max: ps.value_max,
+            alarms: ps.alarms,
+            timestamp: now,
+            update_count,
+            interval_secs,
+        });
+        key
+    }
+
+    /// Возвращает снимок всех параметров, упорядоченный по ключу
+    pub fn snapshot(&self) -> Vec<ParamSnapshot> {
+        let params = self.params.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut snapshot: Vec<ParamSnapshot> = params.iter()
+            .map(|(key, state)| ParamSnapshot {
+                key: *key,
+                state: state.clone(),
+            })
+            .collect();
+        snapshot.sort_by_key(|entry| entry.key);
+        snapshot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn package(module_addr: u8, dev_id: u8, prm_id: u16, value: f32) -> PackageStruct {
+        PackageStruct { module_addr, dev_id, src_id: 2, prm_id, value, ..Default::default() }
+    }
+
+    #[test]
+    fn update_count_and_interval() {
+        let store = StateStore::default();
+        let start = Utc::now();
+        let at = |millis: i64| start + Duration::milliseconds(millis);
+
+        let key = store.update_at(&package(9, 1, 10, 40.0), None, at(0));
+        store.update_at(&package(9, 1, 10, 41.0), None, at(1000));
+        let state = &store.snapshot()[0].state;
+        assert_eq!(state.update_count, 2);
+        // Первый интервал берется как есть
+        assert_eq!(state.interval_secs, Some(1.0));
+
+        // Далее интервал сглаживается: 1 + 0.2 * (3 - 1)
+        store.update_at(&package(9, 1, 10, 42.0), None, at(4000));
+        let snapshot = store.snapshot();
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot[0].key, key);
+        assert_eq!(snapshot[0].state.update_count, 3);
+        assert_eq!(snapshot[0].state.value, 42.0);
+        assert_eq!(snapshot[0].state.timestamp, at(4000));
+        assert!((snapshot[0].state.interval_secs.unwrap() - 1.4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn snapshot_ordered_by_key() {
+        let store = StateStore::default();
+        for (module_addr, dev_id, prm_id) in [(17, 1, 11), (9, 2, 10), (9, 1, 12), (9, 1, 10)] {
+            store.update(&package(module_addr, dev_id, prm_id, 1.0), None);
+        }
+        let keys: Vec<(u8, u8, u16)> = store.snapshot().iter()
+            .map(|entry| (entry.key.module_addr, entry.key.dev_id, entry.key.prm_id))
+            .collect();
+        assert_eq!(keys, [(9, 1, 10), (9, 1, 12), (9, 2, 10), (17, 1, 11)]);
+        // Новый параметр начинает счет обновлений и интервал заново
+        assert!(store.snapshot().iter().all(|entry| entry.state.update_count == 1 && entry.state.interval_secs.is_none()));
+    }
+}
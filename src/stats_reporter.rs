@@ -0,0 +1,237 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+use crate::psorter::SorterStats;
+
+/// Куда выводится текстовый периодический отчет
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatsOutput {
+    /// В лог через tracing
+    Log,
+    /// Непосредственно в stdout
+    Stdout,
+}
+
+impl StatsOutput {
+    /// Разбирает вариант вывода из аргумента командной строки
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "log" => Ok(StatsOutput::Log),
+            "stdout" => Ok(StatsOutput::Stdout),
+            other => Err(anyhow::anyhow!("Unknown stats output: {} (expected log/stdout)", other)),
+        }
+    }
+}
+
+/// Количество и скорость за окно отчета
+#[derive(Debug, Clone, Serialize)]
+pub struct RateEntry {
+    pub count: u64,         // Прирост счетчика за окно
+    pub per_sec: f64,       // Скорость (в секунду)
+}
+
+/// Периодический отчет: приросты и скорости с момента прошлого отчета
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsReport {
+    pub timestamp: String,                          // Время формирования отчета (RFC 3339)
+    pub window_secs: f64,                           // Длительность окна
+    pub packages: RateEntry,                        // Принятые пакеты
+    pub crc_correct: u64,                           // Пакеты с корректным CRC за окно
+    pub crc_incorrect: u64,                         // Пакеты с некорректным CRC за окно
+    pub crc_error_ratio: f64,                       // Доля ошибок CRC за окно
+    pub routes: BTreeMap<String, RateEntry>,        // Скорости по маршрутам
+    pub modules: BTreeMap<String, RateEntry>,       // Скорости по модулям
+    pub parse_errors: BTreeMap<String, RateEntry>,  // Ошибки разбора по видам
+}
+
+/// Формирует периодические отчеты по счетчикам сортировщика
+pub struct StatsReporter {
+    output: StatsOutput,                       // Вывод текстового отчета
+    json_file: Option<File>,                   // Файл для JSON-строк (если задан)
+    previous: Option<(Instant, SorterStats)>,  // Снимок на момент прошлого отчета
+}
+
+impl StatsReporter {
+    /// Создает генератор отчетов; JSON-строки дописываются в `json_path`, если он задан
+    pub fn new(output: StatsOutput, json_path: Option<&str>) -> Result<Self> {
+        let json_file = match json_path {
+            Some(path) => Some(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .context(format!("Failed to open stats JSON file: {}", path))?,
+            ),
+            None => None,
+        };
+
+        Ok(Self {
+            output,
+            json_file,
+            previous: None,
+        })
+    }
+
+    /// Запоминает начальный снимок без вывода отчета
+    pub fn start(&mut self, stats: SorterStats) {
+        self.previous = Some((Instant::now(), stats));
+    }
+
+    /// Формирует отчет по разнице с прошлым снимком, выводит и записывает его
+    pub fn report(&mut self, stats: SorterStats) -> Result<()> {
+        let now = Instant::now();
+        let (since, previous) = self.previous.take()
+            .unwrap_or_else(|| (now, SorterStats::default()));
+        let report = Self::build_report(now.duration_since(since), &previous, &stats);
+        self.previous = Some((now, stats));
+
+        self.print(&report);
+
+        if let Some(file) = self.json_file.as_mut() {
+            let line = serde_json::to_string(&report)?;
+            writeln!(file, "{}", line).context("Failed to write stats JSON line")?;
+        }
+        Ok(())
+    }
+
+    /// Вычисляет приросты и скорости между двумя снимками
+    fn build_report(window: Duration, previous: &SorterStats, current: &SorterStats) -> StatsReport {
+        let window_secs = window.as_secs_f64();
+        let rate = |count: u64| RateEntry {
+            count,
+            per_sec: if window_secs > 0.0 { count as f64 / window_secs } else { 0.0 },
+        };
+        let deltas = |current: &BTreeMap<String, u64>, previous: &BTreeMap<String, u64>| {
+            current.iter()
+                .map(|(name, count)| {
+                    let delta = count.saturating_sub(previous.get(name).copied().unwrap_or(0));
+                    (name.clone(), rate(delta))
+                })
+                .collect::<BTreeMap<_, _>>()
+        };
+
+        let crc_correct = current.crc_correct.saturating_sub(previous.crc_correct);
+        let crc_incorrect = current.crc_incorrect.saturating_sub(previous.crc_incorrect);
+        let crc_total = crc_correct + crc_incorrect;
+
+        StatsReport {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            window_secs,
+            packages: rate(current.input_packages.saturating_sub(previous.input_packages)),
+            crc_correct,
+            crc_incorrect,
+            crc_error_ratio: if crc_total > 0 { crc_incorrect as f64 / crc_total as f64 } else { 0.0 },
+            routes: deltas(&current.routes, &previous.routes),
+            modules: deltas(&current.modules, &previous.modules),
+            parse_errors: deltas(&current.parse_errors, &previous.parse_errors),
+        }
+    }
+
+    /// Выводит текстовый отчет в лог или stdout
+    fn print(&self, report: &StatsReport) {
+        let join = |entries: &BTreeMap<String, RateEntry>| {
+            entries.iter()
+                .map(|(name, entry)| format!("{}={} ({:.1}/s)", name, entry.count, entry.per_sec))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let routes = join(&report.routes);
+        let modules = join(&report.modules);
+        let parse_errors = join(&report.parse_errors);
+
+        match self.output {
+            StatsOutput::Log => info!(
+                window_secs = format!("{:.1}", report.window_secs),
+                packages = report.packages.count,
+                packages_per_sec = format!("{:.1}", report.packages.per_sec),
+                crc_incorrect = report.crc_incorrect,
+                crc_error_ratio = format!("{:.4}", report.crc_error_ratio),
+                routes,
+                modules,
+                parse_errors,
+                "Periodic statistics"
+            ),
+            StatsOutput::Stdout => {
+                println!("------------------------------------------------");
+                println!("STATISTICS for last {:.1} s:", report.window_secs);
+                println!("Packages: {} ({:.1}/s)", report.packages.count, report.packages.per_sec);
+                println!("CRC incorrect: {} (ratio {:.4})", report.crc_incorrect, report.crc_error_ratio);
+                println!("Routes: {}", routes);
+                println!("Modules: {}", modules);
+                println!("Parse errors: {}", parse_errors);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(input: u64, correct: u64, incorrect: u64, tmonitor: u64) -> SorterStats {
+        SorterStats {
+            input_packages: input,
+            crc_correct: correct,
+            crc_incorrect: incorrect,
+            routes: BTreeMap::from([(String::from("TMonitor"), tmonitor)]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn deltas_and_rates() {
+        let previous = stats(100, 90, 10, 50);
+        let mut current = stats(300, 280, 20, 150);
+        current.parse_errors.insert(String::from("too_short"), 4);
+
+        let report = StatsReporter::build_report(Duration::from_secs(2), &previous, &current);
+        assert_eq!(report.window_secs, 2.0);
+        assert_eq!(report.packages.count, 200);
+        assert_eq!(report.packages.per_sec, 100.0);
+        assert_eq!(report.crc_correct, 190);
+        assert_eq!(report.crc_incorrect, 10);
+        assert_eq!(report.crc_error_ratio, 0.05);
+        assert_eq!(report.routes["TMonitor"].count, 100);
+        assert_eq!(report.routes["TMonitor"].per_sec, 50.0);
+        // Новый ключ считается от нуля
+        assert_eq!(report.parse_errors["too_short"].count, 4);
+
+        // Пустое окно и сброс счетчиков не дают деления на ноль и отрицательных приростов
+        let report = StatsReporter::build_report(Duration::ZERO, &current, &previous);
+        assert_eq!(report.packages.count, 0);
+        assert_eq!(report.packages.per_sec, 0.0);
+        assert_eq!(report.crc_error_ratio, 0.0);
+    }
+
+    #[test]
+    fn json_line_shape() {
+        let path = std::env::temp_dir().join(format!("hwmon-stats-test-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut reporter = StatsReporter::new(StatsOutput::Log, Some(path.to_str().unwrap())).unwrap();
+        reporter.start(stats(0, 0, 0, 0));
+        reporter.report(stats(10, 9, 1, 5)).unwrap();
+        reporter.report(stats(20, 18, 2, 10)).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<serde_json::Value> = text.lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        for line in &lines {
+            for key in ["timestamp", "window_secs", "packages", "crc_correct", "crc_incorrect",
+                        "crc_error_ratio", "routes", "modules", "parse_errors"] {
+                assert!(line.get(key).is_some(), "missing {}", key);
+            }
+            assert_eq!(line["packages"]["count"], 10);
+            assert_eq!(line["routes"]["TMonitor"]["count"], 5);
+            assert!(line["packages"]["per_sec"].is_number());
+        }
+    }
+}
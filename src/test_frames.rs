@@ -0,0 +1,44 @@
+//! Сборка пакетов и кадров для модульных тестов разбора, сортировки и экспорта
+
+use crate::include::byte_stuffing;
+use crate::protocol::CrcCheck;
+use crate::sim_frame::FRAME_DELIMITER;
+
+/// Значения prm, prm_max, prm_min в Linear11: 4.0, 6.0, 2.0
+pub const VALUES: [u16; 3] = [0xE820, 0xE830, 0xE810];
+
+/// Поле addr стандартной раскладки: module_id, BM, MCU
+pub fn addr(module_id: u16, bm: u16, mcu: u16) -> u16 {
+    (module_id << 7) | (bm << 3) | mcu
+}
+
+/// Поле src стандартной раскладки: src_id, dev_id
+pub fn src(src_id: u16, dev_id: u16) -> u16 {
+    (src_id << 11) | dev_id
+}
+
+/// Содержимое пакета из 16-битных слов (little-endian)
+pub fn words(words: &[u16]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+/// Пакет: слова заголовка (addr, package_type, src, data_type), содержимое и CRC
+pub fn package(crc: CrcCheck, header: [u16; 4], payload: &[u8]) -> Vec<u8> {
+    let mut package = words(&header);
+    package.extend_from_slice(payload);
+    crc.append(&mut package);
+    package
+}
+
+/// Кадр без разделителя (вход сортировщика): пакет после байт-стаффинга
+pub fn stuffed(mut package: Vec<u8>) -> Vec<u8> {
+    byte_stuffing::request_byte_stuffing(&mut package);
+    package
+}
+
+/// Кадр потока: пакет после байт-стаффинга с разделителем
+pub fn framed(package: Vec<u8>) -> Vec<u8> {
+    let mut frame = stuffed(package);
+    frame.push(FRAME_DELIMITER);
+    frame
+}
@@ -0,0 +1,565 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Cell, Paragraph, Row, Sparkline, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::alarm_engine::{AlarmLevel, SharedAlarmEngine};
+use crate::channels::{RecordReceiver, RECORD_TAP_CAPACITY};
+use crate::device_stats::SharedDeviceStats;
+use crate::hw_alarms::SharedHwAlarms;
+use crate::psorter::PSorter;
+use crate::record::{device_name, param_name, FrameRecord};
+use crate::state_store::ParamKey;
+
+/// Период перерисовки и опроса клавиатуры
+const TICK: Duration = Duration::from_millis(250);
+
+/// Период выборки доли ошибок CRC для графика
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Количество точек графика ошибок CRC
+const SPARKLINE_POINTS: usize = 120;
+
+/// Количество записей в логе пакетов
+const LOG_CAPACITY: usize = 2000;
+
+/// Устройство или параметр без обновлений дольше этого считается пропавшим, с
+const STALE_SECS: f64 = 5.0;
+
+/// Доля ошибок CRC устройства, начиная с которой связь считается плохой
+const CRC_BAD_RATIO: f64 = 0.01;
+
+/// Маршрут пакетов температуры
+const TEMPERATURE_ROUTE: &str = "TMonitor";
+
+/// Ключ ячейки сетки температур: (BM, MCU, dev_id)
+type CellKey = (u8, u8, u8);
+
+/// Ключ температурного параметра для уровня тревоги: (BM, MCU, dev_id, src_id, prm_id)
+type TemperatureKey = (u8, u8, u8, u8, u16);
+
+/// Последнее значение температурного параметра
+#[derive(Debug, Clone)]
+struct Temperature {
+    key: TemperatureKey,             // Ключ параметра (для уровня тревоги)
+    value: f32,                      // Значение
+    updated: DateTime<Utc>,          // Время обновления
+}
+
+/// Условие фильтра лога пакетов
+#[derive(Debug, Clone, PartialEq)]
+enum FilterTerm {
+    /// Поле записи: bm, mcu, dev, src, prm, route, crc
+    Field(String, String),
+    /// Подстрока строки лога
+    Text(String),
+}
+
+/// Фильтр лога пакетов: условия через пробел, все должны выполняться
+/// Пример: "bm=1 dev=2 route=tmonitor crc=fail vout"
+#[derive(Debug, Clone, Default, PartialEq)]
+struct LogFilter {
+    terms: Vec<FilterTerm>,
+}
+
+impl LogFilter {
+    /// Разбирает строку фильтра
+    fn parse(text: &str) -> Self {
+        let terms = text.split_whitespace()
+            .map(|term| match term.split_once('=') {
+                Some((field, value)) if Self::is_field(field) => {
+                    FilterTerm::Field(field.to_ascii_lowercase(), value.to_ascii_lowercase())
+                }
+                _ => FilterTerm::Text(term.to_ascii_lowercase()),
+            })
+            .collect();
+        Self { terms }
+    }
+
+    /// Поддерживаемые поля
+    fn is_field(field: &str) -> bool {
+        matches!(field.to_ascii_lowercase().as_str(), "bm" | "mcu" | "dev" | "src" | "prm" | "route" | "crc")
+    }
+
+    /// Подходит ли запись (line - строка лога для текстовых условий)
+    fn matches(&self, record: &FrameRecord, line: &str) -> bool {
+        let number = |value: Option<u16>, expected: &str| value.is_some_and(|value| value.to_string() == expected);
+        self.terms.iter().all(|term| match term {
+            FilterTerm::Field(field, value) => match field.as_str() {
+                "bm" => number(record.bm.map(u16::from), value),
+                "mcu" => number(record.mcu.map(u16::from), value),
+                "dev" => number(record.dev_id.map(u16::from), value),
+                "src" => number(record.src_id.map(u16::from), value),
+                "prm" => number(record.prm_id, value),
+                "route" => record.route.as_ref().is_some_and(|route| route.eq_ignore_ascii_case(value)),
+                "crc" => record.crc == *value,
+                _ => true,
+            },
+            FilterTerm::Text(text) => line.to_ascii_lowercase().contains(text),
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+}
+
+/// Терминальный интерфейс: сетка температур, состояние связи с устройствами,
+/// график ошибок CRC, активные тревоги и лог декодированных пакетов
+/// Работает поверх того же конвейера, что и режимы DUMP и UART
+pub struct Dashboard {
+    source: String,                                      // Источник пакетов (для заголовка)
+    sorter: Arc<Mutex<PSorter>>,                         // Сортировщик (счетчики)
+    records: RecordReceiver,                             // Декодированные записи от сортировщика
+    device_stats: SharedDeviceStats,                     // Статистика устройств
+    alarm_engine: SharedAlarmEngine,                     // Пороговые тревоги
+    hw_alarms: SharedHwAlarms,                           // Аппаратные аварийные биты
+    temperatures: BTreeMap<CellKey, BTreeMap<u16, Temperature>>,  // Температуры по устройствам и параметрам
+    log: VecDeque<(FrameRecord, String)>,                // Последние записи и их строки
+    crc_rates: VecDeque<u64>,                            // Доля ошибок CRC по секундам, сотые доли %
+    last_sample: (Instant, u64, u64),                    // Время и счетчики CRC (ok, fail) прошлой выборки
+    filter: LogFilter,                                   // Фильтр лога
+    filter_input: Option<String>,                        // Редактируемый фильтр ("/")
+    paused: bool,                                        // Лог остановлен
+    scroll: usize,                                       // Прокрутка лога от последней строки
+}
+
+impl Dashboard {
+    /// Создает интерфейс поверх сортировщика запущенного контроллера
+    pub fn new(source: &str, sorter: Arc<Mutex<PSorter>>, records: RecordReceiver) -> Self {
+        let (device_stats, alarm_engine, hw_alarms) = {
+            let sorter = sorter.blocking_lock();
+            (sorter.device_stats(), sorter.alarm_engine(), sorter.hw_alarms())
+        };
+        Self {
+            source: source.to_string(),
+            sorter,
+            records,
+            device_stats,
+            alarm_engine,
+            hw_alarms,
+            temperatures: BTreeMap::new(),
+            log: VecDeque::new(),
+            crc_rates: VecDeque::new(),
+            last_sample: (Instant::now(), 0, 0),
+            filter: LogFilter::default(),
+            filter_input: None,
+            paused: false,
+            scroll: 0,
+        }
+    }
+
+    /// Запускает интерфейс до нажатия q (блокирующий вызов)
+    pub fn run(mut self) -> Result<()> {
+        let mut terminal = ratatui::try_init()?;
+        let result = self.event_loop(&mut terminal);
+        ratatui::restore();
+        result
+    }
+
+    /// Цикл перерисовки и обработки клавиш
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            self.drain_records();
+            self.sample_crc_rate();
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if event::poll(TICK)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press && self.handle_key(key) {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Обрабатывает клавишу; возвращает true для выхода
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return true;
+        }
+
+        if let Some(input) = &mut self.filter_input {
+            match key.code {
+                KeyCode::Enter => {
+                    self.filter = LogFilter::parse(input);
+                    self.filter_input = None;
+                    self.scroll = 0;
+                }
+                KeyCode::Esc => self.filter_input = None,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return false;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return true,
+            KeyCode::Char('/') => {
+                let current = self.filter.terms.iter()
+                    .map(|term| match term {
+                        FilterTerm::Field(field, value) => format!("{}={}", field, value),
+                        FilterTerm::Text(text) => text.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                self.filter_input = Some(current);
+            }
+            KeyCode::Char('c') => {
+                self.filter = LogFilter::default();
+                self.scroll = 0;
+            }
+            KeyCode::Char(' ') | KeyCode::Char('p') => self.paused = !self.paused,
+            KeyCode::Up => self.scroll = self.scroll.saturating_add(1),
+            KeyCode::Down => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_add(20),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_sub(20),
+            KeyCode::End => self.scroll = 0,
+            _ => {}
+        }
+        false
+    }
+
+    /// Забирает накопившиеся записи: обновляет температуры и лог
+    fn drain_records(&mut self) {
+        for _ in 0..RECORD_TAP_CAPACITY {
+            let Ok(record) = self.records.try_recv() else {
+                break;
+            };
+
+            if record.route.as_deref() == Some(TEMPERATURE_ROUTE) {
+                if let (Some(bm), Some(mcu), Some(dev_id), Some(src_id), Some(prm_id), Some(value)) =
+                    (record.bm, record.mcu, record.dev_id, record.src_id, record.prm_id, record.value)
+                {
+                    let key = (bm, mcu, dev_id, src_id, prm_id);
+                    let updated = record.timestamp.unwrap_or_else(Utc::now);
+                    self.temperatures.entry((bm, mcu, dev_id))
+                        .or_default()
+                        .insert(prm_id, Temperature { key, value, updated });
+                }
+            }
+
+            if !self.paused {
+                let line = log_line(&record);
+                self.log.push_back((record, line));
+                if self.log.len() > LOG_CAPACITY {
+                    self.log.pop_front();
+                }
+            }
+        }
+    }
+
+    /// Раз в секунду добавляет точку графика доли ошибок CRC
+    fn sample_crc_rate(&mut self) {
+        let (last_time, last_ok, last_fail) = self.last_sample;
+        if last_time.elapsed() < SAMPLE_INTERVAL {
+            return;
+        }
+        let stats = self.sorter.blocking_lock().stats();
+        let ok = stats.crc_correct.saturating_sub(last_ok);
+        let fail = stats.crc_incorrect.saturating_sub(last_fail);
+        let rate = (fail * 10_000).checked_div(ok + fail).unwrap_or(0);
+
+        self.crc_rates.push_back(rate);
+        if self.crc_rates.len() > SPARKLINE_POINTS {
+            self.crc_rates.pop_front();
+        }
+        self.last_sample = (Instant::now(), stats.crc_correct, stats.crc_incorrect);
+    }
+
+    /// Рисует экран
+    fn draw(&self, frame: &mut Frame) {
+        let [header, top, middle, log, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Percentage(35),
+            Constraint::Length(8),
+            Constraint::Min(6),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [grid, links] = Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)]).areas(top);
+        let [crc, alarms] = Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(middle);
+
+        let levels = self.alarm_levels();
+        self.draw_header(frame, header);
+        self.draw_temperatures(frame, grid, &levels);
+        self.draw_links(frame, links);
+        self.draw_crc_rate(frame, crc);
+        self.draw_alarms(frame, alarms);
+        self.draw_log(frame, log);
+        self.draw_footer(frame, footer);
+    }
+
+    /// Уровни активных пороговых тревог по параметрам
+    fn alarm_levels(&self) -> HashMap<TemperatureKey, AlarmLevel> {
+        self.alarm_engine.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .active_alarms()
+            .into_iter()
+            .map(|alarm| {
+                let key = alarm.key;
+                ((key.module_addr_bm, key.module_addr_mcu, key.dev_id, key.src_id, key.prm_id), alarm.level)
+            })
+            .collect()
+    }
+
+    fn draw_header(&self, frame: &mut Frame, area: Rect) {
+        let stats = self.sorter.blocking_lock().stats();
+        let text = format!(
+            " HWMon [{}]  packages {}  CRC ok {}  CRC fail {}  {}",
+            self.source,
+            stats.input_packages,
+            stats.crc_correct,
+            stats.crc_incorrect,
+            if self.paused { "[LOG PAUSED]" } else { "" },
+        );
+        frame.render_widget(Paragraph::new(text).style(Style::default().add_modifier(Modifier::REVERSED)), area);
+    }
+
+    /// Сетка температур: строки - модули BM/MCU, столбцы - устройства
+    /// Цвет по уровню пороговой тревоги; серый - нет обновлений дольше STALE_SECS
+    fn draw_temperatures(&self, frame: &mut Frame, area: Rect, levels: &HashMap<TemperatureKey, AlarmLevel>) {
+        let now = Utc::now();
+        let modules: BTreeSet<(u8, u8)> = self.temperatures.keys().map(|&(bm, mcu, _)| (bm, mcu)).collect();
+        let devices: BTreeSet<u8> = self.temperatures.keys().map(|&(_, _, dev_id)| dev_id).collect();
+
+        let header = Row::new(
+            std::iter::once(Cell::from("Module"))
+                .chain(devices.iter().map(|dev_id| Cell::from(format!("DEV{}", dev_id))))
+        )
+        .style(Style::default().add_modifier(Modifier::BOLD));
+
+        let rows = modules.iter().map(|&(bm, mcu)| {
+            let cells = devices.iter().map(|&dev_id| {
+                let Some(params) = self.temperatures.get(&(bm, mcu, dev_id)) else {
+                    return Cell::from("-");
+                };
+                // Самый горячий параметр устройства и худший уровень тревоги
+                let hottest = params.values().max_by(|a, b| a.value.total_cmp(&b.value));
+                let level = params.values()
+                    .filter_map(|temperature| levels.get(&temperature.key))
+                    .max()
+                    .copied();
+                let stale = params.values()
+                    .all(|temperature| (now - temperature.updated).num_milliseconds() as f64 / 1000.0 > STALE_SECS);
+                let color = match level {
+                    _ if stale => Color::DarkGray,
+                    Some(AlarmLevel::Critical) => Color::Red,
+                    Some(AlarmLevel::Warning) => Color::Yellow,
+                    _ => Color::Green,
+                };
+                let text = hottest.map_or(String::from("-"), |temperature| format!("{:.1}", temperature.value));
+                Cell::from(text).style(Style::default().fg(color))
+            });
+            Row::new(std::iter::once(Cell::from(format!("BM{}.MCU{}", bm, mcu))).chain(cells))
+        });
+
+        let widths = std::iter::once(Constraint::Length(10))
+            .chain(devices.iter().map(|_| Constraint::Length(7)));
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(Block::bordered().title(" Temperatures "));
+        frame.render_widget(table, area);
+    }
+
+    /// Состояние связи с устройствами: пакеты, доля ошибок CRC, время последнего пакета
+    fn draw_links(&self, frame: &mut Frame, area: Rect) {
+        let now = Utc::now();
+        let entries = self.device_stats.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entries();
+
+        let header = Row::new(["Device", "Packets", "CRC %", "Seen", "Status"])
+            .style(Style::default().add_modifier(Modifier::BOLD));
+        let rows = entries.iter().map(|entry| {
+            let age = entry.last_seen.map(|seen| (now - seen).num_milliseconds() as f64 / 1000.0);
+            let (status, color) = match age {
+                None => ("NO DATA", Color::DarkGray),
+                Some(age) if age > STALE_SECS => ("STALE", Color::Red),
+                _ if entry.crc_failure_ratio >= CRC_BAD_RATIO => ("CRC", Color::Yellow),
+                _ => ("OK", Color::Green),
+            };
+            Row::new([
+                Cell::from(device_name((entry.key.module_addr_bm, entry.key.module_addr_mcu, entry.key.dev_id))),
+                Cell::from(entry.packages.to_string()),
+                Cell::from(format!("{:.2}", entry.crc_failure_ratio * 100.0)),
+                Cell::from(age.map_or(String::from("-"), |age| format!("{:.0}s", age))),
+                Cell::from(status).style(Style::default().fg(color)),
+            ])
+        });
+
+        let widths = [
+            Constraint::Length(16),
+            Constraint::Length(9),
+            Constraint::Length(7),
+            Constraint::Length(6),
+            Constraint::Length(8),
+        ];
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(Block::bordered().title(" Links "));
+        frame.render_widget(table, area);
+    }
+
+    /// График доли ошибок CRC по секундам
+    fn draw_crc_rate(&self, frame: &mut Frame, area: Rect) {
+        let data: Vec<u64> = self.crc_rates.iter().copied().collect();
+        let current = data.last().copied().unwrap_or(0);
+        let title = format!(" CRC errors {:.2}% ", current as f64 / 100.0);
+        let color = if current as f64 / 10_000.0 >= CRC_BAD_RATIO { Color::Yellow } else { Color::Green };
+        // Шкала не меньше 1%, чтобы единичные ошибки не занимали всю высоту
+        let sparkline = Sparkline::default()
+            .data(&data)
+            .max(data.iter().copied().max().unwrap_or(0).max(100))
+            .style(Style::default().fg(color))
+            .block(Block::bordered().title(title));
+        frame.render_widget(sparkline, area);
+    }
+
+    /// Активные пороговые тревоги и аппаратные аварийные биты
+    fn draw_alarms(&self, frame: &mut Frame, area: Rect) {
+        let key_name = |key: &ParamKey| {
+            param_name((key.module_addr_bm, key.module_addr_mcu, key.dev_id), key.prm_id)
+        };
+
+        let threshold = self.alarm_engine.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .active_alarms();
+        let hardware = self.hw_alarms.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .active_summary();
+
+        let mut lines: Vec<Line> = threshold.iter()
+            .map(|alarm| {
+                let color = if alarm.level == AlarmLevel::Critical { Color::Red } else { Color::Yellow };
+                Line::from(vec![
+                    Span::styled(format!("{:8} ", format!("{:?}", alarm.level).to_uppercase()), Style::default().fg(color)),
+                    Span::raw(format!("{} {} = {:.2} since {}",
+                                      alarm.class, key_name(&alarm.key), alarm.value, alarm.since.format("%H:%M:%S"))),
+                ])
+            })
+            .collect();
+        lines.extend(hardware.iter().map(|alarm| {
+            Line::from(vec![
+                Span::styled("HW       ", Style::default().fg(Color::Magenta)),
+                Span::raw(format!("{} (bit {}) {} since {}",
+                                  alarm.name, alarm.bit, key_name(&alarm.key), alarm.since.format("%H:%M:%S"))),
+            ])
+        }));
+        if lines.is_empty() {
+            lines.push(Line::styled("No active alarms", Style::default().fg(Color::Green)));
+        }
+
+        let title = format!(" Alarms ({}) ", threshold.len() + hardware.len());
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(title)), area);
+    }
+
+    /// Лог декодированных пакетов с фильтром и прокруткой
+    fn draw_log(&self, frame: &mut Frame, area: Rect) {
+        let visible: Vec<&(FrameRecord, String)> = self.log.iter()
+            .filter(|(record, line)| self.filter.matches(record, line))
+            .collect();
+        let height = area.height.saturating_sub(2) as usize;
+        let end = visible.len().saturating_sub(self.scroll.min(visible.len().saturating_sub(height)));
+        let start = end.saturating_sub(height);
+
+        let lines: Vec<Line> = visible[start..end].iter()
+            .map(|(record, line)| {
+                let color = if record.crc != "ok" {
+                    Color::Red
+                } else if record.error.is_some() {
+                    Color::Yellow
+                } else {
+                    Color::Reset
+                };
+                Line::styled(line.as_str(), Style::default().fg(color))
+            })
+            .collect();
+
+        let filter = if self.filter.is_empty() { String::from("none") } else { self.filter_text() };
+        let title = format!(" Packets ({} of {}, filter: {}) ", visible.len(), self.log.len(), filter);
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(title)), area);
+    }
+
+    /// Строка подсказки или редактируемого фильтра
+    fn draw_footer(&self, frame: &mut Frame, area: Rect) {
+        let text = match &self.filter_input {
+            Some(input) => format!(" Filter (bm= mcu= dev= src= prm= route= crc=ok|fail, text): {}_", input),
+            None => String::from(" q quit  / filter  c clear filter  space pause log  ↑↓ PgUp PgDn End scroll"),
+        };
+        frame.render_widget(Paragraph::new(text), area);
+    }
+
+    /// Текущий фильтр в виде строки
+    fn filter_text(&self) -> String {
+        self.filter.terms.iter()
+            .map(|term| match term {
+                FilterTerm::Field(field, value) => format!("{}={}", field, value),
+                FilterTerm::Text(text) => text.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Строка лога для записи
+fn log_line(record: &FrameRecord) -> String {
+    let time = record.timestamp.map_or(String::from("--:--:--.---"), |time| time.format("%H:%M:%S%.3f").to_string());
+    let route = record.route.as_deref().unwrap_or("-");
+    let device = match (record.bm, record.mcu, record.dev_id) {
+        (Some(bm), Some(mcu), Some(dev_id)) => device_name((bm, mcu, dev_id)),
+        _ => String::from("-"),
+    };
+    let param = record.prm_id.map_or(String::from("-"), |prm_id| format!("prm{}", prm_id));
+    let value = match (&record.text, record.value) {
+        (Some(text), _) => text.clone(),
+        (None, Some(value)) => format!("{:.3}", value),
+        (None, None) => String::from("-"),
+    };
+    let note = record.error.clone()
+        .or_else(|| record.param.clone())
+        .unwrap_or_default();
+    format!("{} #{:<7} {:4} {:9} {:16} {:8} {:>12}  {}",
+            time, record.frame, record.crc, route, device, param, value, note)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_filter_fields_and_text() {
+        let record = FrameRecord {
+            frame: 7,
+            crc: String::from("ok"),
+            route: Some(String::from("TMonitor")),
+            bm: Some(1),
+            mcu: Some(2),
+            dev_id: Some(3),
+            prm_id: Some(10),
+            value: Some(42.5),
+            ..Default::default()
+        };
+        let line = log_line(&record);
+
+        assert!(LogFilter::parse("").matches(&record, &line));
+        assert!(LogFilter::parse("bm=1 dev=3 route=tmonitor crc=ok").matches(&record, &line));
+        assert!(LogFilter::parse("BM1.MCU2").matches(&record, &line));
+        assert!(!LogFilter::parse("prm=11").matches(&record, &line));
+        assert!(!LogFilter::parse("crc=fail").matches(&record, &line));
+        // Неизвестное поле ищется как подстрока
+        assert_eq!(LogFilter::parse("foo=1").terms, vec![FilterTerm::Text(String::from("foo=1"))]);
+    }
+}
@@ -1,33 +1,50 @@
 use anyhow::{Context, Result};
 use serialport::SerialPort;
 use std::time::Duration;
+use tracing::{debug, info, trace};
+
+/// Последовательный порт по умолчанию
+pub const DEFAULT_PORT: &str = "/dev/ttyS0";
 
 /// Структура для работы с последовательным портом (UART)
 pub struct Uart {
-    port: Box<dyn SerialPort>,  // Объект последовательного порта
+    path: String,                       // Путь к порту (например, /dev/ttyS0 или pty симулятора)
+    port: Option<Box<dyn SerialPort>>,  // Объект последовательного порта (None - закрыт)
 }
 
 impl Uart {
     /// Создает новый экземпляр UART с настройками по умолчанию
     /// Настройки: 1 Мбит/с, 8 бит данных, без контроля четности, 1 стоп-бит
-    pub fn new() -> Result<Self> {
-        let port = serialport::new("/dev/ttyS0", 1_000_000)
+    pub fn new(path: &str) -> Result<Self> {
+        let port = Self::open_port(path)?;
+        info!(path, "UART port opened successfully");
+        Ok(Uart { path: path.to_string(), port: Some(port) })
+    }
+
+    /// Закрывает и заново открывает порт (после ошибки чтения)
+    /// Старый дескриптор закрывается до открытия: порт открывается в монопольном режиме (TIOCEXCL)
+    pub fn reopen(&mut self) -> Result<()> {
+        self.port = None;
+        self.port = Some(Self::open_port(&self.path)?);
+        info!(path = %self.path, "UART port reopened");
+        Ok(())
+    }
+
+    /// Открывает последовательный порт с настройками по умолчанию
+    fn open_port(path: &str) -> Result<Box<dyn SerialPort>> {
+        serialport::new(path, 1_000_000)
             .data_bits(serialport::DataBits::Eight)     // 8 бит данных
             .parity(serialport::Parity::None)           // Без контроля четности
             .stop_bits(serialport::StopBits::One)       // 1 стоп-бит
             .flow_control(serialport::FlowControl::None) // Без управления потоком
             .timeout(Duration::from_millis(1000))       // Таймаут 1 секунда
             .open()
-            .context("Failed to open serial port")?;
-
-        println!("UART port opened successfully");
-        Ok(Uart { port })
+            .context(format!("Failed to open serial port: {}", path))
     }
 
-    /// Проверяет, открыт ли порт
-    /// В данной реализации всегда возвращает true, так как порт открывается при создании
+    /// Проверяет, открыт ли порт (после неудачного переоткрытия порт закрыт)
     pub fn is_open(&self) -> bool {
-        true // serialport всегда открыт если создан успешно
+        self.port.is_some()
     }
 
     /// Проверяет, доступен ли порт для чтения
@@ -39,13 +56,11 @@ impl Uart {
     /// Читает все доступные данные из порта
     /// Возвращает вектор байтов или пустой вектор при таймауте
     pub fn read_all(&mut self) -> Result<Vec<u8>> {
+        let port = self.port.as_mut().context("Serial port is closed")?;
         let mut buffer = vec![0; 1024];  // Буфер размером 1 КБ
-        match self.port.read(&mut buffer) {
+        match port.read(&mut buffer) {
             Ok(size) => {
                 buffer.truncate(size);
-                if size > 0 {
-                    println!("UART read {} bytes", size);
-                }
                 Ok(buffer)
             }
             Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {
@@ -57,14 +72,15 @@ impl Uart {
 
     /// Записывает данные в порт
     pub fn write_all(&mut self, data: &[u8]) -> Result<()> {
-        self.port.write_all(data)
+        let port = self.port.as_mut().context("Serial port is closed")?;
+        port.write_all(data)
             .context("Failed to write to serial port")?;
         
         // Обеспечиваем отправку всех данных
-        self.port.flush()
+        port.flush()
             .context("Failed to flush serial port")?;
         
-        println!("UART wrote {} bytes", data.len());
+        trace!(size = data.len(), "UART wrote");
         Ok(())
     }
 }
@@ -72,6 +88,6 @@ impl Uart {
 /// Реализация деструктора для корректного закрытия порта
 impl Drop for Uart {
     fn drop(&mut self) {
-        println!("UART port closed");
+        debug!("UART port closed");
     }
 }
\ No newline at end of file
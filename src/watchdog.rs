@@ -0,0 +1,208 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::config::WatchdogConfig;
+use crate::state_store::{ParamKey, ParamSnapshot};
+
+/// Событие сторожевого таймера: параметр замолчал или снова обновляется
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchdogEvent {
+    pub event: &'static str,                 // "stale" или "recovered"
+    #[serde(flatten)]
+    pub key: ParamKey,
+    pub last_seen: DateTime<Utc>,            // Время последнего обновления до тишины
+    pub expected_interval_secs: f64,         // Ожидаемый интервал обновления
+    pub silent_secs: f64,                    // Длительность тишины
+    pub timestamp: DateTime<Utc>,            // Время формирования события
+}
+
+/// Параметр, находящийся в состоянии "stale"
+#[derive(Debug, Clone)]
+struct StaleParam {
+    last_seen: DateTime<Utc>,
+    expected_interval_secs: f64,
+}
+
+/// Обнаруживает параметры, переставшие обновляться, по времени последнего обновления
+/// Ожидаемый интервал берется из конфигурации или обучается по потоку (StateStore)
+pub struct Watchdog {
+    config: WatchdogConfig,
+    stale: HashMap<ParamKey, StaleParam>,
+}
+
+impl Watchdog {
+    /// Создает сторожевой таймер с указанными настройками
+    pub fn new(config: WatchdogConfig) -> Self {
+        Self {
+            config,
+            stale: HashMap::new(),
+        }
+    }
+
+    /// Проверяет снимок хранилища и возвращает новые события stale/recovered
+    pub fn check(&mut self, snapshot: &[ParamSnapshot], now: DateTime<Utc>) -> Vec<WatchdogEvent> {
+        let mut events = Vec::new();
+
+        for entry in snapshot {
+            let last_seen = entry.state.timestamp;
+
+            // Параметр уже помечен как stale - ждем нового обновления
+            if let Some(stale) = self.stale.get(&entry.key) {
+                if last_seen > stale.last_seen {
+                    let stale = self.stale.remove(&entry.key).expect("stale entry exists");
+                    events.push(Self::event("recovered", entry, stale.last_seen,
+                                            stale.expected_interval_secs, last_seen,
+                                            seconds_between(stale.last_seen, last_seen)));
+                }
+                continue;
+            }
+
+            let Some(expected) = self.expected_interval(entry) else {
+                continue;
+            };
+
+            let silent_secs = seconds_between(last_seen, now);
+            if silent_secs > expected * self.config.stale_factor {
+                self.stale.insert(entry.key, StaleParam {
+                    last_seen,
+                    expected_interval_secs: expected,
+                });
+                events.push(Self::event("stale", entry, last_seen, expected, now, silent_secs));
+            }
+        }
+
+        events
+    }
+
+    /// Ожидаемый интервал: из конфигурации, иначе обученный (после min_updates обновлений)
+    fn expected_interval(&self, entry: &ParamSnapshot) -> Option<f64> {
+        let key = &entry.key;
+        let configured = self.config.params.iter().find(|param| {
+            param.prm_id == key.prm_id
+                && param.src_id.is_none_or(|src_id| src_id == key.src_id)
+                && param.module_addr.is_none_or(|module_addr| module_addr == key.module_addr)
+                && param.dev_id.is_none_or(|dev_id| dev_id == key.dev_id)
+        });
+        if let Some(param) = configured {
+            return Some(param.expected_interval_secs);
+        }
+
+        if entry.state.update_count < self.config.min_updates {
+            return None;
+        }
+        entry.state.interval_secs
+            .map(|interval| interval.max(self.config.min_interval_secs))
+    }
+
+    fn event(
+        event: &'static str,
+        entry: &ParamSnapshot,
+        last_seen: DateTime<Utc>,
+        expected_interval_secs: f64,
+        timestamp: DateTime<Utc>,
+        silent_secs: f64,
+    ) -> WatchdogEvent {
+        WatchdogEvent {
+            event,
+            key: entry.key,
+            last_seen,
+            expected_interval_secs,
+            silent_secs,
+            timestamp,
+        }
+    }
+}
+
+/// Разница между двумя моментами времени в секундах
+fn seconds_between(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    (to - from).num_microseconds().unwrap_or(i64::MAX) as f64 / 1e6
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WatchdogParamConfig;
+    use crate::state_store::ParamState;
+    use chrono::Duration;
+
+    fn entry(prm_id: u16, timestamp: DateTime<Utc>, update_count: u64, interval_secs: Option<f64>) -> ParamSnapshot {
+        ParamSnapshot {
+            key: ParamKey { module_addr: 9, module_addr_bm: 1, module_addr_mcu: 1, dev_id: 1, src_id: 2, prm_id },
+            state: ParamState {
+                name: None,
+                unit: None,
+                value: 40.0,
+                text: None,
+                min: 40.0,
+                max: 40.0,
+                alarms: 0,
+                timestamp,
+                update_count,
+                interval_secs,
+            },
+        }
+    }
+
+    fn config() -> WatchdogConfig {
+        WatchdogConfig { stale_factor: 3.0, min_updates: 3, min_interval_secs: 0.5, ..Default::default() }
+    }
+
+    #[test]
+    fn stale_once_then_recovered() {
+        let mut watchdog = Watchdog::new(config());
+        let start = Utc::now();
+        let at = |secs: i64| start + Duration::seconds(secs);
+        let learned = entry(10, start, 5, Some(1.0));
+
+        // Тишина не дольше stale_factor * интервал - событий нет
+        assert!(watchdog.check(std::slice::from_ref(&learned), at(3)).is_empty());
+
+        let events = watchdog.check(std::slice::from_ref(&learned), at(4));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "stale");
+        assert_eq!(events[0].expected_interval_secs, 1.0);
+        assert_eq!(events[0].silent_secs, 4.0);
+        assert_eq!(events[0].last_seen, start);
+
+        // Одно событие на период тишины
+        assert!(watchdog.check(std::slice::from_ref(&learned), at(10)).is_empty());
+
+        let updated = entry(10, at(11), 6, Some(1.0));
+        let events = watchdog.check(std::slice::from_ref(&updated), at(11));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "recovered");
+        assert_eq!(events[0].silent_secs, 11.0);
+        assert!(watchdog.check(&[updated], at(12)).is_empty());
+    }
+
+    #[test]
+    fn min_updates_and_configured_interval() {
+        let mut config = config();
+        config.params.push(WatchdogParamConfig {
+            prm_id: 20,
+            src_id: None,
+            module_addr: Some(9),
+            dev_id: None,
+            expected_interval_secs: 10.0,
+        });
+        let mut watchdog = Watchdog::new(config);
+        let start = Utc::now();
+
+        // Интервал еще не обучен (меньше min_updates обновлений)
+        let young = entry(10, start, 2, Some(1.0));
+        assert!(watchdog.check(&[young], start + Duration::seconds(100)).is_empty());
+
+        // Обученный интервал не меньше min_interval_secs: 3 * 0.5
+        let fast = entry(11, start, 3, Some(0.1));
+        assert!(watchdog.check(std::slice::from_ref(&fast), start + Duration::milliseconds(1400)).is_empty());
+        assert_eq!(watchdog.check(&[fast], start + Duration::milliseconds(1600)).len(), 1);
+
+        // Заданный интервал действует сразу и важнее обученного
+        let configured = entry(20, start, 1, Some(1.0));
+        assert!(watchdog.check(std::slice::from_ref(&configured), start + Duration::seconds(30)).is_empty());
+        let events = watchdog.check(&[configured], start + Duration::seconds(31));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].expected_interval_secs, 10.0);
+    }
+}
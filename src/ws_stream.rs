@@ -0,0 +1,433 @@
+use anyhow::{Context, Result};
+use futures_util::{Sink, SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::{self, Message};
+use tracing::{debug, info, warn};
+
+use crate::channels::RecordReceiver;
+use crate::http_server::parse_query;
+use crate::record::FrameRecord;
+
+/// Емкость общей очереди потока: клиент, отставший больше, теряет сообщения
+const HUB_CAPACITY: usize = 1024;
+
+/// Период отчета клиенту о пропущенных сообщениях
+const DROP_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Максимальное время отправки сообщения; клиент, не принимающий данные дольше, отключается
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Сообщение потока
+#[derive(Debug, Clone)]
+pub enum StreamItem {
+    /// Декодированная запись пакета
+    Package(Box<FrameRecord>),
+    /// Событие (тревоги, сторожевой таймер) в том виде, в каком оно уходит в EMonitor
+    Event(serde_json::Value),
+}
+
+impl StreamItem {
+    /// Сообщение клиенту: {"type": "package" | "event", "data": {...}}
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            StreamItem::Package(record) => serde_json::json!({ "type": "package", "data": record }),
+            StreamItem::Event(event) => serde_json::json!({ "type": "event", "data": event }),
+        }
+    }
+}
+
+/// Общая очередь декодированных пакетов и событий для WebSocket и MQTT
+/// Публикация никогда не ждет получателей: отставшие получатели теряют старые сообщения
+#[derive(Clone)]
+pub struct StreamHub {
+    sender: broadcast::Sender<Arc<StreamItem>>,
+}
+
+impl Default for StreamHub {
+    fn default() -> Self {
+        Self { sender: broadcast::channel(HUB_CAPACITY).0 }
+    }
+}
+
+impl StreamHub {
+    /// Есть ли подключенные клиенты
+    pub fn has_subscribers(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+
+    /// Публикует сообщение всем клиентам
+    pub fn publish(&self, item: StreamItem) {
+        // Ошибка означает только отсутствие клиентов
+        let _ = self.sender.send(Arc::new(item));
+    }
+
+    /// Подключает нового получателя (клиент WebSocket, публикация MQTT)
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<StreamItem>> {
+        self.sender.subscribe()
+    }
+
+    /// Переносит декодированные записи сортировщика в поток
+    pub async fn forward_records(self, mut records: RecordReceiver) {
+        while let Some(record) = records.recv().await {
+            if self.has_subscribers() {
+                self.publish(StreamItem::Package(Box::new(record)));
+            }
+        }
+    }
+}
+
+/// Подписка клиента: что и с какой частотой отправлять
+/// Задается строкой запроса при подключении (?route=TMonitor,SMonitor&bm=1&dev_id=2&prm_id=10,11&events=0&rate=50)
+/// и может быть заменена текстовым сообщением с JSON этой структуры
+/// (поля JSON принимают и имена параметров запроса: route, prm_id, rate)
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Subscription {
+    #[serde(alias = "route")]
+    pub routes: Vec<String>,         // Маршруты пакетов (пусто - все)
+    pub bm: Option<u8>,              // Адрес BM
+    pub mcu: Option<u8>,             // Адрес MCU
+    pub dev_id: Option<u8>,          // Идентификатор устройства
+    #[serde(alias = "prm_id")]
+    pub prm_ids: Vec<u16>,           // Идентификаторы параметров (пусто - все)
+    pub packages: bool,              // Отправлять декодированные пакеты
+    pub events: bool,                // Отправлять события
+    #[serde(alias = "rate")]
+    pub max_rate: Option<u32>,       // Сообщений в секунду (не больше ограничения сервера)
+}
+
+impl Default for Subscription {
+    fn default() -> Self {
+        Self {
+            routes: Vec::new(),
+            bm: None,
+            mcu: None,
+            dev_id: None,
+            prm_ids: Vec::new(),
+            packages: true,
+            events: true,
+            max_rate: None,
+        }
+    }
+}
+
+impl Subscription {
+    /// Разбирает подписку из параметров строки запроса
+    fn from_query(query: &HashMap<String, String>) -> Result<Self> {
+        let number = |name: &str| -> Result<Option<u32>> {
+            query.get(name)
+                .map(|value| value.parse().context(format!("Invalid {}: {}", name, value)))
+                .transpose()
+        };
+        let flag = |name: &str| -> Result<bool> {
+            match query.get(name).map(String::as_str) {
+                None | Some("1") | Some("true") => Ok(true),
+                Some("0") | Some("false") => Ok(false),
+                Some(other) => Err(anyhow::anyhow!("Invalid {}: {} (expected 0/1)", name, other)),
+            }
+        };
+        let byte = |name: &str| -> Result<Option<u8>> {
+            number(name)?.map(|value| u8::try_from(value).context(format!("{} out of range", name))).transpose()
+        };
+        let list = |name: &str| query.get(name)
+            .map(|value| value.split(',').map(str::trim).filter(|item| !item.is_empty()).map(String::from).collect())
+            .unwrap_or_default();
+
+        Ok(Self {
+            routes: list("route"),
+            bm: byte("bm")?,
+            mcu: byte("mcu")?,
+            dev_id: byte("dev_id")?,
+            prm_ids: list("prm_id").iter()
+                .map(|id| id.parse().context(format!("Invalid prm_id: {}", id)))
+                .collect::<Result<_>>()?,
+            packages: flag("packages")?,
+            events: flag("events")?,
+            max_rate: number("rate")?,
+        })
+    }
+
+    /// Подходит ли сообщение под подписку
+    /// Фильтр по маршруту применяется только к пакетам; события фильтруются по устройству и параметру
+    fn matches(&self, item: &StreamItem) -> bool {
+        match item {
+            StreamItem::Package(record) => {
+                self.packages
+                    && (self.routes.is_empty() || record.route.as_ref().is_some_and(|route| {
+                        self.routes.iter().any(|expected| expected.eq_ignore_ascii_case(route))
+                    }))
+                    && self.matches_device(record.bm, record.mcu, record.dev_id, record.prm_id)
+            }
+            StreamItem::Event(event) => {
+                let field = |name: &str| event.get(name).and_then(serde_json::Value::as_u64);
+                self.events
+                    && self.matches_device(
+                        field("module_addr_bm").map(|bm| bm as u8),
+                        field("module_addr_mcu").map(|mcu| mcu as u8),
+                        field("dev_id").map(|id| id as u8),
+                        field("prm_id").map(|id| id as u16),
+                    )
+            }
+        }
+    }
+
+    fn matches_device(&self, bm: Option<u8>, mcu: Option<u8>, dev_id: Option<u8>, prm_id: Option<u16>) -> bool {
+        self.bm.is_none_or(|expected| bm == Some(expected))
+            && self.mcu.is_none_or(|expected| mcu == Some(expected))
+            && self.dev_id.is_none_or(|expected| dev_id == Some(expected))
+            && (self.prm_ids.is_empty() || prm_id.is_some_and(|id| self.prm_ids.contains(&id)))
+    }
+
+    /// Частота клиента с учетом ограничения сервера
+    fn rate(&self, server_max_rate: u32) -> u32 {
+        self.max_rate.map_or(server_max_rate, |rate| rate.clamp(1, server_max_rate))
+    }
+}
+
+/// Ограничитель частоты сообщений клиента (корзина токенов на одну секунду)
+struct TokenBucket {
+    rate: f64,                       // Токенов в секунду
+    tokens: f64,                     // Доступно токенов
+    updated: Instant,                // Время последнего пополнения
+}
+
+impl TokenBucket {
+    fn new(rate: u32) -> Self {
+        Self { rate: rate as f64, tokens: rate as f64, updated: Instant::now() }
+    }
+
+    /// Забирает токен; false - сообщение нужно пропустить
+    fn take(&mut self) -> bool {
+        let now = Instant::now();
+        self.tokens = (self.tokens + now.duration_since(self.updated).as_secs_f64() * self.rate).min(self.rate);
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Сервер WebSocket: поток декодированных пакетов и событий в формате JSON
+pub struct WsServer {
+    listener: TcpListener,
+    hub: StreamHub,
+    max_rate: u32,                   // Ограничение частоты сообщений на клиента
+}
+
+impl WsServer {
+    /// Привязывает сервер к адресу
+    pub async fn bind(addr: &str, hub: StreamHub, max_rate: u32) -> Result<Self> {
+        let listener = TcpListener::bind(addr).await
+            .context(format!("Failed to bind WebSocket server to {}", addr))?;
+        let server = Self { listener, hub, max_rate };
+        info!(addr = %server.local_addr()?, max_rate, "WebSocket server listening");
+        Ok(server)
+    }
+
+    /// Адрес, к которому привязан сервер
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Основной цикл приема соединений
+    pub async fn run(self) {
+        loop {
+            let (stream, peer) = match self.listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("WebSocket accept error: {}", e);
+                    continue;
+                }
+            };
+
+            let hub = self.hub.clone();
+            let max_rate = self.max_rate;
+            tokio::spawn(async move {
+                match serve_client(stream, hub, max_rate).await {
+                    Ok(()) => debug!(%peer, "WebSocket client disconnected"),
+                    Err(e) => debug!(%peer, "WebSocket client error: {:#}", e),
+                }
+            });
+        }
+    }
+}
+
+/// Обслуживает одного клиента до закрытия соединения
+/// Клиент читает из своей позиции общей очереди: медленный клиент теряет сообщения
+/// (об этом приходит сообщение "dropped"), но не задерживает обработку пакетов
+#[allow(clippy::result_large_err)] // Тип ошибки обратного вызова рукопожатия задан tungstenite
+async fn serve_client(stream: TcpStream, hub: StreamHub, max_rate: u32) -> Result<()> {
+    let mut query = String::new();
+    let socket = tokio_tungstenite::accept_hdr_async(stream, |request: &Request, response: Response| {
+        query = request.uri().query().unwrap_or_default().to_string();
+        Ok(response)
+    })
+    .await
+    .context("WebSocket handshake failed")?;
+    let (mut sink, mut source) = socket.split();
+
+    let mut subscription = match Subscription::from_query(&parse_query(&query)) {
+        Ok(subscription) => subscription,
+        Err(e) => {
+            send(&mut sink, &serde_json::json!({ "type": "error", "error": format!("{:#}", e) })).await?;
+            sink.close().await?;
+            return Ok(());
+        }
+    };
+    let mut items = hub.subscribe();
+    let mut bucket = TokenBucket::new(subscription.rate(max_rate));
+    send(&mut sink, &subscribed(&subscription, max_rate)).await?;
+    info!(?subscription, "WebSocket client subscribed");
+
+    let (mut rate_limited, mut lagged) = (0u64, 0u64);
+    let mut report = tokio::time::interval(DROP_REPORT_INTERVAL);
+    loop {
+        tokio::select! {
+            item = items.recv() => match item {
+                Ok(item) => {
+                    if !subscription.matches(&item) {
+                        continue;
+                    }
+                    if !bucket.take() {
+                        rate_limited += 1;
+                        continue;
+                    }
+                    send(&mut sink, &item.to_json()).await?;
+                }
+                Err(RecvError::Lagged(count)) => lagged += count,
+                Err(RecvError::Closed) => break,
+            },
+            message = source.next() => match message {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str::<Subscription>(&text) {
+                    Ok(update) => {
+                        subscription = update;
+                        bucket = TokenBucket::new(subscription.rate(max_rate));
+                        send(&mut sink, &subscribed(&subscription, max_rate)).await?;
+                    }
+                    Err(e) => {
+                        send(&mut sink, &serde_json::json!({ "type": "error", "error": e.to_string() })).await?;
+                    }
+                },
+                Some(Ok(Message::Close(_))) | None => break,
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e.into()),
+            },
+            _ = report.tick() => {
+                if rate_limited + lagged > 0 {
+                    send(&mut sink, &serde_json::json!({
+                        "type": "dropped",
+                        "rate_limited": rate_limited,
+                        "lagged": lagged,
+                    })).await?;
+                    (rate_limited, lagged) = (0, 0);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Подтверждение подписки с действующим ограничением частоты
+fn subscribed(subscription: &Subscription, max_rate: u32) -> serde_json::Value {
+    serde_json::json!({
+        "type": "subscribed",
+        "subscription": subscription,
+        "rate": subscription.rate(max_rate),
+    })
+}
+
+/// Отправляет сообщение клиенту; клиент, не принимающий данные SEND_TIMEOUT, отключается
+async fn send<S>(sink: &mut S, value: &serde_json::Value) -> Result<()>
+where
+    S: Sink<Message, Error = tungstenite::Error> + Unpin,
+{
+    tokio::time::timeout(SEND_TIMEOUT, sink.send(Message::Text(value.to_string())))
+        .await
+        .context("Client is not reading, disconnecting")??;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(route: &str, bm: u8, prm_id: u16) -> StreamItem {
+        StreamItem::Package(Box::new(FrameRecord {
+            crc: String::from("ok"),
+            route: Some(String::from(route)),
+            bm: Some(bm),
+            mcu: Some(1),
+            dev_id: Some(2),
+            prm_id: Some(prm_id),
+            ..Default::default()
+        }))
+    }
+
+    /// Следующее текстовое сообщение клиента в виде JSON
+    async fn next_json<S>(source: &mut S) -> serde_json::Value
+    where
+        S: futures_util::Stream<Item = Result<Message, tungstenite::Error>> + Unpin,
+    {
+        loop {
+            let message = tokio::time::timeout(Duration::from_secs(5), source.next()).await
+                .unwrap().unwrap().unwrap();
+            if let Message::Text(text) = message {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn filtered_stream_and_resubscribe() {
+        let hub = StreamHub::default();
+        let server = WsServer::bind("127.0.0.1:0", hub.clone(), 100).await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(server.run());
+
+        let url = format!("ws://{}/stream?route=tmonitor&bm=1&prm_id=10,11&rate=500", addr);
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        let subscribed = next_json(&mut socket).await;
+        assert_eq!(subscribed["type"], "subscribed");
+        assert_eq!(subscribed["rate"], 100);
+
+        // Подходит только последняя запись
+        hub.publish(record("SMonitor", 1, 10));
+        hub.publish(record("TMonitor", 2, 10));
+        hub.publish(record("TMonitor", 1, 12));
+        hub.publish(record("TMonitor", 1, 11));
+        let package = next_json(&mut socket).await;
+        assert_eq!(package["type"], "package");
+        assert_eq!(package["data"]["prm_id"], 11);
+
+        // Замена подписки: только события устройства DEV2
+        socket.send(Message::Text(String::from(r#"{"packages": false, "dev_id": 2}"#))).await.unwrap();
+        assert_eq!(next_json(&mut socket).await["subscription"]["packages"], false);
+        hub.publish(record("TMonitor", 1, 11));
+        hub.publish(StreamItem::Event(serde_json::json!({ "event": "stale", "module_addr": 9, "module_addr_bm": 1, "module_addr_mcu": 1, "dev_id": 3 })));
+        hub.publish(StreamItem::Event(serde_json::json!({ "event": "recovered", "module_addr": 9, "module_addr_bm": 1, "module_addr_mcu": 1, "dev_id": 2 })));
+        let event = next_json(&mut socket).await;
+        assert_eq!(event["data"]["event"], "recovered");
+
+        // JSON-подписка с именами параметров запроса: route, prm_id, rate
+        socket.send(Message::Text(String::from(r#"{"route": ["SMonitor"], "prm_id": [12], "rate": 20}"#))).await.unwrap();
+        let subscribed = next_json(&mut socket).await;
+        assert_eq!(subscribed["subscription"]["routes"], serde_json::json!(["SMonitor"]));
+        assert_eq!(subscribed["subscription"]["prm_ids"], serde_json::json!([12]));
+        assert_eq!(subscribed["rate"], 20);
+        hub.publish(record("TMonitor", 1, 12));
+        hub.publish(record("SMonitor", 1, 11));
+        hub.publish(record("SMonitor", 1, 12));
+        let package = next_json(&mut socket).await;
+        assert_eq!((&package["data"]["route"], &package["data"]["prm_id"]), (&serde_json::json!("SMonitor"), &serde_json::json!(12)));
+    }
+}
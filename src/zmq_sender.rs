@@ -1,6 +1,14 @@
 use anyhow::Result;
-use zmq;
 use std::marker;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{error, info, trace, warn};
+
+use crate::logging::RateLimiter;
+use crate::metrics::METRICS;
+
+/// Минимальный интервал между предупреждениями об ошибках отправки
+const SEND_WARN_INTERVAL: Duration = Duration::from_secs(5);
 
 /// Структура для отправки данных через ZeroMQ PUB socket
 /// Используется для передачи данных различным мониторам (TMonitor, SMonitor и др.)
@@ -8,6 +16,7 @@ pub struct ZmqSender {
     socket: zmq::Socket,               // ZeroMQ сокет
     endpoint: String,                  // Адрес конечной точки
     connected: bool,                   // Флаг подключения
+    error_limiter: Mutex<RateLimiter>, // Ограничитель предупреждений об ошибках отправки
     _marker: marker::PhantomData<*const ()>,  // Маркер для безопасности памяти
 }
 
@@ -25,13 +34,13 @@ impl ZmqSender {
         // Попытка привязать сокет к адресу
         let connected = match socket.bind(endpoint) {
             Ok(()) => {
-                println!("ZeroMQ PUB socket bound to: {}", endpoint);
+                info!(endpoint, "ZeroMQ PUB socket bound");
                 // Небольшая задержка для стабилизации соединения
                 std::thread::sleep(std::time::Duration::from_millis(100));
                 true
             }
             Err(e) => {
-                eprintln!("ZeroMQ bind error: {}", e);
+                error!(endpoint, "ZeroMQ bind error: {}", e);
                 false
             }
         };
@@ -40,11 +49,13 @@ impl ZmqSender {
             socket,
             endpoint: endpoint.to_string(),
             connected,
+            error_limiter: Mutex::new(RateLimiter::new(SEND_WARN_INTERVAL)),
             _marker: marker::PhantomData,
         }
     }
 
     /// Проверяет, подключен ли сокет
+    #[allow(dead_code)]
     pub fn is_connected(&self) -> bool {
         self.connected
     }
@@ -57,15 +68,15 @@ impl ZmqSender {
 
         match self.socket.send(data, 0) {
             Ok(()) => {
-                println!("ZeroMQ: Data sent, size: {}", data.len());
+                trace!(endpoint = %self.endpoint, size = data.len(), "ZeroMQ: Data sent");
                 Ok(())
             }
             Err(e) => {
-                eprintln!("ZeroMQ send error: {}", e);
-                
+                METRICS.zmq_send_errors.inc(&[&self.endpoint]);
+
                 // Попытка переподключения при определенных ошибках
                 if e.to_string().contains("ETERM") || e.to_string().contains("ENOTSOCK") {
-                    eprintln!("ZeroMQ socket invalid, need to recreate");
+                    error!(endpoint = %self.endpoint, "ZeroMQ socket invalid, need to recreate");
                 }
                 Err(anyhow::anyhow!("ZeroMQ send error: {}", e))
             }
@@ -74,12 +85,15 @@ impl ZmqSender {
 
     /// Отправляет пакет данных с дополнительным логированием
     pub fn send_package(&self, pkg: &[u8]) -> Result<()> {
-    println!("ZMQ: Attempting to send package, size: {}", pkg.len());
-    let result = self.send_data(pkg);
-    match &result {
-        Ok(_) => println!("ZMQ: Package sent successfully"),
-        Err(e) => eprintln!("ZMQ: Failed to send package: {}", e),
-    }
+        let result = self.send_data(pkg);
+        if let Err(e) = &result {
+            // Ограничиваем частоту повторяющихся ошибок отправки
+            let mut limiter = self.error_limiter.lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if let Some(suppressed) = limiter.check() {
+                warn!(endpoint = %self.endpoint, suppressed, "ZMQ: Failed to send package: {}", e);
+            }
+        }
         result
     }
 }